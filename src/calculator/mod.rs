@@ -1,32 +1,1168 @@
-use crate::cli::NumberInput;
+use crate::cli::{InvoiceInput, NumberInput, PairInput};
+use crate::exchange::Conversion;
+use crate::invoice::lines::ImportedLine;
+use crate::invoice::{EInvoice, EInvoiceLine, TaxSubtotal};
+use crate::rounding::{RoundingLevel, RoundingMode, RoundingRule, round_to_precision};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TaxTreatment {
+    #[default]
+    Standard,
+    Exempt,
+    ReverseCharge,
+    OutOfScope,
+    Tip,
+}
+
+impl TaxTreatment {
+    pub fn parse(input: &str) -> Option<TaxTreatment> {
+        match input.to_lowercase().as_str() {
+            "standard" | "std" => Some(TaxTreatment::Standard),
+            "exempt" | "ex" => Some(TaxTreatment::Exempt),
+            "reverse-charge" | "rc" => Some(TaxTreatment::ReverseCharge),
+            "out-of-scope" | "oos" => Some(TaxTreatment::OutOfScope),
+            "tip" | "gratuity" => Some(TaxTreatment::Tip),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            TaxTreatment::Standard => "standard",
+            TaxTreatment::Exempt => "exempt",
+            TaxTreatment::ReverseCharge => "reverse-charge",
+            TaxTreatment::OutOfScope => "out-of-scope",
+            TaxTreatment::Tip => "tip",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TaxTreatment::Standard => "standard",
+            TaxTreatment::Exempt => "tax-exempt (§4 UStG)",
+            TaxTreatment::ReverseCharge => "reverse charge (§13b UStG)",
+            TaxTreatment::OutOfScope => "out of scope",
+            TaxTreatment::Tip => "tip (not subject to VAT)",
+        }
+    }
+
+    // UNTDID 5305 VAT category codes as used by EN 16931
+    pub fn from_category(code: &str) -> TaxTreatment {
+        match code {
+            "E" => TaxTreatment::Exempt,
+            "AE" => TaxTreatment::ReverseCharge,
+            "O" => TaxTreatment::OutOfScope,
+            _ => TaxTreatment::Standard,
+        }
+    }
+
+    // The UNTDID 5305 category an amount with this treatment is invoiced under
+    pub fn category(self, rate: f64) -> &'static str {
+        match self {
+            TaxTreatment::Standard if rate == 0.0 => "Z",
+            TaxTreatment::Standard => "S",
+            TaxTreatment::Exempt => "E",
+            TaxTreatment::ReverseCharge => "AE",
+            TaxTreatment::OutOfScope | TaxTreatment::Tip => "O",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxComponent {
+    pub name: String,
+    pub rate: f64,
+    pub compound: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdjustmentBase {
+    BeforeVat,
+    AfterVat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdjustmentLevel {
+    Line,
+    Total,
+}
+
+// A discount is an adjustment with a negative value, a surcharge one with a
+// positive value. Percentages are given in percent, e.g. -3.0 for 3% Skonto.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustment {
+    pub value: f64,
+    pub is_percentage: bool,
+    pub base: AdjustmentBase,
+    pub level: AdjustmentLevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdjustedAmounts {
+    pub with_vat: f64,
+    pub without_vat: f64,
+    pub vat_correction: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertedAmounts {
+    pub currency: String,
+    pub without_vat: f64,
+    pub vat: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PricingTarget {
+    Markup(f64),
+    Margin(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricingResult {
+    pub cost: f64,
+    pub net_price: f64,
+    pub gross_price: f64,
+    pub margin: f64,
+    pub vat: f64,
+    pub uses_comma: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MarginSchemeResult {
+    pub purchase: f64,
+    pub selling: f64,
+    pub margin: f64,
+    pub vat: f64,
+    pub uses_comma: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReceiptTotal {
+    pub gross: f64,
+    pub net: f64,
+    pub vat: f64,
+    pub tip: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateSolution {
+    pub gross: f64,
+    pub net: f64,
+    pub implied_rate: Option<f64>,
+    pub matched_rate: Option<f64>,
+    pub uses_comma: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationResult {
+    pub gross: f64,
+    pub net: f64,
+    pub vat: f64,
+    pub rate: f64,
+    pub expected_net: f64,
+    pub expected_vat: f64,
+    pub passed: bool,
+    pub uses_comma: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Share {
+    pub name: String,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SplitPart {
+    pub gross: f64,
+    pub net: f64,
+    pub vat: f64,
+    pub uses_comma: bool,
+}
+
+// An invoice line of `count` units at `unit_price` gross each
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantity {
+    pub count: f64,
+    pub unit_price: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtotalCheck {
+    pub category: String,
+    pub rate: f64,
+    pub basis: f64,
+    pub vat: f64,
+    pub expected_basis: f64,
+    pub expected_vat: f64,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EInvoiceCheck {
+    pub subtotals: Vec<SubtotalCheck>,
+    pub declared: SplitPart,
+    pub expected: SplitPart,
+    pub passed: bool,
+}
+
+// Standard, reduced and zero rate under §12 UStG
+pub const GERMAN_VAT_RATES: [f64; 3] = [19.0, 7.0, 0.0];
+
+#[derive(Default, Clone)]
 pub struct CalculationResult {
     pub with_vat: f64,
     pub without_vat: f64,
     pub uses_comma: bool,
+    pub treatment: TaxTreatment,
+    pub self_assessed_vat: f64,
+    pub component_taxes: Vec<f64>,
+    pub adjusted: Option<AdjustedAmounts>,
+    pub converted: Option<ConvertedAmounts>,
+    pub quantity: Option<Quantity>,
+    pub label: Option<String>,
+}
+
+impl CalculationResult {
+    // Gross and net after line discounts and surcharges
+    pub fn adjusted_amounts(&self) -> (f64, f64) {
+        self.adjusted
+            .map_or((self.with_vat, self.without_vat), |adjusted| {
+                (adjusted.with_vat, adjusted.without_vat)
+            })
+    }
 }
 
 pub fn calculate_without_vat(amount: f64, vat_rate: f64) -> f64 {
     amount / (1.0 + vat_rate / 100.0)
 }
 
-pub fn process_numbers(numbers: &[NumberInput], vat_rate: f64) -> Vec<CalculationResult> {
+pub fn calculate_self_assessed_vat(net: f64, vat_rate: f64) -> f64 {
+    net * vat_rate / 100.0
+}
+
+// Simple components are levied on the net amount only, compound components on
+// the net plus every component listed before them (e.g. Quebec QST before 2013).
+pub fn calculate_component_taxes(net: f64, components: &[TaxComponent]) -> Vec<f64> {
+    let mut base = net;
+    components
+        .iter()
+        .map(|component| {
+            let basis = if component.compound { base } else { net };
+            let tax = basis * component.rate / 100.0;
+            base += tax;
+            tax
+        })
+        .collect()
+}
+
+pub fn calculate_without_components(amount: f64, components: &[TaxComponent]) -> f64 {
+    let factor = 1.0
+        + calculate_component_taxes(1.0, components)
+            .iter()
+            .sum::<f64>();
+    amount / factor
+}
+
+// Line level rounds the line total once and calculates on it; unit level
+// calculates on the rounded unit price and multiplies the rounded results.
+fn calculate_line(
+    input: &NumberInput,
+    precision: usize,
+    mode: RoundingMode,
+    level: RoundingLevel,
+    calculate: impl Fn(f64) -> CalculationResult,
+) -> CalculationResult {
+    let Some(quantity) = input.quantity else {
+        return calculate(input.value);
+    };
+    let round = |value: f64| round_to_precision(value, precision, mode);
+    let result = match level {
+        RoundingLevel::Line => calculate(round(quantity.count * quantity.unit_price)),
+        RoundingLevel::Unit => {
+            let unit = calculate(round(quantity.unit_price));
+            CalculationResult {
+                with_vat: round(unit.with_vat * quantity.count),
+                without_vat: round(unit.without_vat * quantity.count),
+                self_assessed_vat: round(unit.self_assessed_vat * quantity.count),
+                component_taxes: unit
+                    .component_taxes
+                    .iter()
+                    .map(|tax| round(tax * quantity.count))
+                    .collect(),
+                ..unit
+            }
+        }
+    };
+    CalculationResult {
+        quantity: Some(quantity),
+        ..result
+    }
+}
+
+pub fn process_numbers(
+    numbers: &[NumberInput],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+    level: RoundingLevel,
+) -> Vec<CalculationResult> {
+    numbers
+        .iter()
+        .map(|input| {
+            calculate_line(input, precision, mode, level, |gross| {
+                // Only standard-rated amounts contain VAT; for the other
+                // treatments the gross already equals the net.
+                let (without_vat, self_assessed_vat) = match input.treatment {
+                    TaxTreatment::Standard => (calculate_without_vat(gross, vat_rate), 0.0),
+                    TaxTreatment::ReverseCharge => {
+                        (gross, calculate_self_assessed_vat(gross, vat_rate))
+                    }
+                    TaxTreatment::Exempt | TaxTreatment::OutOfScope | TaxTreatment::Tip => {
+                        (gross, 0.0)
+                    }
+                };
+                CalculationResult {
+                    with_vat: gross,
+                    without_vat: round_to_precision(without_vat, precision, mode),
+                    uses_comma: input.uses_comma,
+                    treatment: input.treatment,
+                    self_assessed_vat: round_to_precision(self_assessed_vat, precision, mode),
+                    component_taxes: Vec::new(),
+                    adjusted: None,
+                    converted: None,
+                    quantity: None,
+                    label: input.label.clone(),
+                }
+            })
+        })
+        .collect()
+}
+
+// Imported lines carry their own rate; lines without one use the default
+pub fn process_lines(
+    lines: &[ImportedLine],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+    level: RoundingLevel,
+) -> Vec<CalculationResult> {
+    lines
+        .iter()
+        .flat_map(|line| {
+            process_numbers(
+                std::slice::from_ref(&line.input),
+                line.rate.unwrap_or(vat_rate),
+                precision,
+                mode,
+                level,
+            )
+        })
+        .collect()
+}
+
+pub fn process_numbers_with_components(
+    numbers: &[NumberInput],
+    components: &[TaxComponent],
+    precision: usize,
+    mode: RoundingMode,
+    level: RoundingLevel,
+) -> Vec<CalculationResult> {
     numbers
         .iter()
         .map(|input| {
-            let without_vat = calculate_without_vat(input.value, vat_rate);
+            calculate_line(input, precision, mode, level, |gross| {
+                let (without_vat, component_taxes, self_assessed_vat) = match input.treatment {
+                    // Each component is rounded on its own and the net takes the
+                    // remainder, so gross = net + taxes holds to the minor unit.
+                    TaxTreatment::Standard => {
+                        let net = calculate_without_components(gross, components);
+                        let taxes: Vec<f64> = calculate_component_taxes(net, components)
+                            .into_iter()
+                            .map(|tax| round_to_precision(tax, precision, mode))
+                            .collect();
+                        (gross - taxes.iter().sum::<f64>(), taxes, 0.0)
+                    }
+                    TaxTreatment::ReverseCharge => {
+                        let owed = calculate_component_taxes(gross, components);
+                        (gross, vec![0.0; components.len()], owed.iter().sum())
+                    }
+                    TaxTreatment::Exempt | TaxTreatment::OutOfScope | TaxTreatment::Tip => {
+                        (gross, vec![0.0; components.len()], 0.0)
+                    }
+                };
+                CalculationResult {
+                    with_vat: gross,
+                    without_vat: round_to_precision(without_vat, precision, mode),
+                    uses_comma: input.uses_comma,
+                    treatment: input.treatment,
+                    self_assessed_vat: round_to_precision(self_assessed_vat, precision, mode),
+                    component_taxes,
+                    adjusted: None,
+                    converted: None,
+                    quantity: None,
+                    label: input.label.clone(),
+                }
+            })
+        })
+        .collect()
+}
+
+// The gross/net ratio of the original amount is kept, so the VAT share of a
+// discount is corrected as required for Skonto (§17 UStG), also for stacked
+// taxes and for lines without VAT.
+pub fn apply_adjustment(
+    with_vat: f64,
+    without_vat: f64,
+    adjustment: &Adjustment,
+) -> AdjustedAmounts {
+    let ratio = if without_vat == 0.0 {
+        1.0
+    } else {
+        with_vat / without_vat
+    };
+    let adjust = |amount: f64| {
+        if adjustment.is_percentage {
+            amount * (1.0 + adjustment.value / 100.0)
+        } else {
+            amount + adjustment.value
+        }
+    };
+    let (adjusted_with, adjusted_without) = match adjustment.base {
+        AdjustmentBase::BeforeVat => {
+            let net = adjust(without_vat);
+            (net * ratio, net)
+        }
+        AdjustmentBase::AfterVat => {
+            let gross = adjust(with_vat);
+            (gross, gross / ratio)
+        }
+    };
+
+    AdjustedAmounts {
+        with_vat: adjusted_with,
+        without_vat: adjusted_without,
+        vat_correction: (adjusted_with - adjusted_without) - (with_vat - without_vat),
+    }
+}
+
+fn apply_adjustments<'a>(
+    with_vat: f64,
+    without_vat: f64,
+    adjustments: impl Iterator<Item = &'a Adjustment>,
+) -> Option<AdjustedAmounts> {
+    let mut adjusted: Option<AdjustedAmounts> = None;
+    for adjustment in adjustments {
+        let (current_with, current_without) = adjusted
+            .map(|a| (a.with_vat, a.without_vat))
+            .unwrap_or((with_vat, without_vat));
+        let next = apply_adjustment(current_with, current_without, adjustment);
+        adjusted = Some(AdjustedAmounts {
+            with_vat: next.with_vat,
+            without_vat: next.without_vat,
+            vat_correction: (next.with_vat - next.without_vat) - (with_vat - without_vat),
+        });
+    }
+    adjusted
+}
+
+// A tip is paid on top of the bill, so discounts and surcharges leave it
+// alone. Reverse-charge VAT follows the adjusted net.
+pub fn apply_line_adjustments(results: &mut [CalculationResult], adjustments: &[Adjustment]) {
+    for result in results
+        .iter_mut()
+        .filter(|result| result.treatment != TaxTreatment::Tip)
+    {
+        result.adjusted = apply_adjustments(
+            result.with_vat,
+            result.without_vat,
+            adjustments
+                .iter()
+                .filter(|a| a.level == AdjustmentLevel::Line),
+        );
+        if let Some(adjusted) = result.adjusted
+            && result.treatment == TaxTreatment::ReverseCharge
+            && result.without_vat != 0.0
+        {
+            result.self_assessed_vat *= adjusted.without_vat / result.without_vat;
+        }
+    }
+}
+
+pub fn calculate_total_adjustment(
+    results: &[CalculationResult],
+    adjustments: &[Adjustment],
+) -> Option<AdjustedAmounts> {
+    let (bill, tips): (Vec<_>, Vec<_>) = results
+        .iter()
+        .partition(|r| r.treatment != TaxTreatment::Tip);
+    let (with_vat, without_vat) = bill.iter().fold((0.0, 0.0), |(with, without), r| {
+        let (w, wo) = r
+            .adjusted
+            .map(|a| (a.with_vat, a.without_vat))
+            .unwrap_or((r.with_vat, r.without_vat));
+        (with + w, without + wo)
+    });
+    let tip: f64 = tips.iter().map(|r| r.with_vat).sum();
+
+    apply_adjustments(
+        with_vat,
+        without_vat,
+        adjustments
+            .iter()
+            .filter(|a| a.level == AdjustmentLevel::Total),
+    )
+    .map(|adjusted| AdjustedAmounts {
+        with_vat: adjusted.with_vat + tip,
+        without_vat: adjusted.without_vat + tip,
+        ..adjusted
+    })
+}
+
+// Receipt totals for expense reports, only for receipts with a tip: the tip
+// is part of the amount paid but carries no VAT.
+pub fn calculate_receipt_total(results: &[CalculationResult]) -> Option<ReceiptTotal> {
+    if results.iter().all(|r| r.treatment != TaxTreatment::Tip) {
+        return None;
+    }
+
+    Some(results.iter().fold(ReceiptTotal::default(), |total, r| {
+        let (with_vat, without_vat) = r
+            .adjusted
+            .map(|a| (a.with_vat, a.without_vat))
+            .unwrap_or((r.with_vat, r.without_vat));
+        if r.treatment == TaxTreatment::Tip {
+            ReceiptTotal {
+                gross: total.gross + with_vat,
+                tip: total.tip + with_vat,
+                ..total
+            }
+        } else {
+            ReceiptTotal {
+                gross: total.gross + with_vat,
+                net: total.net + without_vat,
+                vat: total.vat + with_vat - without_vat,
+                ..total
+            }
+        }
+    }))
+}
+
+// The share of a gross amount that is left after cash rounding, to scale what
+// is derived from it
+fn cash_rounding_ratio(with_vat: f64, rounded: f64) -> f64 {
+    if with_vat == 0.0 {
+        1.0
+    } else {
+        rounded / with_vat
+    }
+}
+
+pub fn round_adjusted_amounts(
+    amounts: AdjustedAmounts,
+    rule: &RoundingRule,
+    precision: usize,
+) -> AdjustedAmounts {
+    let with_vat = rule.round(amounts.with_vat);
+    let ratio = cash_rounding_ratio(amounts.with_vat, with_vat);
+    let without_vat = round_to_precision(amounts.without_vat * ratio, precision, rule.mode);
+    let vat_change = (with_vat - without_vat) - (amounts.with_vat - amounts.without_vat);
+    AdjustedAmounts {
+        with_vat,
+        without_vat,
+        vat_correction: round_to_precision(
+            amounts.vat_correction + vat_change,
+            precision,
+            rule.mode,
+        ),
+    }
+}
+
+// Cash rounding applies to the amount paid: the gross is rounded to the
+// rule's increment and the net and taxes are calculated from it again
+pub fn round_results(
+    results: &mut [CalculationResult],
+    rule: &RoundingRule,
+    vat_rate: f64,
+    components: &[TaxComponent],
+    precision: usize,
+) {
+    for result in results {
+        let input = [NumberInput {
+            value: rule.round(result.with_vat),
+            uses_comma: result.uses_comma,
+            treatment: result.treatment,
+            quantity: None,
+            label: None,
+        }];
+        let rounded = if components.is_empty() {
+            process_numbers(&input, vat_rate, precision, rule.mode, RoundingLevel::Line)
+        } else {
+            process_numbers_with_components(
+                &input,
+                components,
+                precision,
+                rule.mode,
+                RoundingLevel::Line,
+            )
+        };
+        if let Some(rounded) = rounded.into_iter().next() {
+            result.with_vat = rounded.with_vat;
+            result.without_vat = rounded.without_vat;
+            result.self_assessed_vat = rounded.self_assessed_vat;
+            result.component_taxes = rounded.component_taxes;
+        }
+        result.adjusted = result
+            .adjusted
+            .map(|adjusted| round_adjusted_amounts(adjusted, rule, precision));
+    }
+}
+
+pub fn convert_results(
+    results: &mut [CalculationResult],
+    conversion: &Conversion,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    for result in results {
+        // Converted next to the amounts shown, after discounts and surcharges
+        let (gross, net) = result.adjusted_amounts();
+        let without_vat = round_to_precision(net * conversion.rate, precision, mode);
+        let vat = round_to_precision((gross - net) * conversion.rate, precision, mode);
+        result.converted = Some(ConvertedAmounts {
+            currency: conversion.to.clone(),
+            without_vat,
+            vat,
+        });
+    }
+}
+
+// The amounts a journal books: line discounts and surcharges applied and,
+// with a conversion, in the target currency
+pub fn booked_results(
+    results: &[CalculationResult],
+    conversion: Option<&Conversion>,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<CalculationResult> {
+    let rate = conversion.map_or(1.0, |conversion| conversion.rate);
+    let round = |value: f64| round_to_precision(value, precision, mode);
+    results
+        .iter()
+        .map(|result| {
+            let (with_vat, without_vat) = result.adjusted_amounts();
+            let scale = if result.without_vat != 0.0 {
+                rate * without_vat / result.without_vat
+            } else {
+                rate
+            };
+            let net = round(without_vat * rate);
             CalculationResult {
-                with_vat: input.value,
-                without_vat,
+                with_vat: net + round((with_vat - without_vat) * rate),
+                without_vat: net,
+                self_assessed_vat: round(result.self_assessed_vat * rate),
+                component_taxes: result
+                    .component_taxes
+                    .iter()
+                    .map(|tax| round(tax * scale))
+                    .collect(),
+                adjusted: None,
+                converted: None,
+                ..result.clone()
+            }
+        })
+        .collect()
+}
+
+// Markup is the profit relative to the cost, margin the profit relative to
+// the net selling price.
+pub fn calculate_net_price(cost: f64, target: PricingTarget) -> f64 {
+    match target {
+        PricingTarget::Markup(markup) => cost * (1.0 + markup / 100.0),
+        PricingTarget::Margin(margin) => cost / (1.0 - margin / 100.0),
+    }
+}
+
+pub fn process_pricing(
+    costs: &[NumberInput],
+    target: PricingTarget,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<PricingResult> {
+    costs
+        .iter()
+        .map(|input| {
+            let net_price =
+                round_to_precision(calculate_net_price(input.value, target), precision, mode);
+            let gross_price =
+                round_to_precision(net_price * (1.0 + vat_rate / 100.0), precision, mode);
+            PricingResult {
+                cost: input.value,
+                net_price,
+                gross_price,
+                margin: net_price - input.value,
+                vat: gross_price - net_price,
                 uses_comma: input.uses_comma,
             }
         })
         .collect()
 }
 
+// Under the margin scheme (§25a UStG) only the difference between selling and
+// purchase price contains VAT. A loss on one item leaves no VAT due and is not
+// offset against other items.
+pub fn process_margin_scheme(
+    pairs: &[PairInput],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<MarginSchemeResult> {
+    pairs
+        .iter()
+        .map(|pair| {
+            let margin = pair.second - pair.first;
+            let vat = if margin > 0.0 {
+                round_to_precision(
+                    margin - calculate_without_vat(margin, vat_rate),
+                    precision,
+                    mode,
+                )
+            } else {
+                0.0
+            };
+            MarginSchemeResult {
+                purchase: pair.first,
+                selling: pair.second,
+                margin,
+                vat,
+                uses_comma: pair.uses_comma,
+            }
+        })
+        .collect()
+}
+
+pub fn total_margin_scheme(results: &[MarginSchemeResult]) -> MarginSchemeResult {
+    results
+        .iter()
+        .fold(MarginSchemeResult::default(), |total, result| {
+            MarginSchemeResult {
+                purchase: total.purchase + result.purchase,
+                selling: total.selling + result.selling,
+                margin: total.margin + result.margin,
+                vat: total.vat + result.vat,
+                uses_comma: total.uses_comma || result.uses_comma,
+            }
+        })
+}
+
+// Credit notes have a negative gross and net and the same rate
+pub fn calculate_implied_rate(gross: f64, net: f64) -> Option<f64> {
+    (net != 0.0).then(|| (gross / net - 1.0) * 100.0)
+}
+
+// A known rate matches if the implied rate lies within the tolerance, or if
+// it reproduces the gross amount after rounding: on small amounts the cent
+// rounding alone can move the implied rate by whole percentage points.
+pub fn match_known_rate(
+    gross: f64,
+    net: f64,
+    known_rates: &[f64],
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Option<f64> {
+    let implied_rate = calculate_implied_rate(gross, net)?;
+    let half_unit = 0.5 / 10f64.powi(precision as i32);
+    let mut candidates = known_rates.to_vec();
+    candidates.sort_by(|a, b| {
+        (a - implied_rate)
+            .abs()
+            .total_cmp(&(b - implied_rate).abs())
+    });
+    candidates.into_iter().find(|rate| {
+        let expected = round_to_precision(net * (1.0 + rate / 100.0), precision, mode);
+        (rate - implied_rate).abs() <= tolerance || (expected - gross).abs() < half_unit
+    })
+}
+
+pub fn solve_rates(
+    pairs: &[PairInput],
+    known_rates: &[f64],
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<RateSolution> {
+    pairs
+        .iter()
+        .map(|pair| RateSolution {
+            gross: pair.first,
+            net: pair.second,
+            implied_rate: calculate_implied_rate(pair.first, pair.second),
+            matched_rate: match_known_rate(
+                pair.first,
+                pair.second,
+                known_rates,
+                tolerance,
+                precision,
+                mode,
+            ),
+            uses_comma: pair.uses_comma,
+        })
+        .collect()
+}
+
+// Net and VAT are recomputed from the stated gross; an invoice passes if both
+// stated amounts lie within the tolerance of the recomputed ones.
+pub fn verify_invoices(
+    invoices: &[InvoiceInput],
+    vat_rate: f64,
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<VerificationResult> {
+    // Absorbs binary float noise such as 119.01 - 119.00 = 0.010000000000005116
+    const EPSILON: f64 = 1e-9;
+
+    invoices
+        .iter()
+        .map(|invoice| {
+            let rate = invoice.rate.unwrap_or(vat_rate);
+            let expected_net =
+                round_to_precision(calculate_without_vat(invoice.gross, rate), precision, mode);
+            let expected_vat = invoice.gross - expected_net;
+            VerificationResult {
+                gross: invoice.gross,
+                net: invoice.net,
+                vat: invoice.vat,
+                rate,
+                expected_net,
+                expected_vat,
+                passed: (invoice.net - expected_net).abs() <= tolerance + EPSILON
+                    && (invoice.vat - expected_vat).abs() <= tolerance + EPSILON,
+                uses_comma: invoice.uses_comma,
+            }
+        })
+        .collect()
+}
+
+// Largest-remainder method: each share gets its truncated amount in minor
+// units and the leftover units go to the largest remainders, earlier shares
+// winning ties, so the shares always add up to the total exactly.
+pub fn allocate_largest_remainder(total: f64, weights: &[f64], precision: usize) -> Vec<f64> {
+    let scale = 10f64.powi(precision as i32);
+    let units = (total * scale).round() as i64;
+    let weight_sum: f64 = weights.iter().sum();
+    let exact: Vec<f64> = weights
+        .iter()
+        .map(|weight| {
+            if weight_sum == 0.0 {
+                units as f64 / weights.len() as f64
+            } else {
+                units as f64 * weight / weight_sum
+            }
+        })
+        .collect();
+
+    let mut shares: Vec<i64> = exact.iter().map(|share| share.floor() as i64).collect();
+    let leftover = units - shares.iter().sum::<i64>();
+    let mut order: Vec<usize> = (0..exact.len()).collect();
+    order.sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+    for &index in order.iter().take(leftover.max(0) as usize) {
+        shares[index] += 1;
+    }
+
+    shares
+        .into_iter()
+        .map(|share| share as f64 / scale)
+        .collect()
+}
+
+// The gross is split by weight first; the net total is then allocated in
+// proportion to those gross parts and each part's VAT is the difference, so
+// gross, net and VAT all sum exactly to the totals.
+pub fn split_gross(
+    input: &NumberInput,
+    weights: &[f64],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<SplitPart> {
+    let net_total = round_to_precision(
+        calculate_without_vat(input.value, vat_rate),
+        precision,
+        mode,
+    );
+    let gross_parts = allocate_largest_remainder(input.value, weights, precision);
+    let net_parts = allocate_largest_remainder(net_total, &gross_parts, precision);
+
+    gross_parts
+        .into_iter()
+        .zip(net_parts)
+        .map(|(gross, net)| SplitPart {
+            gross,
+            net,
+            vat: gross - net,
+            uses_comma: input.uses_comma,
+        })
+        .collect()
+}
+
+pub fn split_installments(
+    input: &NumberInput,
+    count: usize,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<SplitPart> {
+    split_gross(input, &vec![1.0; count], vat_rate, precision, mode)
+}
+
+pub fn allocate_shares(
+    input: &NumberInput,
+    shares: &[Share],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<SplitPart> {
+    let weights: Vec<f64> = shares.iter().map(|share| share.weight).collect();
+    split_gross(input, &weights, vat_rate, precision, mode)
+}
+
+pub fn total_split_parts(parts: &[SplitPart]) -> SplitPart {
+    parts
+        .iter()
+        .fold(SplitPart::default(), |total, part| SplitPart {
+            gross: total.gross + part.gross,
+            net: total.net + part.net,
+            vat: total.vat + part.vat,
+            uses_comma: total.uses_comma || part.uses_comma,
+        })
+}
+
+pub fn total_results(results: &[CalculationResult]) -> SplitPart {
+    total_split_parts(
+        &results
+            .iter()
+            .map(|result| SplitPart {
+                gross: result.with_vat,
+                net: result.without_vat,
+                vat: result.with_vat - result.without_vat,
+                uses_comma: result.uses_comma,
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+// E-invoice lines are net. The VAT shown per line is informational, the
+// binding amount is calculated per category and rate in `verify_einvoice`.
+// Each line is calculated at its own rate; reverse-charge lines usually
+// declare 0%, their VAT is then self-assessed at `vat_rate`.
+pub fn process_einvoice_lines(
+    invoice: &EInvoice,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<CalculationResult> {
+    invoice
+        .lines
+        .iter()
+        .map(|line| {
+            let treatment = TaxTreatment::from_category(&line.category);
+            let rate = match treatment {
+                TaxTreatment::ReverseCharge if line.rate == 0.0 => vat_rate,
+                _ => line.rate,
+            };
+            let gross = match treatment {
+                TaxTreatment::Standard => {
+                    line.net + round_to_precision(line.net * rate / 100.0, precision, mode)
+                }
+                _ => line.net,
+            };
+            let input = NumberInput {
+                value: gross,
+                uses_comma: false,
+                treatment,
+                quantity: (line.quantity != 0.0).then(|| Quantity {
+                    count: line.quantity,
+                    unit_price: gross / line.quantity,
+                }),
+                label: line.label.clone(),
+            };
+            let mut results = process_numbers(&[input], rate, precision, mode, RoundingLevel::Line);
+            results.remove(0)
+        })
+        .collect()
+}
+
+// EN 16931 calculates VAT once per category and rate on the sum of the net
+// amounts in it (BR-CO-17), in the order the categories first appear
+pub fn calculate_tax_subtotals(
+    lines: &[EInvoiceLine],
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<TaxSubtotal> {
+    let mut subtotals: Vec<TaxSubtotal> = Vec::new();
+    for line in lines {
+        match subtotals
+            .iter_mut()
+            .find(|subtotal| subtotal.category == line.category && subtotal.rate == line.rate)
+        {
+            Some(subtotal) => subtotal.basis += line.net,
+            None => subtotals.push(TaxSubtotal {
+                category: line.category.clone(),
+                rate: line.rate,
+                basis: line.net,
+                vat: 0.0,
+            }),
+        }
+    }
+    for subtotal in &mut subtotals {
+        subtotal.basis = round_to_precision(subtotal.basis, precision, mode);
+        subtotal.vat = round_to_precision(subtotal.basis * subtotal.rate / 100.0, precision, mode);
+    }
+    subtotals
+}
+
+// Builds an invoice from calculated amounts, one rate per result. Only the
+// net amounts are kept; the VAT is calculated per category and rate.
+pub fn build_einvoice(
+    results: &[CalculationResult],
+    rates: &[f64],
+    number: &str,
+    currency: &str,
+    precision: usize,
+    mode: RoundingMode,
+) -> EInvoice {
+    let lines: Vec<EInvoiceLine> = results
+        .iter()
+        .zip(rates)
+        .map(|(result, &rate)| {
+            let category = result.treatment.category(rate);
+            EInvoiceLine {
+                label: result.label.clone(),
+                quantity: result.quantity.map_or(1.0, |quantity| quantity.count),
+                net: result.without_vat,
+                category: category.to_string(),
+                rate: if category == "S" { rate } else { 0.0 },
+            }
+        })
+        .collect();
+    let subtotals = calculate_tax_subtotals(&lines, precision, mode);
+    let net_total = round_to_precision(
+        subtotals.iter().map(|subtotal| subtotal.basis).sum(),
+        precision,
+        mode,
+    );
+    let vat_total = round_to_precision(
+        subtotals.iter().map(|subtotal| subtotal.vat).sum(),
+        precision,
+        mode,
+    );
+
+    EInvoice {
+        number: number.to_string(),
+        currency: currency.to_uppercase(),
+        lines,
+        subtotals,
+        net_total,
+        vat_total,
+        gross_total: round_to_precision(net_total + vat_total, precision, mode),
+    }
+}
+
+fn find_subtotal<'a>(
+    subtotals: &'a [TaxSubtotal],
+    category: &str,
+    rate: f64,
+) -> Option<&'a TaxSubtotal> {
+    subtotals
+        .iter()
+        .find(|subtotal| subtotal.category == category && subtotal.rate == rate)
+}
+
+// Every declared subtotal is compared with the one recomputed from the lines.
+// Categories found only in the lines fail as undeclared.
+pub fn verify_einvoice(
+    invoice: &EInvoice,
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> EInvoiceCheck {
+    // Absorbs binary float noise, as in `verify_invoices`
+    const EPSILON: f64 = 1e-9;
+    let within = |stated: f64, expected: f64| (stated - expected).abs() <= tolerance + EPSILON;
+    let round = |value: f64| round_to_precision(value, precision, mode);
+
+    let recomputed = calculate_tax_subtotals(&invoice.lines, precision, mode);
+    let mut groups: Vec<(&str, f64)> = invoice
+        .subtotals
+        .iter()
+        .map(|subtotal| (subtotal.category.as_str(), subtotal.rate))
+        .collect();
+    for subtotal in &recomputed {
+        if !groups.contains(&(subtotal.category.as_str(), subtotal.rate)) {
+            groups.push((subtotal.category.as_str(), subtotal.rate));
+        }
+    }
+
+    let subtotals: Vec<SubtotalCheck> = groups
+        .into_iter()
+        .map(|(category, rate)| {
+            let declared = find_subtotal(&invoice.subtotals, category, rate);
+            let (expected_basis, expected_vat) = find_subtotal(&recomputed, category, rate)
+                .map_or((0.0, 0.0), |subtotal| (subtotal.basis, subtotal.vat));
+            let (basis, vat) =
+                declared.map_or((0.0, 0.0), |subtotal| (subtotal.basis, subtotal.vat));
+            SubtotalCheck {
+                category: category.to_string(),
+                rate,
+                basis,
+                vat,
+                expected_basis,
+                expected_vat,
+                passed: declared.is_some()
+                    && within(basis, expected_basis)
+                    && within(vat, expected_vat),
+            }
+        })
+        .collect();
+
+    let expected_net = round(
+        subtotals
+            .iter()
+            .map(|subtotal| subtotal.expected_basis)
+            .sum(),
+    );
+    let expected_vat = round(subtotals.iter().map(|subtotal| subtotal.expected_vat).sum());
+    let expected = SplitPart {
+        gross: round(expected_net + expected_vat),
+        net: expected_net,
+        vat: expected_vat,
+        uses_comma: false,
+    };
+    let declared = SplitPart {
+        gross: invoice.gross_total,
+        net: invoice.net_total,
+        vat: invoice.vat_total,
+        uses_comma: false,
+    };
+    let passed = subtotals.iter().all(|subtotal| subtotal.passed)
+        && within(declared.gross, expected.gross)
+        && within(declared.net, expected.net)
+        && within(declared.vat, expected.vat);
+
+    EInvoiceCheck {
+        subtotals,
+        declared,
+        expected,
+        passed,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::invoice::{EInvoiceLine, TaxSubtotal};
 
     #[test]
     fn test_calculate_without_vat_19_percent() {
@@ -96,7 +1232,7 @@ mod tests {
     #[test]
     fn test_process_numbers_empty() {
         let numbers = vec![];
-        let results = process_numbers(&numbers, 19.0);
+        let results = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
         assert_eq!(results.len(), 0);
     }
 
@@ -105,8 +1241,11 @@ mod tests {
         let numbers = vec![NumberInput {
             value: 119.0,
             uses_comma: false,
+            treatment: TaxTreatment::Standard,
+            quantity: None,
+            label: None,
         }];
-        let results = process_numbers(&numbers, 19.0);
+        let results = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].with_vat, 119.0);
         assert!((results[0].without_vat - 100.0).abs() < 0.01);
@@ -119,13 +1258,19 @@ mod tests {
             NumberInput {
                 value: 119.0,
                 uses_comma: true,
+                treatment: TaxTreatment::Standard,
+                quantity: None,
+                label: None,
             },
             NumberInput {
                 value: 238.0,
                 uses_comma: false,
+                treatment: TaxTreatment::Standard,
+                quantity: None,
+                label: None,
             },
         ];
-        let results = process_numbers(&numbers, 19.0);
+        let results = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
         assert_eq!(results.len(), 2);
         assert!(results[0].uses_comma);
         assert!(!results[1].uses_comma);
@@ -137,9 +1282,1094 @@ mod tests {
             with_vat: 119.0,
             without_vat: 100.0,
             uses_comma: true,
+            ..Default::default()
         };
         assert_eq!(result.with_vat, 119.0);
         assert_eq!(result.without_vat, 100.0);
         assert!(result.uses_comma);
     }
+
+    #[test]
+    fn test_tax_treatment_parse() {
+        assert_eq!(TaxTreatment::parse("rc"), Some(TaxTreatment::ReverseCharge));
+        assert_eq!(
+            TaxTreatment::parse("Reverse-Charge"),
+            Some(TaxTreatment::ReverseCharge)
+        );
+        assert_eq!(TaxTreatment::parse("exempt"), Some(TaxTreatment::Exempt));
+        assert_eq!(TaxTreatment::parse("oos"), Some(TaxTreatment::OutOfScope));
+        assert_eq!(TaxTreatment::parse("std"), Some(TaxTreatment::Standard));
+        assert_eq!(TaxTreatment::parse("tip"), Some(TaxTreatment::Tip));
+        assert_eq!(TaxTreatment::parse("gratuity"), Some(TaxTreatment::Tip));
+        assert_eq!(TaxTreatment::parse("zero"), None);
+    }
+
+    #[test]
+    fn test_process_numbers_treatments() {
+        let numbers = vec![
+            NumberInput {
+                value: 100.0,
+                uses_comma: false,
+                treatment: TaxTreatment::ReverseCharge,
+                quantity: None,
+                label: None,
+            },
+            NumberInput {
+                value: 100.0,
+                uses_comma: false,
+                treatment: TaxTreatment::Exempt,
+                quantity: None,
+                label: None,
+            },
+            NumberInput {
+                value: 100.0,
+                uses_comma: false,
+                treatment: TaxTreatment::OutOfScope,
+                quantity: None,
+                label: None,
+            },
+            NumberInput {
+                value: 100.0,
+                uses_comma: false,
+                treatment: TaxTreatment::Tip,
+                quantity: None,
+                label: None,
+            },
+        ];
+        let results = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+
+        assert_eq!(results[0].treatment, TaxTreatment::ReverseCharge);
+        assert_eq!(results[0].without_vat, 100.0);
+        assert!((results[0].self_assessed_vat - 19.0).abs() < 0.001);
+
+        for result in &results[1..] {
+            assert_eq!(result.without_vat, 100.0);
+            assert_eq!(result.self_assessed_vat, 0.0);
+        }
+    }
+
+    fn component(name: &str, rate: f64, compound: bool) -> TaxComponent {
+        TaxComponent {
+            name: name.to_string(),
+            rate,
+            compound,
+        }
+    }
+
+    #[test]
+    fn test_calculate_component_taxes_simple() {
+        let components = [component("GST", 5.0, false), component("PST", 7.0, false)];
+        let taxes = calculate_component_taxes(100.0, &components);
+        assert!((taxes[0] - 5.0).abs() < 0.0001);
+        assert!((taxes[1] - 7.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_calculate_component_taxes_compound() {
+        let components = [component("GST", 5.0, false), component("QST", 10.0, true)];
+        let taxes = calculate_component_taxes(100.0, &components);
+        assert!((taxes[0] - 5.0).abs() < 0.0001);
+        assert!((taxes[1] - 10.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_calculate_without_components() {
+        let components = [component("GST", 5.0, false), component("QST", 10.0, true)];
+        let net = calculate_without_components(115.5, &components);
+        assert!((net - 100.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_calculate_without_components_single_matches_vat() {
+        let components = [component("VAT", 19.0, false)];
+        let net = calculate_without_components(119.0, &components);
+        assert!((net - calculate_without_vat(119.0, 19.0)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_process_numbers_with_components() {
+        let components = [
+            component("State", 6.0, false),
+            component("County", 1.0, false),
+            component("City", 0.5, false),
+        ];
+        let numbers = vec![
+            NumberInput {
+                value: 107.5,
+                uses_comma: false,
+                treatment: TaxTreatment::Standard,
+                quantity: None,
+                label: None,
+            },
+            NumberInput {
+                value: 200.0,
+                uses_comma: false,
+                treatment: TaxTreatment::ReverseCharge,
+                quantity: None,
+                label: None,
+            },
+        ];
+        let results = process_numbers_with_components(
+            &numbers,
+            &components,
+            2,
+            RoundingMode::HalfUp,
+            RoundingLevel::Line,
+        );
+
+        assert!((results[0].without_vat - 100.0).abs() < 0.0001);
+        assert_eq!(results[0].component_taxes.len(), 3);
+        assert!((results[0].component_taxes[0] - 6.0).abs() < 0.0001);
+        assert!((results[0].component_taxes[2] - 0.5).abs() < 0.0001);
+
+        assert_eq!(results[1].without_vat, 200.0);
+        assert_eq!(results[1].component_taxes, vec![0.0; 3]);
+        assert!((results[1].self_assessed_vat - 15.0).abs() < 0.0001);
+    }
+
+    fn adjustment(value: f64, is_percentage: bool, base: AdjustmentBase) -> Adjustment {
+        Adjustment {
+            value,
+            is_percentage,
+            base,
+            level: AdjustmentLevel::Line,
+        }
+    }
+
+    #[test]
+    fn test_apply_adjustment_skonto() {
+        let skonto = adjustment(-3.0, true, AdjustmentBase::AfterVat);
+        let adjusted = apply_adjustment(119.0, 100.0, &skonto);
+        assert!((adjusted.with_vat - 115.43).abs() < 0.0001);
+        assert!((adjusted.without_vat - 97.0).abs() < 0.0001);
+        assert!((adjusted.vat_correction - (-0.57)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_apply_adjustment_percentage_base_irrelevant() {
+        let after = apply_adjustment(
+            119.0,
+            100.0,
+            &adjustment(-3.0, true, AdjustmentBase::AfterVat),
+        );
+        let before = apply_adjustment(
+            119.0,
+            100.0,
+            &adjustment(-3.0, true, AdjustmentBase::BeforeVat),
+        );
+        assert!((after.with_vat - before.with_vat).abs() < 0.0001);
+        assert!((after.without_vat - before.without_vat).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_apply_adjustment_absolute_before_and_after_vat() {
+        let before = apply_adjustment(
+            119.0,
+            100.0,
+            &adjustment(-10.0, false, AdjustmentBase::BeforeVat),
+        );
+        assert!((before.without_vat - 90.0).abs() < 0.0001);
+        assert!((before.with_vat - 107.1).abs() < 0.0001);
+        assert!((before.vat_correction - (-1.9)).abs() < 0.0001);
+
+        let after = apply_adjustment(
+            119.0,
+            100.0,
+            &adjustment(-11.9, false, AdjustmentBase::AfterVat),
+        );
+        assert!((after.with_vat - 107.1).abs() < 0.0001);
+        assert!((after.without_vat - 90.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_apply_adjustment_surcharge() {
+        let adjusted = apply_adjustment(
+            119.0,
+            100.0,
+            &adjustment(5.0, false, AdjustmentBase::BeforeVat),
+        );
+        assert!((adjusted.with_vat - 124.95).abs() < 0.0001);
+        assert!((adjusted.vat_correction - 0.95).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_apply_adjustment_without_vat_line() {
+        let adjusted = apply_adjustment(
+            100.0,
+            100.0,
+            &adjustment(-2.0, true, AdjustmentBase::AfterVat),
+        );
+        assert!((adjusted.without_vat - 98.0).abs() < 0.0001);
+        assert_eq!(adjusted.vat_correction, 0.0);
+    }
+
+    #[test]
+    fn test_apply_line_adjustments_chained() {
+        let mut results = vec![CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            ..Default::default()
+        }];
+        let adjustments = [
+            adjustment(-10.0, true, AdjustmentBase::AfterVat),
+            adjustment(-3.0, true, AdjustmentBase::AfterVat),
+            Adjustment {
+                level: AdjustmentLevel::Total,
+                ..adjustment(-50.0, true, AdjustmentBase::AfterVat)
+            },
+        ];
+        apply_line_adjustments(&mut results, &adjustments);
+
+        let adjusted = results[0].adjusted.unwrap();
+        assert!((adjusted.with_vat - 103.887).abs() < 0.0001);
+        assert!((adjusted.without_vat - 87.3).abs() < 0.0001);
+        assert!((adjusted.vat_correction - (-2.413)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_apply_line_adjustments_reverse_charge() {
+        let mut results = vec![CalculationResult {
+            with_vat: 100.0,
+            without_vat: 100.0,
+            treatment: TaxTreatment::ReverseCharge,
+            self_assessed_vat: 19.0,
+            ..Default::default()
+        }];
+        apply_line_adjustments(
+            &mut results,
+            &[adjustment(-2.0, true, AdjustmentBase::AfterVat)],
+        );
+        assert_eq!(results[0].adjusted_amounts(), (98.0, 98.0));
+        assert!((results[0].self_assessed_vat - 18.62).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_apply_line_adjustments_none() {
+        let mut results = vec![CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            ..Default::default()
+        }];
+        apply_line_adjustments(&mut results, &[]);
+        assert!(results[0].adjusted.is_none());
+    }
+
+    #[test]
+    fn test_calculate_total_adjustment() {
+        let results = vec![
+            CalculationResult {
+                with_vat: 119.0,
+                without_vat: 100.0,
+                ..Default::default()
+            },
+            CalculationResult {
+                with_vat: 100.0,
+                without_vat: 100.0,
+                ..Default::default()
+            },
+        ];
+        let adjustments = [Adjustment {
+            value: -21.9,
+            is_percentage: false,
+            base: AdjustmentBase::AfterVat,
+            level: AdjustmentLevel::Total,
+        }];
+        let total = calculate_total_adjustment(&results, &adjustments).unwrap();
+        assert!((total.with_vat - 197.1).abs() < 0.0001);
+        assert!((total.without_vat - 180.0).abs() < 0.0001);
+        assert!((total.vat_correction - (-1.9)).abs() < 0.0001);
+
+        assert!(calculate_total_adjustment(&results, &[]).is_none());
+    }
+
+    #[test]
+    fn test_round_results_to_swiss_rappen() {
+        let rule = RoundingRule::for_currency("CHF").unwrap();
+        // 100.42 at 8.1%: the amount paid is 100.40, of which 92.88 is net
+        let mut results = vec![CalculationResult {
+            with_vat: 100.42,
+            without_vat: 92.9,
+            adjusted: Some(AdjustedAmounts {
+                with_vat: 98.41,
+                without_vat: 91.04,
+                vat_correction: -0.15,
+            }),
+            ..Default::default()
+        }];
+        round_results(&mut results, &rule, 8.1, &[], 2);
+
+        assert!((results[0].with_vat - 100.4).abs() < 1e-9);
+        assert!((results[0].without_vat - 92.88).abs() < 1e-9);
+        let adjusted = results[0].adjusted.unwrap();
+        assert!((adjusted.with_vat - 98.4).abs() < 1e-9);
+        assert!((adjusted.without_vat - 91.03).abs() < 1e-9);
+        assert!((adjusted.vat_correction - (-0.15)).abs() < 1e-9);
+
+        // Stacked taxes are calculated from the rounded gross as well
+        let components = [TaxComponent {
+            name: "MWST".to_string(),
+            rate: 8.1,
+            compound: false,
+        }];
+        let mut results = vec![CalculationResult {
+            with_vat: 108.12,
+            without_vat: 100.02,
+            component_taxes: vec![8.1],
+            ..Default::default()
+        }];
+        round_results(&mut results, &rule, 8.1, &components, 2);
+        assert!((results[0].with_vat - 108.1).abs() < 1e-9);
+        assert!((results[0].component_taxes[0] - 8.1).abs() < 1e-9);
+        assert!((results[0].without_vat - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_process_numbers_rounds_net_with_mode() {
+        // 100.55625 / 1.25 = 80.445, exactly halfway between two cents
+        let numbers = vec![NumberInput {
+            value: 100.55625,
+            uses_comma: false,
+            treatment: TaxTreatment::Standard,
+            quantity: None,
+            label: None,
+        }];
+        let half_up = process_numbers(&numbers, 25.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        let half_even = process_numbers(
+            &numbers,
+            25.0,
+            2,
+            RoundingMode::HalfEven,
+            RoundingLevel::Line,
+        );
+        let floor = process_numbers(&numbers, 25.0, 2, RoundingMode::Floor, RoundingLevel::Line);
+        assert!((half_up[0].without_vat - 80.45).abs() < 1e-9);
+        assert!((half_even[0].without_vat - 80.44).abs() < 1e-9);
+        assert!((floor[0].without_vat - 80.44).abs() < 1e-9);
+
+        // 100.54375 / 1.25 = 80.435
+        let numbers = vec![NumberInput {
+            value: 100.54375,
+            uses_comma: false,
+            treatment: TaxTreatment::Standard,
+            quantity: None,
+            label: None,
+        }];
+        let half_up = process_numbers(&numbers, 25.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        let half_even = process_numbers(
+            &numbers,
+            25.0,
+            2,
+            RoundingMode::HalfEven,
+            RoundingLevel::Line,
+        );
+        assert!((half_up[0].without_vat - 80.44).abs() < 1e-9);
+        assert!((half_even[0].without_vat - 80.44).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_process_numbers_with_components_reconciles() {
+        let components = [component("GST", 5.0, false), component("QST", 9.975, false)];
+        let numbers = vec![NumberInput {
+            value: 10.0,
+            uses_comma: false,
+            treatment: TaxTreatment::Standard,
+            quantity: None,
+            label: None,
+        }];
+        let results = process_numbers_with_components(
+            &numbers,
+            &components,
+            2,
+            RoundingMode::HalfUp,
+            RoundingLevel::Line,
+        );
+        let taxes: f64 = results[0].component_taxes.iter().sum();
+        assert!((results[0].without_vat + taxes - 10.0).abs() < 1e-9);
+        assert!((results[0].component_taxes[0] - 0.43).abs() < 1e-9);
+        assert!((results[0].component_taxes[1] - 0.87).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_process_numbers_zero_decimals() {
+        let numbers = vec![NumberInput {
+            value: 1000.0,
+            uses_comma: false,
+            treatment: TaxTreatment::Standard,
+            quantity: None,
+            label: None,
+        }];
+        let results = process_numbers(&numbers, 10.0, 0, RoundingMode::HalfUp, RoundingLevel::Line);
+        assert_eq!(results[0].without_vat, 909.0);
+    }
+
+    #[test]
+    fn test_convert_results() {
+        let conversion = Conversion {
+            from: "USD".to_string(),
+            to: "EUR".to_string(),
+            rate: 0.8555,
+            date: "2025-10-17".to_string(),
+        };
+        let mut results = vec![CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            ..Default::default()
+        }];
+        convert_results(&mut results, &conversion, 2, RoundingMode::HalfUp);
+
+        let converted = results[0].converted.as_ref().unwrap();
+        assert_eq!(converted.currency, "EUR");
+        assert!((converted.without_vat - 85.55).abs() < 1e-9);
+        assert!((converted.vat - 16.25).abs() < 1e-9);
+
+        let discount = Adjustment {
+            value: -2.0,
+            is_percentage: true,
+            base: AdjustmentBase::AfterVat,
+            level: AdjustmentLevel::Line,
+        };
+        apply_line_adjustments(&mut results, &[discount]);
+        convert_results(&mut results, &conversion, 2, RoundingMode::HalfUp);
+        let converted = results[0].converted.as_ref().unwrap();
+        assert!((converted.without_vat - 83.84).abs() < 1e-9);
+        assert!((converted.vat - 15.93).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_booked_results() {
+        let conversion = Conversion {
+            from: "USD".to_string(),
+            to: "EUR".to_string(),
+            rate: 0.8555,
+            date: "2025-10-17".to_string(),
+        };
+        let mut results = vec![CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            ..Default::default()
+        }];
+        let discount = Adjustment {
+            value: -2.0,
+            is_percentage: true,
+            base: AdjustmentBase::AfterVat,
+            level: AdjustmentLevel::Line,
+        };
+        apply_line_adjustments(&mut results, &[discount]);
+
+        let booked = booked_results(&results, None, 2, RoundingMode::HalfUp);
+        assert!((booked[0].with_vat - 116.62).abs() < 1e-9);
+        assert!((booked[0].without_vat - 98.0).abs() < 1e-9);
+        assert_eq!(booked[0].adjusted, None);
+
+        let booked = booked_results(&results, Some(&conversion), 2, RoundingMode::HalfUp);
+        assert!((booked[0].without_vat - 83.84).abs() < 1e-9);
+        assert!((booked[0].with_vat - 99.77).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calculate_net_price_markup() {
+        let price = calculate_net_price(80.0, PricingTarget::Markup(25.0));
+        assert!((price - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calculate_net_price_margin() {
+        let price = calculate_net_price(80.0, PricingTarget::Margin(20.0));
+        assert!((price - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_process_pricing() {
+        let costs = vec![NumberInput {
+            value: 49.99,
+            uses_comma: true,
+            treatment: TaxTreatment::Standard,
+            quantity: None,
+            label: None,
+        }];
+        let results = process_pricing(
+            &costs,
+            PricingTarget::Margin(30.0),
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+
+        let result = &results[0];
+        assert_eq!(result.cost, 49.99);
+        assert!((result.net_price - 71.41).abs() < 1e-9);
+        assert!((result.gross_price - 84.98).abs() < 1e-9);
+        assert!((result.margin - 21.42).abs() < 1e-9);
+        assert!((result.vat - 13.57).abs() < 1e-9);
+        assert!(result.uses_comma);
+    }
+
+    #[test]
+    fn test_process_margin_scheme() {
+        let pairs = vec![
+            PairInput {
+                first: 800.0,
+                second: 1000.0,
+                uses_comma: false,
+                label: None,
+            },
+            PairInput {
+                first: 500.0,
+                second: 450.0,
+                uses_comma: true,
+                label: None,
+            },
+        ];
+        let results = process_margin_scheme(&pairs, 19.0, 2, RoundingMode::HalfUp);
+
+        assert_eq!(results[0].margin, 200.0);
+        assert!((results[0].vat - 31.93).abs() < 1e-9);
+        assert_eq!(results[1].margin, -50.0);
+        assert_eq!(results[1].vat, 0.0);
+        assert!(results[1].uses_comma);
+    }
+
+    #[test]
+    fn test_total_margin_scheme() {
+        let results = vec![
+            MarginSchemeResult {
+                purchase: 800.0,
+                selling: 1000.0,
+                margin: 200.0,
+                vat: 31.93,
+                uses_comma: false,
+            },
+            MarginSchemeResult {
+                purchase: 500.0,
+                selling: 450.0,
+                margin: -50.0,
+                vat: 0.0,
+                uses_comma: false,
+            },
+        ];
+        let total = total_margin_scheme(&results);
+        assert_eq!(total.purchase, 1300.0);
+        assert_eq!(total.selling, 1450.0);
+        assert_eq!(total.margin, 150.0);
+        assert_eq!(total.vat, 31.93);
+    }
+
+    #[test]
+    fn test_calculate_implied_rate() {
+        assert!((calculate_implied_rate(119.0, 100.0).unwrap() - 19.0).abs() < 1e-9);
+        assert!((calculate_implied_rate(107.0, 100.0).unwrap() - 7.0).abs() < 1e-9);
+        assert_eq!(calculate_implied_rate(10.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_match_known_rate_credit_note() {
+        assert!((calculate_implied_rate(-119.0, -100.0).unwrap() - 19.0).abs() < 1e-9);
+        assert_eq!(
+            match_known_rate(
+                -119.0,
+                -100.0,
+                &GERMAN_VAT_RATES,
+                0.1,
+                2,
+                RoundingMode::HalfUp
+            ),
+            Some(19.0)
+        );
+        assert_eq!(
+            match_known_rate(
+                -0.06,
+                -0.05,
+                &GERMAN_VAT_RATES,
+                0.1,
+                2,
+                RoundingMode::HalfUp
+            ),
+            Some(19.0)
+        );
+    }
+
+    #[test]
+    fn test_match_known_rate() {
+        let known = GERMAN_VAT_RATES;
+        let mode = RoundingMode::HalfUp;
+        assert_eq!(
+            match_known_rate(119.0, 100.0, &known, 0.1, 2, mode),
+            Some(19.0)
+        );
+        assert_eq!(
+            match_known_rate(107.05, 100.0, &known, 0.1, 2, mode),
+            Some(7.0)
+        );
+        assert_eq!(match_known_rate(116.0, 100.0, &known, 0.1, 2, mode), None);
+        assert_eq!(
+            match_known_rate(116.0, 100.0, &known, 4.0, 2, mode),
+            Some(19.0)
+        );
+    }
+
+    #[test]
+    fn test_match_known_rate_small_amounts() {
+        // 0.05 * 1.19 = 0.0595 rounds to 0.06, an implied rate of 20%
+        assert_eq!(
+            match_known_rate(0.06, 0.05, &GERMAN_VAT_RATES, 0.1, 2, RoundingMode::HalfUp),
+            Some(19.0)
+        );
+    }
+
+    #[test]
+    fn test_solve_rates() {
+        let pairs = vec![
+            PairInput {
+                first: 119.0,
+                second: 100.0,
+                uses_comma: false,
+                label: None,
+            },
+            PairInput {
+                first: 120.0,
+                second: 100.0,
+                uses_comma: true,
+                label: None,
+            },
+        ];
+        let results = solve_rates(&pairs, &[19.0, 7.0], 0.1, 2, RoundingMode::HalfUp);
+        assert_eq!(results[0].gross, 119.0);
+        assert_eq!(results[0].net, 100.0);
+        assert_eq!(results[0].matched_rate, Some(19.0));
+        assert!((results[1].implied_rate.unwrap() - 20.0).abs() < 1e-9);
+        assert_eq!(results[1].matched_rate, None);
+        assert!(results[1].uses_comma);
+    }
+
+    fn invoice(gross: f64, net: f64, vat: f64, rate: Option<f64>) -> InvoiceInput {
+        InvoiceInput {
+            gross,
+            net,
+            vat,
+            rate,
+            uses_comma: false,
+        }
+    }
+
+    #[test]
+    fn test_verify_invoices() {
+        let invoices = vec![
+            invoice(119.0, 100.0, 19.0, None),
+            invoice(107.0, 100.0, 7.0, Some(7.0)),
+            invoice(119.0, 100.0, 7.0, None),
+        ];
+        let results = verify_invoices(&invoices, 19.0, 0.01, 2, RoundingMode::HalfUp);
+        assert!(results[0].passed);
+        assert!(results[1].passed);
+        assert_eq!(results[1].rate, 7.0);
+        assert!(!results[2].passed);
+        assert_eq!(results[2].expected_net, 100.0);
+        assert_eq!(results[2].expected_vat, 19.0);
+    }
+
+    #[test]
+    fn test_verify_invoices_tolerance() {
+        // 10.00 / 1.19 = 8.403..., so exactly 8.40 net and 1.60 VAT
+        let invoices = vec![invoice(10.0, 8.40, 1.60, None)];
+        let results = verify_invoices(&invoices, 19.0, 0.0, 2, RoundingMode::HalfUp);
+        assert_eq!(results[0].expected_net, 8.40);
+        assert!(results[0].passed);
+
+        let invoices = vec![invoice(10.0, 8.41, 1.59, None)];
+        assert!(!verify_invoices(&invoices, 19.0, 0.0, 2, RoundingMode::HalfUp)[0].passed);
+        assert!(verify_invoices(&invoices, 19.0, 0.01, 2, RoundingMode::HalfUp)[0].passed);
+    }
+
+    #[test]
+    fn test_allocate_largest_remainder() {
+        assert_eq!(
+            allocate_largest_remainder(100.0, &[1.0, 1.0, 1.0], 2),
+            vec![33.34, 33.33, 33.33]
+        );
+        assert_eq!(
+            allocate_largest_remainder(10.0, &[1.0, 2.0, 3.0], 2),
+            vec![1.67, 3.33, 5.0]
+        );
+        assert_eq!(
+            allocate_largest_remainder(1000.0, &[1.0, 1.0, 1.0], 0),
+            vec![334.0, 333.0, 333.0]
+        );
+        assert_eq!(
+            allocate_largest_remainder(0.0, &[0.0, 0.0], 2),
+            vec![0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_split_installments() {
+        let input = NumberInput {
+            value: 1000.0,
+            uses_comma: true,
+            treatment: TaxTreatment::Standard,
+            quantity: None,
+            label: None,
+        };
+        let installments = split_installments(&input, 3, 19.0, 2, RoundingMode::HalfUp);
+        assert_eq!(installments.len(), 3);
+        assert_eq!(installments[0].gross, 333.34);
+        assert_eq!(installments[1].gross, 333.33);
+        assert_eq!(installments[0].net, 280.12);
+        assert_eq!(installments[1].net, 280.11);
+        assert!(installments[0].uses_comma);
+
+        let total = total_split_parts(&installments);
+        assert!((total.gross - 1000.0).abs() < 1e-9);
+        assert!((total.net - 840.34).abs() < 1e-9);
+        assert!((total.vat - 159.66).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_allocate_shares() {
+        let input = NumberInput {
+            value: 250.0,
+            uses_comma: false,
+            treatment: TaxTreatment::Standard,
+            quantity: None,
+            label: None,
+        };
+        let shares = vec![
+            Share {
+                name: "Sales".to_string(),
+                weight: 2.0,
+            },
+            Share {
+                name: "Support".to_string(),
+                weight: 1.0,
+            },
+        ];
+        let parts = allocate_shares(&input, &shares, 19.0, 2, RoundingMode::HalfUp);
+        assert_eq!(parts[0].gross, 166.67);
+        assert_eq!(parts[1].gross, 83.33);
+        assert_eq!(parts[0].net, 140.06);
+        assert_eq!(parts[1].net, 70.02);
+
+        let total = total_split_parts(&parts);
+        assert!((total.gross - 250.0).abs() < 1e-9);
+        assert!((total.net - 210.08).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tip_excluded_from_adjustments() {
+        let mut results = vec![
+            CalculationResult {
+                with_vat: 119.0,
+                without_vat: 100.0,
+                ..Default::default()
+            },
+            CalculationResult {
+                with_vat: 10.0,
+                without_vat: 10.0,
+                treatment: TaxTreatment::Tip,
+                ..Default::default()
+            },
+        ];
+        let adjustments = vec![
+            Adjustment {
+                value: -10.0,
+                is_percentage: true,
+                base: AdjustmentBase::AfterVat,
+                level: AdjustmentLevel::Line,
+            },
+            Adjustment {
+                value: -10.0,
+                is_percentage: false,
+                base: AdjustmentBase::BeforeVat,
+                level: AdjustmentLevel::Total,
+            },
+        ];
+        apply_line_adjustments(&mut results, &adjustments);
+        assert!(results[0].adjusted.is_some());
+        assert!(results[1].adjusted.is_none());
+
+        let total = calculate_total_adjustment(&results, &adjustments).unwrap();
+        // 119 - 10% = 107.10 gross / 90 net, minus 10 net = 95.20 / 80, plus the tip
+        assert!((total.with_vat - 105.2).abs() < 1e-9);
+        assert!((total.without_vat - 90.0).abs() < 1e-9);
+        assert!((total.vat_correction + 1.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calculate_receipt_total() {
+        let mut results = vec![
+            CalculationResult {
+                with_vat: 119.0,
+                without_vat: 100.0,
+                ..Default::default()
+            },
+            CalculationResult {
+                with_vat: 5.5,
+                without_vat: 5.5,
+                treatment: TaxTreatment::Tip,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            calculate_receipt_total(&results),
+            Some(ReceiptTotal {
+                gross: 124.5,
+                net: 100.0,
+                vat: 19.0,
+                tip: 5.5,
+            })
+        );
+        results.pop();
+        assert_eq!(calculate_receipt_total(&results), None);
+    }
+
+    #[test]
+    fn test_process_lines() {
+        let lines = vec![
+            ImportedLine {
+                row: "Taxi;23,80;19".to_string(),
+                input: NumberInput {
+                    value: 23.8,
+                    uses_comma: true,
+                    treatment: TaxTreatment::Standard,
+                    quantity: None,
+                    label: None,
+                },
+                rate: Some(19.0),
+                date: None,
+                description: None,
+                counterparty: None,
+                category: None,
+            },
+            ImportedLine {
+                row: "Buch;10,70;".to_string(),
+                input: NumberInput {
+                    value: 10.7,
+                    uses_comma: true,
+                    treatment: TaxTreatment::Standard,
+                    quantity: None,
+                    label: None,
+                },
+                rate: None,
+                date: None,
+                description: None,
+                counterparty: None,
+                category: None,
+            },
+        ];
+        let results = process_lines(&lines, 7.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        assert_eq!(results[0].without_vat, 20.0);
+        assert_eq!(results[1].without_vat, 10.0);
+    }
+
+    #[test]
+    fn test_process_numbers_quantity_rounding_level() {
+        let numbers = vec![NumberInput {
+            value: 1.05,
+            uses_comma: true,
+            treatment: TaxTreatment::Standard,
+            quantity: Some(Quantity {
+                count: 3.0,
+                unit_price: 0.35,
+            }),
+            label: None,
+        }];
+
+        // 1,05 / 1,19 = 0,8824 on the line, 0,35 / 1,19 = 0,2941 per unit
+        let line = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        assert!((line[0].with_vat - 1.05).abs() < 1e-9);
+        assert!((line[0].without_vat - 0.88).abs() < 1e-9);
+        assert_eq!(line[0].quantity, numbers[0].quantity);
+
+        let unit = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Unit);
+        assert!((unit[0].with_vat - 1.05).abs() < 1e-9);
+        assert!((unit[0].without_vat - 0.87).abs() < 1e-9);
+        assert_eq!(unit[0].quantity, numbers[0].quantity);
+    }
+
+    fn sample_einvoice() -> EInvoice {
+        let line = |label: &str, net: f64, category: &str, rate: f64| EInvoiceLine {
+            label: Some(label.to_string()),
+            quantity: 1.0,
+            net,
+            category: category.to_string(),
+            rate,
+        };
+        let subtotal = |category: &str, rate: f64, basis: f64, vat: f64| TaxSubtotal {
+            category: category.to_string(),
+            rate,
+            basis,
+            vat,
+        };
+        EInvoice {
+            number: "R-1".to_string(),
+            currency: "EUR".to_string(),
+            // Per line 0,19 + 0,19 VAT, but 0,38 on the summed 2,00 net
+            lines: vec![
+                line("A", 0.99, "S", 19.0),
+                line("B", 1.01, "S", 19.0),
+                line("C", 50.0, "E", 0.0),
+            ],
+            subtotals: vec![
+                subtotal("S", 19.0, 2.0, 0.38),
+                subtotal("E", 0.0, 50.0, 0.0),
+            ],
+            net_total: 52.0,
+            vat_total: 0.38,
+            gross_total: 52.38,
+        }
+    }
+
+    #[test]
+    fn test_verify_einvoice() {
+        let check = verify_einvoice(&sample_einvoice(), 0.01, 2, RoundingMode::HalfUp);
+        assert!(check.passed);
+        assert_eq!(check.subtotals.len(), 2);
+        assert!((check.subtotals[0].expected_vat - 0.38).abs() < 1e-9);
+        assert!((check.expected.gross - 52.38).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_verify_einvoice_mismatch() {
+        let mut invoice = sample_einvoice();
+        invoice.subtotals[0].vat = 0.40;
+        invoice.lines.push(EInvoiceLine {
+            label: None,
+            quantity: 1.0,
+            net: 10.0,
+            category: "S".to_string(),
+            rate: 7.0,
+        });
+        let check = verify_einvoice(&invoice, 0.01, 2, RoundingMode::HalfUp);
+        assert!(!check.passed);
+        assert!(!check.subtotals[0].passed);
+        assert!(check.subtotals[1].passed);
+        // Lines at 7% have no declared subtotal
+        assert_eq!(check.subtotals[2].rate, 7.0);
+        assert_eq!(check.subtotals[2].basis, 0.0);
+        assert!(!check.subtotals[2].passed);
+    }
+
+    #[test]
+    fn test_process_einvoice_lines() {
+        let results = process_einvoice_lines(&sample_einvoice(), 19.0, 2, RoundingMode::HalfUp);
+        assert!((results[0].with_vat - 1.18).abs() < 1e-9);
+        assert_eq!(results[0].without_vat, 0.99);
+        assert_eq!(results[0].label.as_deref(), Some("A"));
+        assert_eq!(results[2].treatment, TaxTreatment::Exempt);
+        assert_eq!(results[2].with_vat, 50.0);
+    }
+
+    #[test]
+    fn test_process_einvoice_lines_reverse_charge_rate() {
+        let line = |category: &str, rate: f64| EInvoiceLine {
+            label: None,
+            quantity: 2.0,
+            net: 100.0,
+            category: category.to_string(),
+            rate,
+        };
+        let mut invoice = sample_einvoice();
+        invoice.lines = vec![line("AE", 7.0), line("AE", 0.0), line("S", 7.0)];
+        let results = process_einvoice_lines(&invoice, 19.0, 2, RoundingMode::HalfUp);
+        assert!((results[0].self_assessed_vat - 7.0).abs() < 1e-9);
+        assert!((results[1].self_assessed_vat - 19.0).abs() < 1e-9);
+        assert!((results[2].with_vat - 107.0).abs() < 1e-9);
+        assert_eq!(results[2].without_vat, 100.0);
+        assert!((results[2].quantity.unwrap().unit_price - 53.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tax_treatment_from_category() {
+        assert_eq!(TaxTreatment::from_category("S"), TaxTreatment::Standard);
+        assert_eq!(TaxTreatment::from_category("E"), TaxTreatment::Exempt);
+        assert_eq!(
+            TaxTreatment::from_category("AE"),
+            TaxTreatment::ReverseCharge
+        );
+        assert_eq!(TaxTreatment::from_category("O"), TaxTreatment::OutOfScope);
+    }
+
+    #[test]
+    fn test_tax_treatment_category() {
+        assert_eq!(TaxTreatment::Standard.category(19.0), "S");
+        assert_eq!(TaxTreatment::Standard.category(0.0), "Z");
+        assert_eq!(TaxTreatment::ReverseCharge.category(19.0), "AE");
+        assert_eq!(TaxTreatment::Tip.category(19.0), "O");
+    }
+
+    #[test]
+    fn test_calculate_tax_subtotals() {
+        let line = |net: f64, category: &str, rate: f64| EInvoiceLine {
+            label: None,
+            quantity: 1.0,
+            net,
+            category: category.to_string(),
+            rate,
+        };
+        let lines = [
+            line(10.05, "S", 7.0),
+            line(4.2, "S", 19.0),
+            line(10.05, "S", 7.0),
+            line(50.0, "E", 0.0),
+        ];
+        let subtotals = calculate_tax_subtotals(&lines, 2, RoundingMode::HalfUp);
+        assert_eq!(subtotals.len(), 3);
+        assert!((subtotals[0].basis - 20.1).abs() < 1e-9);
+        // 7% of the 20,10 sum, not twice 7% of 10,05 (0,70 + 0,70)
+        assert!((subtotals[0].vat - 1.41).abs() < 1e-9);
+        assert_eq!(subtotals[1].rate, 19.0);
+        assert_eq!(subtotals[2].vat, 0.0);
+    }
+
+    #[test]
+    fn test_build_einvoice() {
+        let numbers: Vec<NumberInput> = ["Buch=11,90", "100:rc"]
+            .iter()
+            .map(|input| crate::cli::parse_number(input).unwrap())
+            .collect();
+        let results = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        let invoice = build_einvoice(
+            &results,
+            &[19.0, 19.0],
+            "R-1",
+            "eur",
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert_eq!(invoice.currency, "EUR");
+        assert_eq!(invoice.lines[0].label.as_deref(), Some("Buch"));
+        assert_eq!(invoice.lines[0].net, 10.0);
+        assert_eq!(invoice.lines[1].category, "AE");
+        assert_eq!(invoice.lines[1].rate, 0.0);
+        assert_eq!(invoice.net_total, 110.0);
+        assert!((invoice.vat_total - 1.9).abs() < 1e-9);
+        assert!((invoice.gross_total - 111.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_process_numbers_with_components_per_unit() {
+        let components = vec![TaxComponent {
+            name: "GST".to_string(),
+            rate: 5.0,
+            compound: false,
+        }];
+        let numbers = vec![NumberInput {
+            value: 31.5,
+            uses_comma: false,
+            treatment: TaxTreatment::Standard,
+            quantity: Some(Quantity {
+                count: 3.0,
+                unit_price: 10.5,
+            }),
+            label: None,
+        }];
+        let results = process_numbers_with_components(
+            &numbers,
+            &components,
+            2,
+            RoundingMode::HalfUp,
+            RoundingLevel::Unit,
+        );
+        assert!((results[0].without_vat - 30.0).abs() < 1e-9);
+        assert!((results[0].component_taxes[0] - 1.5).abs() < 1e-9);
+    }
 }
@@ -1,20 +1,154 @@
 use std::env;
 
+use crate::calculator::{
+    Adjustment, AdjustmentBase, AdjustmentLevel, GERMAN_VAT_RATES, PricingTarget, Quantity, Share,
+    TaxComponent, TaxTreatment,
+};
+use crate::display::OutputFormat;
+use crate::invoice::lines::{ColumnMap, parse_amount, parse_date};
+use crate::invoice::rules::Rule;
+use crate::rounding::{RoundingLevel, RoundingMode, RoundingRule, minor_units};
+
 #[derive(Debug)]
 pub struct NumberInput {
     pub value: f64,
     pub uses_comma: bool,
+    pub treatment: TaxTreatment,
+    pub quantity: Option<Quantity>,
+    pub label: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct PairInput {
+    pub first: f64,
+    pub second: f64,
+    pub uses_comma: bool,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvoiceInput {
+    pub gross: f64,
+    pub net: f64,
+    pub vat: f64,
+    pub rate: Option<f64>,
+    pub uses_comma: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Mode {
+    #[default]
+    Vat,
+    Pricing(PricingTarget),
+    MarginScheme,
+    SolveRate,
+    Verify,
+    Installments(usize),
+    Allocate,
+    LineImport,
+    EInvoice,
+    XRechnung,
+    Statement,
 }
 
 #[derive(Debug)]
 pub struct ParsedArgs {
+    pub mode: Mode,
+    pub format: OutputFormat,
     pub numbers: Vec<NumberInput>,
+    pub pairs: Vec<PairInput>,
+    pub invoices: Vec<InvoiceInput>,
+    pub invoice_file: Option<String>,
+    pub lines_file: Option<String>,
+    pub einvoice_file: Option<String>,
+    pub parties_file: Option<String>,
+    pub statement_file: Option<String>,
+    pub payee_rules: Vec<Rule>,
+    pub rules_file: Option<String>,
+    pub accounts_file: Option<String>,
+    pub column_map: ColumnMap,
+    pub tolerance: f64,
     pub vat_rate: f64,
+    pub known_rates: Vec<f64>,
+    pub rate_tolerance: f64,
+    pub tax_components: Vec<TaxComponent>,
+    pub shares: Vec<Share>,
+    pub adjustments: Vec<Adjustment>,
+    pub rounding: Option<RoundingRule>,
+    pub rounding_mode: RoundingMode,
+    pub rounding_level: RoundingLevel,
+    pub precision: usize,
+    pub currency: Option<String>,
+    pub convert_to: Option<String>,
+    pub rates_file: Option<String>,
+    pub rate_date: Option<String>,
 }
 
 pub fn print_usage(program_name: &str) {
     eprintln!("Usage: {program_name} <number1> [number2 ...] [--rate <percentage>]");
+    eprintln!(
+        "Append :exempt, :rc (reverse charge), :oos (out of scope) or :tip to a number to change its tax treatment"
+    );
+    eprintln!("Tip: --tip <amount> adds a tip line that is excluded from VAT");
+    eprintln!("Stacked taxes: --tax <name>=<percentage>[:compound] (repeatable, replaces --rate)");
+    eprintln!(
+        "Discounts/surcharges: --discount|--surcharge <amount>[%][:before][:total] (repeatable)"
+    );
+    eprintln!(
+        "Cash rounding: --currency <code> (e.g. CHF) or --rounding <increment>[:half-up|half-even|floor|ceil]"
+    );
+    eprintln!("Rounding mode: --rounding-mode half-up|half-even|floor|ceil (default: half-up)");
+    eprintln!(
+        "Quantities: <count>x<unit price> (e.g. 3x12,99) [--round-per unit|line (default: line)]"
+    );
+    eprintln!("Decimal places: --precision <0-10> (default: from --currency, otherwise 2)");
+    eprintln!(
+        "Currency conversion: --currency <code> --convert-to <code> --rates <ECB csv/xml file> [--rate-date <date> (required for historical files)]"
+    );
+    eprintln!(
+        "Pricing: --markup <percentage> or --margin <percentage> treats the numbers as net costs"
+    );
+    eprintln!(
+        "Margin scheme (§25a UStG): --margin-scheme with <purchase>/<selling> pairs instead of numbers"
+    );
+    eprintln!(
+        "Solve for the rate: --solve-rate with <gross>/<net> pairs [--known-rate <percentage> (repeatable, default: 19, 7, 0)] [--rate-tolerance <points> (default: 0.1)]"
+    );
+    eprintln!(
+        "Verify invoices: {program_name} verify <gross>/<net>/<vat> ... [--file <csv/json>] [--tolerance <amount> (default: 0.01)]"
+    );
+    eprintln!("Installments: --installments <count> splits a single gross amount into payments");
+    eprintln!(
+        "Allocate a shared bill: --share <name>=<weight> (repeatable) splits a single gross amount"
+    );
+    eprintln!(
+        "Line items: --lines <csv> [--map description=<col>,amount=<col>,rate=<col>,quantity=<col>,date=<col>,treatment=<col>] appends net and VAT columns"
+    );
+    eprintln!(
+        "E-invoices: --einvoice <ZUGFeRD/XRechnung CII or UBL xml> checks the VAT breakdown and totals [--tolerance <amount>]"
+    );
+    eprintln!(
+        "Generate an invoice: --xrechnung <parties.toml> with labelled numbers or --lines <csv> prints XRechnung CII XML"
+    );
+    eprintln!(
+        "Bank statements: --statement <CAMT.053 xml or MT940> extracts VAT from outgoing payments [--payee <counterparty>=<rate|treatment> (repeatable)]"
+    );
+    eprintln!(
+        "Rules: --rules <file> with lines <keyword or /regex/>;<rate|treatment>;<category> sets the rate of imported lines and statement payments"
+    );
+    eprintln!(
+        "DATEV export: --format datev --accounts <accounts.toml> writes a Buchungsstapel CSV for --lines or --statement"
+    );
+    eprintln!(
+        "Plain-text accounting: --format hledger|ledger|beancount [--accounts <accounts.toml> with [ledger] expense, vat, reverse_charge, payment]"
+    );
+    eprintln!("Output format: --format table|json|datev|hledger|ledger|beancount (default: table)");
     eprintln!("Environment variable: DEFAULT_VAT_RATE (default: 19)");
+    eprintln!("Environment variable: EXCHANGE_RATES_FILE (default for --rates)");
+}
+
+pub fn get_default_rates_file() -> Option<String> {
+    env::var("EXCHANGE_RATES_FILE").ok()
 }
 
 pub fn get_default_vat_rate() -> f64 {
@@ -24,14 +158,66 @@ pub fn get_default_vat_rate() -> f64 {
         .unwrap_or(19.0)
 }
 
-fn parse_number(input: &str) -> Option<NumberInput> {
-    let uses_comma = input.contains(',');
-    let normalized = input.replace(',', ".");
+pub fn parse_number(input: &str) -> Option<NumberInput> {
+    // "Taxi=23,80" labels the amount; the label may itself contain '='
+    let (label, input) = match input.rsplit_once('=') {
+        Some((label, amount)) => (Some(label.trim()).filter(|label| !label.is_empty()), amount),
+        None => (None, input),
+    };
+    let (amount, treatment) = match input.split_once(':') {
+        Some((amount, treatment)) => (amount, TaxTreatment::parse(treatment)?),
+        None => (input, TaxTreatment::Standard),
+    };
+    // "3x12,99" is three units at 12,99 each; a credit takes a negative price
+    let (count, amount) = match amount.split_once(['x', 'X', '×', '*']) {
+        Some((count, unit_price)) => (
+            Some(parse_amount(count, count.contains(',')).filter(|count| *count > 0.0)?),
+            unit_price,
+        ),
+        None => (None, amount),
+    };
+    let uses_comma = amount.contains(',');
+    parse_amount(amount, uses_comma).map(|value| NumberInput {
+        value: count.map_or(value, |count| count * value),
+        uses_comma,
+        treatment,
+        quantity: count.map(|count| Quantity {
+            count,
+            unit_price: value,
+        }),
+        label: label.map(str::to_string),
+    })
+}
 
-    normalized
-        .parse::<f64>()
-        .ok()
-        .map(|value| NumberInput { value, uses_comma })
+fn parse_pair(input: &str) -> Option<PairInput> {
+    let (first, second) = input.split_once('/')?;
+    let first = parse_number(first)?;
+    let second = parse_number(second)?;
+
+    Some(PairInput {
+        first: first.value,
+        second: second.value,
+        uses_comma: first.uses_comma || second.uses_comma,
+        label: first.label,
+    })
+}
+
+fn parse_invoice(input: &str) -> Option<InvoiceInput> {
+    let mut parts = input.split('/');
+    let gross = parse_number(parts.next()?)?;
+    let net = parse_number(parts.next()?)?;
+    let vat = parse_number(parts.next()?)?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(InvoiceInput {
+        gross: gross.value,
+        net: net.value,
+        vat: vat.value,
+        rate: None,
+        uses_comma: gross.uses_comma || net.uses_comma || vat.uses_comma,
+    })
 }
 
 fn parse_vat_rate(input: &str) -> f64 {
@@ -41,15 +227,165 @@ fn parse_vat_rate(input: &str) -> f64 {
         .unwrap_or_else(|_| get_default_vat_rate())
 }
 
+fn parse_tax_component(input: &str) -> Result<TaxComponent, String> {
+    let invalid = || format!("Invalid tax component: {input}");
+    let (name, rest) = input.split_once('=').ok_or_else(invalid)?;
+    let (rate, compound) = match rest.split_once(':') {
+        Some((rate, "compound")) => (rate, true),
+        Some(_) => return Err(invalid()),
+        None => (rest, false),
+    };
+    let rate = rate.replace(',', ".").parse().map_err(|_| invalid())?;
+
+    if name.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(TaxComponent {
+        name: name.to_string(),
+        rate,
+        compound,
+    })
+}
+
+fn parse_share(input: &str) -> Result<Share, String> {
+    let invalid = || format!("Invalid share: {input}");
+    let (name, weight) = input.split_once('=').ok_or_else(invalid)?;
+    let weight: f64 = weight.replace(',', ".").parse().map_err(|_| invalid())?;
+
+    if name.is_empty() || weight < 0.0 {
+        return Err(invalid());
+    }
+
+    Ok(Share {
+        name: name.to_string(),
+        weight,
+    })
+}
+
+// Adjustments default to the gross amount of every line, like a Skonto stated
+// on the invoice total; `before` applies them to the net, `total` to the sum.
+fn parse_adjustment(input: &str, sign: f64) -> Result<Adjustment, String> {
+    let invalid = || format!("Invalid discount or surcharge: {input}");
+    let mut parts = input.split(':');
+    let amount = parts.next().unwrap_or_default();
+    let (amount, is_percentage) = match amount.strip_suffix('%') {
+        Some(amount) => (amount, true),
+        None => (amount, false),
+    };
+    let value: f64 = amount.replace(',', ".").parse().map_err(|_| invalid())?;
+
+    let mut adjustment = Adjustment {
+        value: sign * value,
+        is_percentage,
+        base: AdjustmentBase::AfterVat,
+        level: AdjustmentLevel::Line,
+    };
+    for modifier in parts {
+        match modifier {
+            "before" => adjustment.base = AdjustmentBase::BeforeVat,
+            "after" => adjustment.base = AdjustmentBase::AfterVat,
+            "total" => adjustment.level = AdjustmentLevel::Total,
+            "line" => adjustment.level = AdjustmentLevel::Line,
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(adjustment)
+}
+
+fn parse_rounding_rule(input: &str) -> Result<RoundingRule, String> {
+    let invalid = || format!("Invalid rounding rule: {input}");
+    let (increment, mode) = match input.split_once(':') {
+        Some((increment, mode)) => (increment, RoundingMode::parse(mode).ok_or_else(invalid)?),
+        None => (input, RoundingMode::HalfUp),
+    };
+    let increment: f64 = increment.replace(',', ".").parse().map_err(|_| invalid())?;
+
+    if increment <= 0.0 {
+        return Err(invalid());
+    }
+
+    Ok(RoundingRule { increment, mode })
+}
+
+fn parse_percentage(flag: &str, input: &str) -> Result<f64, String> {
+    input
+        .replace(',', ".")
+        .parse()
+        .map_err(|_| format!("Invalid {flag} value: {input}"))
+}
+
+// A run computes one thing, so a second mode flag cannot silently replace the
+// first; repeating a flag is fine, and --lines may feed an --xrechnung invoice
+fn select_mode(mode_flag: &mut Option<&'static str>, flag: &'static str) -> Result<(), String> {
+    match *mode_flag {
+        None => *mode_flag = Some(flag),
+        Some(previous)
+            if previous == flag
+                || matches!(
+                    (previous, flag),
+                    ("--lines", "--xrechnung") | ("--xrechnung", "--lines")
+                ) => {}
+        Some(previous) => return Err(format!("Conflicting modes: {previous} and {flag}")),
+    }
+    Ok(())
+}
+
+fn parse_tolerance(flag: &str, input: &str) -> Result<f64, String> {
+    let tolerance = parse_percentage(flag, input)?;
+    if !tolerance.is_finite() {
+        return Err(format!("Invalid {flag} value: {input}"));
+    }
+    if tolerance < 0.0 {
+        return Err(format!("{flag} must not be negative"));
+    }
+    Ok(tolerance)
+}
+
 pub fn parse_arguments(args: Vec<String>) -> Result<ParsedArgs, String> {
     if args.len() < 2 {
         return Err("No arguments provided".to_string());
     }
 
     let mut vat_rate = get_default_vat_rate();
+    let mut known_rates = Vec::new();
+    let mut rate_tolerance = 0.1;
+    let mut tax_components = Vec::new();
+    let mut shares = Vec::new();
+    let mut adjustments = Vec::new();
+    let mut currency: Option<String> = None;
+    let mut rounding = None;
+    let mut rounding_mode = None;
+    let mut rounding_level = RoundingLevel::default();
+    let mut precision = None;
+    let mut convert_to = None;
+    let mut rates_file = get_default_rates_file();
+    let mut rate_date = None;
+    let mut mode = Mode::Vat;
+    let mut mode_flag = None;
+    let mut format = OutputFormat::Table;
+    let mut pairs = Vec::new();
+    let mut invoices = Vec::new();
+    let mut invoice_file = None;
+    let mut lines_file = None;
+    let mut einvoice_file = None;
+    let mut parties_file = None;
+    let mut statement_file = None;
+    let mut payee_rules = Vec::new();
+    let mut rules_file = None;
+    let mut accounts_file = None;
+    let mut column_map = ColumnMap::default();
+    let mut tolerance = 0.01;
     let mut numbers = Vec::new();
     let mut i = 1;
 
+    if args[1] == "verify" {
+        mode = Mode::Verify;
+        mode_flag = Some("verify");
+        i += 1;
+    }
+
     while i < args.len() {
         if args[i] == "--rate" {
             if i + 1 < args.len() {
@@ -58,6 +394,264 @@ pub fn parse_arguments(args: Vec<String>) -> Result<ParsedArgs, String> {
             } else {
                 return Err("--rate requires a value".to_string());
             }
+        } else if args[i] == "--tip" {
+            if i + 1 < args.len() {
+                let mut tip = parse_number(&args[i + 1])
+                    .ok_or_else(|| format!("Invalid --tip value: {}", args[i + 1]))?;
+                tip.treatment = TaxTreatment::Tip;
+                numbers.push(tip);
+                i += 2;
+            } else {
+                return Err("--tip requires a value".to_string());
+            }
+        } else if args[i] == "--tax" {
+            if i + 1 < args.len() {
+                tax_components.push(parse_tax_component(&args[i + 1])?);
+                i += 2;
+            } else {
+                return Err("--tax requires a value".to_string());
+            }
+        } else if args[i] == "--discount" || args[i] == "--surcharge" {
+            if i + 1 < args.len() {
+                let sign = if args[i] == "--discount" { -1.0 } else { 1.0 };
+                adjustments.push(parse_adjustment(&args[i + 1], sign)?);
+                i += 2;
+            } else {
+                return Err(format!("{} requires a value", args[i]));
+            }
+        } else if args[i] == "--currency" {
+            if i + 1 < args.len() {
+                currency = Some(args[i + 1].clone());
+                i += 2;
+            } else {
+                return Err("--currency requires a value".to_string());
+            }
+        } else if args[i] == "--rounding" {
+            if i + 1 < args.len() {
+                rounding = Some(parse_rounding_rule(&args[i + 1])?);
+                i += 2;
+            } else {
+                return Err("--rounding requires a value".to_string());
+            }
+        } else if args[i] == "--rounding-mode" {
+            if i + 1 < args.len() {
+                let mode = RoundingMode::parse(&args[i + 1])
+                    .ok_or_else(|| format!("Invalid rounding mode: {}", args[i + 1]))?;
+                rounding_mode = Some(mode);
+                i += 2;
+            } else {
+                return Err("--rounding-mode requires a value".to_string());
+            }
+        } else if args[i] == "--round-per" {
+            if i + 1 < args.len() {
+                rounding_level = RoundingLevel::parse(&args[i + 1])
+                    .ok_or_else(|| format!("Invalid rounding level: {}", args[i + 1]))?;
+                i += 2;
+            } else {
+                return Err("--round-per requires a value".to_string());
+            }
+        } else if args[i] == "--precision" {
+            if i + 1 < args.len() {
+                // Beyond 10 digits rounding runs out of f64 precision
+                let digits = args[i + 1]
+                    .parse()
+                    .ok()
+                    .filter(|digits| *digits <= 10)
+                    .ok_or_else(|| {
+                        format!("Invalid precision (expected 0 to 10): {}", args[i + 1])
+                    })?;
+                precision = Some(digits);
+                i += 2;
+            } else {
+                return Err("--precision requires a value".to_string());
+            }
+        } else if args[i] == "--convert-to" {
+            if i + 1 < args.len() {
+                convert_to = Some(args[i + 1].to_uppercase());
+                i += 2;
+            } else {
+                return Err("--convert-to requires a value".to_string());
+            }
+        } else if args[i] == "--rates" {
+            if i + 1 < args.len() {
+                rates_file = Some(args[i + 1].clone());
+                i += 2;
+            } else {
+                return Err("--rates requires a value".to_string());
+            }
+        } else if args[i] == "--rate-date" {
+            if i + 1 < args.len() {
+                rate_date = Some(
+                    parse_date(&args[i + 1])
+                        .ok_or_else(|| format!("Invalid --rate-date: {}", args[i + 1]))?,
+                );
+                i += 2;
+            } else {
+                return Err("--rate-date requires a value".to_string());
+            }
+        } else if args[i] == "--markup" {
+            if i + 1 < args.len() {
+                select_mode(&mut mode_flag, "--markup")?;
+                mode = Mode::Pricing(PricingTarget::Markup(parse_percentage(
+                    "--markup",
+                    &args[i + 1],
+                )?));
+                i += 2;
+            } else {
+                return Err("--markup requires a value".to_string());
+            }
+        } else if args[i] == "--margin" {
+            if i + 1 < args.len() {
+                let margin = parse_percentage("--margin", &args[i + 1])?;
+                if margin >= 100.0 {
+                    return Err("--margin must be below 100%".to_string());
+                }
+                select_mode(&mut mode_flag, "--margin")?;
+                mode = Mode::Pricing(PricingTarget::Margin(margin));
+                i += 2;
+            } else {
+                return Err("--margin requires a value".to_string());
+            }
+        } else if args[i] == "--margin-scheme" {
+            select_mode(&mut mode_flag, "--margin-scheme")?;
+            mode = Mode::MarginScheme;
+            i += 1;
+        } else if args[i] == "--solve-rate" {
+            select_mode(&mut mode_flag, "--solve-rate")?;
+            mode = Mode::SolveRate;
+            i += 1;
+        } else if args[i] == "--known-rate" {
+            if i + 1 < args.len() {
+                known_rates.push(parse_percentage("--known-rate", &args[i + 1])?);
+                i += 2;
+            } else {
+                return Err("--known-rate requires a value".to_string());
+            }
+        } else if args[i] == "--rate-tolerance" {
+            if i + 1 < args.len() {
+                rate_tolerance = parse_tolerance("--rate-tolerance", &args[i + 1])?;
+                i += 2;
+            } else {
+                return Err("--rate-tolerance requires a value".to_string());
+            }
+        } else if args[i] == "--file" {
+            if i + 1 < args.len() {
+                invoice_file = Some(args[i + 1].clone());
+                i += 2;
+            } else {
+                return Err("--file requires a value".to_string());
+            }
+        } else if args[i] == "--tolerance" {
+            if i + 1 < args.len() {
+                tolerance = parse_tolerance("--tolerance", &args[i + 1])?;
+                i += 2;
+            } else {
+                return Err("--tolerance requires a value".to_string());
+            }
+        } else if args[i] == "--installments" {
+            if i + 1 < args.len() {
+                let count: usize = args[i + 1]
+                    .parse()
+                    .map_err(|_| format!("Invalid --installments value: {}", args[i + 1]))?;
+                if count == 0 {
+                    return Err("--installments must be at least 1".to_string());
+                }
+                select_mode(&mut mode_flag, "--installments")?;
+                mode = Mode::Installments(count);
+                i += 2;
+            } else {
+                return Err("--installments requires a value".to_string());
+            }
+        } else if args[i] == "--share" {
+            if i + 1 < args.len() {
+                select_mode(&mut mode_flag, "--share")?;
+                shares.push(parse_share(&args[i + 1])?);
+                mode = Mode::Allocate;
+                i += 2;
+            } else {
+                return Err("--share requires a value".to_string());
+            }
+        } else if args[i] == "--lines" {
+            if i + 1 < args.len() {
+                select_mode(&mut mode_flag, "--lines")?;
+                lines_file = Some(args[i + 1].clone());
+                // Imported lines can also feed a generated invoice
+                if mode != Mode::XRechnung {
+                    mode = Mode::LineImport;
+                }
+                i += 2;
+            } else {
+                return Err("--lines requires a value".to_string());
+            }
+        } else if args[i] == "--einvoice" {
+            if i + 1 < args.len() {
+                select_mode(&mut mode_flag, "--einvoice")?;
+                einvoice_file = Some(args[i + 1].clone());
+                mode = Mode::EInvoice;
+                i += 2;
+            } else {
+                return Err("--einvoice requires a value".to_string());
+            }
+        } else if args[i] == "--xrechnung" {
+            if i + 1 < args.len() {
+                select_mode(&mut mode_flag, "--xrechnung")?;
+                parties_file = Some(args[i + 1].clone());
+                mode = Mode::XRechnung;
+                i += 2;
+            } else {
+                return Err("--xrechnung requires a value".to_string());
+            }
+        } else if args[i] == "--statement" {
+            if i + 1 < args.len() {
+                select_mode(&mut mode_flag, "--statement")?;
+                statement_file = Some(args[i + 1].clone());
+                mode = Mode::Statement;
+                i += 2;
+            } else {
+                return Err("--statement requires a value".to_string());
+            }
+        } else if args[i] == "--payee" {
+            if i + 1 < args.len() {
+                payee_rules.push(Rule::parse_payee(&args[i + 1])?);
+                i += 2;
+            } else {
+                return Err("--payee requires a value".to_string());
+            }
+        } else if args[i] == "--rules" {
+            if i + 1 < args.len() {
+                rules_file = Some(args[i + 1].clone());
+                i += 2;
+            } else {
+                return Err("--rules requires a value".to_string());
+            }
+        } else if args[i] == "--accounts" {
+            if i + 1 < args.len() {
+                accounts_file = Some(args[i + 1].clone());
+                i += 2;
+            } else {
+                return Err("--accounts requires a value".to_string());
+            }
+        } else if args[i] == "--map" {
+            if i + 1 < args.len() {
+                column_map = ColumnMap::parse(&args[i + 1])?;
+                i += 2;
+            } else {
+                return Err("--map requires a value".to_string());
+            }
+        } else if args[i] == "--format" {
+            if i + 1 < args.len() {
+                format = OutputFormat::parse(&args[i + 1])
+                    .ok_or_else(|| format!("Invalid output format: {}", args[i + 1]))?;
+                i += 2;
+            } else {
+                return Err("--format requires a value".to_string());
+            }
+        } else if let Some(invoice) = parse_invoice(&args[i]) {
+            invoices.push(invoice);
+            i += 1;
+        } else if let Some(pair) = parse_pair(&args[i]) {
+            pairs.push(pair);
+            i += 1;
         } else if let Some(number) = parse_number(&args[i]) {
             numbers.push(number);
             i += 1;
@@ -66,11 +660,125 @@ pub fn parse_arguments(args: Vec<String>) -> Result<ParsedArgs, String> {
         }
     }
 
-    if numbers.is_empty() {
+    if mode == Mode::MarginScheme {
+        if pairs.is_empty() {
+            return Err("No valid purchase/selling pairs provided".to_string());
+        }
+    } else if mode == Mode::SolveRate {
+        if pairs.is_empty() {
+            return Err("No valid gross/net pairs provided".to_string());
+        }
+    } else if mode == Mode::Verify {
+        if invoices.is_empty() && invoice_file.is_none() {
+            return Err("No invoices to verify provided".to_string());
+        }
+    } else if numbers.is_empty()
+        && !matches!(mode, Mode::LineImport | Mode::EInvoice | Mode::Statement)
+        // An XRechnung can be built from imported lines instead
+        && !(mode == Mode::XRechnung && lines_file.is_some())
+    {
         return Err("No valid numbers provided".to_string());
+    } else if matches!(mode, Mode::Installments(_)) && numbers.len() > 1 {
+        return Err("--installments takes a single gross amount".to_string());
+    } else if mode == Mode::Allocate {
+        if numbers.len() > 1 {
+            return Err("--share takes a single gross amount".to_string());
+        }
+        if shares.iter().map(|share| share.weight).sum::<f64>() <= 0.0 {
+            return Err("Share weights must add up to more than 0".to_string());
+        }
+    }
+
+    // Only the VAT table and the generated invoice have a label column
+    let labelled = numbers.iter().any(|number| number.label.is_some())
+        || pairs.iter().any(|pair| pair.label.is_some());
+    if labelled && !matches!(mode, Mode::Vat | Mode::XRechnung) {
+        return Err(
+            "Labels (Label=amount) are only shown for VAT calculations and --xrechnung".to_string(),
+        );
+    }
+
+    if format == OutputFormat::Datev {
+        if !matches!(mode, Mode::LineImport | Mode::Statement) {
+            return Err("--format datev needs --lines or --statement".to_string());
+        }
+        if accounts_file.is_none() {
+            return Err("--format datev requires --accounts".to_string());
+        }
+    }
+    if format.is_journal() && !matches!(mode, Mode::Vat | Mode::LineImport | Mode::Statement) {
+        return Err(
+            "--format hledger|ledger|beancount needs numbers, --lines or --statement".to_string(),
+        );
+    }
+    // Journals book every amount on its own, a total discount has no line to go to
+    if format.is_journal()
+        && adjustments
+            .iter()
+            .any(|adjustment| adjustment.level == AdjustmentLevel::Total)
+    {
+        return Err(
+            "--format hledger|ledger|beancount cannot book :total discounts or surcharges"
+                .to_string(),
+        );
+    }
+
+    if convert_to.is_some() {
+        if currency.is_none() {
+            return Err("--convert-to requires --currency".to_string());
+        }
+        if rates_file.is_none() {
+            return Err("--convert-to requires --rates or EXCHANGE_RATES_FILE".to_string());
+        }
+    } else if rate_date.is_some() {
+        return Err("--rate-date requires --convert-to".to_string());
     }
 
-    Ok(ParsedArgs { numbers, vat_rate })
+    Ok(ParsedArgs {
+        mode,
+        format,
+        numbers,
+        pairs,
+        invoices,
+        invoice_file,
+        lines_file,
+        einvoice_file,
+        parties_file,
+        statement_file,
+        payee_rules,
+        rules_file,
+        accounts_file,
+        column_map,
+        tolerance,
+        vat_rate,
+        known_rates: if known_rates.is_empty() {
+            GERMAN_VAT_RATES.to_vec()
+        } else {
+            known_rates
+        },
+        rate_tolerance,
+        tax_components,
+        shares,
+        adjustments,
+        // A currency's cash rounding follows the selected mode; an explicit
+        // --rounding rule brings its own.
+        rounding: rounding.or(currency
+            .as_deref()
+            .and_then(RoundingRule::for_currency)
+            .map(|rule| RoundingRule {
+                mode: rounding_mode.unwrap_or_default(),
+                ..rule
+            })),
+        rounding_mode: rounding_mode.unwrap_or_default(),
+        rounding_level,
+        precision: precision
+            .or(currency.as_deref().map(minor_units))
+            .unwrap_or(2),
+        currency: currency.map(|code| code.to_uppercase()),
+        convert_to,
+        rates_file,
+        rate_date,
+    })
 }
 
 #[cfg(test)]
@@ -91,11 +799,56 @@ mod tests {
         assert!(input.uses_comma);
     }
 
+    #[test]
+    fn test_parse_number_with_quantity() {
+        let input = parse_number("3x12,99:exempt").unwrap();
+        assert!((input.value - 38.97).abs() < 1e-9);
+        assert!(input.uses_comma);
+        assert_eq!(input.treatment, TaxTreatment::Exempt);
+        assert_eq!(
+            input.quantity,
+            Some(Quantity {
+                count: 3.0,
+                unit_price: 12.99
+            })
+        );
+
+        let input = parse_number("1,5×4.00").unwrap();
+        assert_eq!(input.value, 6.0);
+        assert!(!input.uses_comma);
+        assert!(parse_number("3*12.99").unwrap().quantity.is_some());
+        assert!(parse_number("123.45").unwrap().quantity.is_none());
+        assert!(parse_number("ax12,99").is_none());
+        assert!(parse_number("0x119").is_none());
+        assert!(parse_number("-2x10").is_none());
+        assert!(parse_number("NaNx10").is_none());
+        assert!(parse_number("infx10").is_none());
+        assert_eq!(parse_number("2x-10").unwrap().value, -20.0);
+    }
+
+    #[test]
+    fn test_parse_number_with_label() {
+        let input = parse_number("Taxi=23,80").unwrap();
+        assert_eq!(input.label.as_deref(), Some("Taxi"));
+        assert_eq!(input.value, 23.8);
+
+        let input = parse_number("a=b: Hotel=3x119:rc").unwrap();
+        assert_eq!(input.label.as_deref(), Some("a=b: Hotel"));
+        assert_eq!(input.treatment, TaxTreatment::ReverseCharge);
+        assert_eq!(input.value, 357.0);
+
+        assert_eq!(parse_number("=23,80").unwrap().label, None);
+        assert_eq!(parse_number("23,80").unwrap().label, None);
+        assert!(parse_number("Taxi=").is_none());
+    }
+
     #[test]
     fn test_parse_number_invalid() {
         assert!(parse_number("abc").is_none());
         assert!(parse_number("12.34.56").is_none());
         assert!(parse_number("").is_none());
+        assert!(parse_number("NaN").is_none());
+        assert!(parse_number("inf").is_none());
     }
 
     #[test]
@@ -116,6 +869,35 @@ mod tests {
         assert_eq!(input.value, 999999999.99);
     }
 
+    #[test]
+    fn test_parse_number_with_treatment() {
+        let input = parse_number("119,50:rc").unwrap();
+        assert_eq!(input.value, 119.5);
+        assert!(input.uses_comma);
+        assert_eq!(input.treatment, TaxTreatment::ReverseCharge);
+
+        let input = parse_number("100").unwrap();
+        assert_eq!(input.treatment, TaxTreatment::Standard);
+    }
+
+    #[test]
+    fn test_parse_number_unknown_treatment() {
+        assert!(parse_number("100:zero").is_none());
+        assert!(parse_number("abc:rc").is_none());
+    }
+
+    #[test]
+    fn test_parse_pair() {
+        let pair = parse_pair("800/1000,50").unwrap();
+        assert_eq!(pair.first, 800.0);
+        assert_eq!(pair.second, 1000.5);
+        assert!(pair.uses_comma);
+
+        assert!(parse_pair("800").is_none());
+        assert!(parse_pair("800/abc").is_none());
+        assert!(parse_pair("/1000").is_none());
+    }
+
     #[test]
     fn test_parse_vat_rate_with_comma() {
         assert_eq!(parse_vat_rate("7,5"), 7.5);
@@ -137,6 +919,919 @@ mod tests {
         assert_eq!(parse_vat_rate("-5"), -5.0);
     }
 
+    #[test]
+    fn test_parse_tax_component() {
+        let component = parse_tax_component("GST=5").unwrap();
+        assert_eq!(component.name, "GST");
+        assert_eq!(component.rate, 5.0);
+        assert!(!component.compound);
+
+        let component = parse_tax_component("QST=9,975:compound").unwrap();
+        assert_eq!(component.name, "QST");
+        assert_eq!(component.rate, 9.975);
+        assert!(component.compound);
+    }
+
+    #[test]
+    fn test_parse_tax_component_invalid() {
+        assert!(parse_tax_component("5").is_err());
+        assert!(parse_tax_component("=5").is_err());
+        assert!(parse_tax_component("GST=abc").is_err());
+        assert!(parse_tax_component("GST=5:stacked").is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_tax_components() {
+        let args = vec![
+            "program".to_string(),
+            "115.5".to_string(),
+            "--tax".to_string(),
+            "GST=5".to_string(),
+            "--tax".to_string(),
+            "QST=10:compound".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.tax_components.len(), 2);
+        assert_eq!(result.tax_components[0].name, "GST");
+        assert!(result.tax_components[1].compound);
+    }
+
+    #[test]
+    fn test_parse_arguments_tax_without_value() {
+        let args = vec![
+            "program".to_string(),
+            "100".to_string(),
+            "--tax".to_string(),
+        ];
+        let result = parse_arguments(args);
+        assert_eq!(result.unwrap_err(), "--tax requires a value");
+    }
+
+    #[test]
+    fn test_parse_adjustment_percentage() {
+        let adjustment = parse_adjustment("3%", -1.0).unwrap();
+        assert_eq!(adjustment.value, -3.0);
+        assert!(adjustment.is_percentage);
+        assert_eq!(adjustment.base, AdjustmentBase::AfterVat);
+        assert_eq!(adjustment.level, AdjustmentLevel::Line);
+    }
+
+    #[test]
+    fn test_parse_adjustment_modifiers() {
+        let adjustment = parse_adjustment("12,50:before:total", 1.0).unwrap();
+        assert_eq!(adjustment.value, 12.5);
+        assert!(!adjustment.is_percentage);
+        assert_eq!(adjustment.base, AdjustmentBase::BeforeVat);
+        assert_eq!(adjustment.level, AdjustmentLevel::Total);
+    }
+
+    #[test]
+    fn test_parse_adjustment_invalid() {
+        assert!(parse_adjustment("abc", -1.0).is_err());
+        assert!(parse_adjustment("3%:sometimes", -1.0).is_err());
+        assert!(parse_adjustment("", -1.0).is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_with_discount() {
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--discount".to_string(),
+            "3%".to_string(),
+            "--surcharge".to_string(),
+            "5:total".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.adjustments.len(), 2);
+        assert_eq!(result.adjustments[0].value, -3.0);
+        assert_eq!(result.adjustments[1].value, 5.0);
+        assert_eq!(result.adjustments[1].level, AdjustmentLevel::Total);
+    }
+
+    #[test]
+    fn test_parse_arguments_discount_without_value() {
+        let args = vec![
+            "program".to_string(),
+            "100".to_string(),
+            "--discount".to_string(),
+        ];
+        let result = parse_arguments(args);
+        assert_eq!(result.unwrap_err(), "--discount requires a value");
+    }
+
+    #[test]
+    fn test_parse_rounding_rule() {
+        let rule = parse_rounding_rule("0,05").unwrap();
+        assert_eq!(rule.increment, 0.05);
+        assert_eq!(rule.mode, RoundingMode::HalfUp);
+
+        let rule = parse_rounding_rule("1:floor").unwrap();
+        assert_eq!(rule.increment, 1.0);
+        assert_eq!(rule.mode, RoundingMode::Floor);
+    }
+
+    #[test]
+    fn test_parse_rounding_rule_invalid() {
+        assert!(parse_rounding_rule("abc").is_err());
+        assert!(parse_rounding_rule("0").is_err());
+        assert!(parse_rounding_rule("0.05:sideways").is_err());
+    }
+
+    #[test]
+    fn test_parse_arguments_currency_rounding() {
+        let args = vec![
+            "program".to_string(),
+            "108.1".to_string(),
+            "--currency".to_string(),
+            "CHF".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.rounding.unwrap().increment, 0.05);
+    }
+
+    #[test]
+    fn test_parse_arguments_rounding_overrides_currency() {
+        let args = vec![
+            "program".to_string(),
+            "108.1".to_string(),
+            "--rounding".to_string(),
+            "1:ceil".to_string(),
+            "--currency".to_string(),
+            "CHF".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        let rule = result.rounding.unwrap();
+        assert_eq!(rule.increment, 1.0);
+        assert_eq!(rule.mode, RoundingMode::Ceil);
+    }
+
+    #[test]
+    fn test_parse_arguments_no_rounding_by_default() {
+        let args = vec!["program".to_string(), "119".to_string()];
+        assert!(parse_arguments(args).unwrap().rounding.is_none());
+    }
+
+    #[test]
+    fn test_parse_arguments_rounding_mode() {
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--rounding-mode".to_string(),
+            "half-even".to_string(),
+            "--currency".to_string(),
+            "CHF".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.rounding_mode, RoundingMode::HalfEven);
+        assert_eq!(result.rounding.unwrap().mode, RoundingMode::HalfEven);
+    }
+
+    #[test]
+    fn test_parse_arguments_rounding_mode_defaults_to_half_up() {
+        let args = vec!["program".to_string(), "119".to_string()];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.rounding_mode, RoundingMode::HalfUp);
+    }
+
+    #[test]
+    fn test_parse_arguments_invalid_rounding_mode() {
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--rounding-mode".to_string(),
+            "sideways".to_string(),
+        ];
+        let result = parse_arguments(args);
+        assert_eq!(result.unwrap_err(), "Invalid rounding mode: sideways");
+    }
+
+    #[test]
+    fn test_parse_arguments_round_per() {
+        let args = vec![
+            "program".to_string(),
+            "3x12,99".to_string(),
+            "--round-per".to_string(),
+            "unit".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.rounding_level, RoundingLevel::Unit);
+
+        let args = vec!["program".to_string(), "3x12,99".to_string()];
+        assert_eq!(
+            parse_arguments(args).unwrap().rounding_level,
+            RoundingLevel::Line
+        );
+
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--round-per".to_string(),
+            "item".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Invalid rounding level: item"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_precision_from_currency() {
+        let args = vec![
+            "program".to_string(),
+            "1100".to_string(),
+            "--currency".to_string(),
+            "JPY".to_string(),
+        ];
+        assert_eq!(parse_arguments(args).unwrap().precision, 0);
+
+        let args = vec!["program".to_string(), "119".to_string()];
+        assert_eq!(parse_arguments(args).unwrap().precision, 2);
+    }
+
+    #[test]
+    fn test_parse_arguments_precision_overrides_currency() {
+        let args = vec![
+            "program".to_string(),
+            "1,789".to_string(),
+            "--precision".to_string(),
+            "3".to_string(),
+            "--currency".to_string(),
+            "EUR".to_string(),
+        ];
+        assert_eq!(parse_arguments(args).unwrap().precision, 3);
+    }
+
+    #[test]
+    fn test_parse_arguments_invalid_precision() {
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--precision".to_string(),
+            "-1".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Invalid precision (expected 0 to 10): -1"
+        );
+
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--precision".to_string(),
+            "400".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Invalid precision (expected 0 to 10): 400"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_conversion() {
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--currency".to_string(),
+            "usd".to_string(),
+            "--convert-to".to_string(),
+            "eur".to_string(),
+            "--rates".to_string(),
+            "eurofxref.csv".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.currency.as_deref(), Some("USD"));
+        assert_eq!(result.convert_to.as_deref(), Some("EUR"));
+        assert_eq!(result.rates_file.as_deref(), Some("eurofxref.csv"));
+        assert_eq!(result.rate_date, None);
+
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--currency".to_string(),
+            "USD".to_string(),
+            "--convert-to".to_string(),
+            "EUR".to_string(),
+            "--rates".to_string(),
+            "eurofxref-hist.csv".to_string(),
+            "--rate-date".to_string(),
+            "16.10.2025".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.rate_date.as_deref(), Some("2025-10-16"));
+
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--rate-date".to_string(),
+            "2025-10-16".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "--rate-date requires --convert-to"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_conversion_requires_currency() {
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--convert-to".to_string(),
+            "EUR".to_string(),
+            "--rates".to_string(),
+            "eurofxref.csv".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "--convert-to requires --currency"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_pricing() {
+        let args = vec![
+            "program".to_string(),
+            "49,99".to_string(),
+            "--margin".to_string(),
+            "30".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::Pricing(PricingTarget::Margin(30.0)));
+
+        let args = vec![
+            "program".to_string(),
+            "80".to_string(),
+            "--markup".to_string(),
+            "12,5".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::Pricing(PricingTarget::Markup(12.5)));
+    }
+
+    #[test]
+    fn test_parse_arguments_invalid_margin() {
+        let args = vec![
+            "program".to_string(),
+            "80".to_string(),
+            "--margin".to_string(),
+            "100".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "--margin must be below 100%"
+        );
+
+        let args = vec![
+            "program".to_string(),
+            "80".to_string(),
+            "--markup".to_string(),
+            "lots".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Invalid --markup value: lots"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_margin_scheme() {
+        let args = vec![
+            "program".to_string(),
+            "--margin-scheme".to_string(),
+            "800/1000".to_string(),
+            "500/450".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::MarginScheme);
+        assert_eq!(result.pairs.len(), 2);
+        assert_eq!(result.pairs[1].first, 500.0);
+        assert_eq!(result.pairs[1].second, 450.0);
+    }
+
+    #[test]
+    fn test_parse_arguments_margin_scheme_without_pairs() {
+        let args = vec![
+            "program".to_string(),
+            "1000".to_string(),
+            "--margin-scheme".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "No valid purchase/selling pairs provided"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_solve_rate() {
+        let args = vec![
+            "program".to_string(),
+            "--solve-rate".to_string(),
+            "119/100".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::SolveRate);
+        assert_eq!(result.known_rates, GERMAN_VAT_RATES.to_vec());
+        assert_eq!(result.rate_tolerance, 0.1);
+
+        let args = vec![
+            "program".to_string(),
+            "--solve-rate".to_string(),
+            "120/100".to_string(),
+            "--known-rate".to_string(),
+            "20".to_string(),
+            "--known-rate".to_string(),
+            "5,5".to_string(),
+            "--rate-tolerance".to_string(),
+            "0,5".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.known_rates, vec![20.0, 5.5]);
+        assert_eq!(result.rate_tolerance, 0.5);
+    }
+
+    #[test]
+    fn test_parse_arguments_negative_tolerances() {
+        let args = |extra: &[&str]| {
+            let mut args = vec!["program".to_string()];
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            parse_arguments(args)
+        };
+        assert_eq!(
+            args(&["--solve-rate", "119/100", "--rate-tolerance", "-0,5"]).unwrap_err(),
+            "--rate-tolerance must not be negative"
+        );
+        assert_eq!(
+            args(&["verify", "119/100/19", "--tolerance", "-0.01"]).unwrap_err(),
+            "--tolerance must not be negative"
+        );
+        assert_eq!(
+            args(&["verify", "119/100/19", "--tolerance", "NaN"]).unwrap_err(),
+            "Invalid --tolerance value: NaN"
+        );
+        assert_eq!(
+            args(&["verify", "119/100/19", "--tolerance", "0"])
+                .unwrap()
+                .tolerance,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_conflicting_modes() {
+        let args = |extra: &[&str]| {
+            let mut args = vec!["program".to_string()];
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            parse_arguments(args)
+        };
+        assert_eq!(
+            args(&["119", "--markup", "30", "--margin-scheme", "1/2"]).unwrap_err(),
+            "Conflicting modes: --markup and --margin-scheme"
+        );
+        assert_eq!(
+            args(&["100", "--margin", "20", "--markup", "30"]).unwrap_err(),
+            "Conflicting modes: --margin and --markup"
+        );
+        assert_eq!(
+            args(&["verify", "119/100/19", "--solve-rate"]).unwrap_err(),
+            "Conflicting modes: verify and --solve-rate"
+        );
+        assert_eq!(
+            args(&["--einvoice", "a.xml", "--statement", "b.xml"]).unwrap_err(),
+            "Conflicting modes: --einvoice and --statement"
+        );
+        assert_eq!(
+            args(&["100", "--markup", "20", "--markup", "30"])
+                .unwrap()
+                .mode,
+            Mode::Pricing(PricingTarget::Markup(30.0))
+        );
+        assert_eq!(
+            args(&["--lines", "a.csv", "--xrechnung", "p.toml"])
+                .unwrap()
+                .mode,
+            Mode::XRechnung
+        );
+        assert_eq!(
+            args(&["--xrechnung", "p.toml", "--lines", "a.csv"])
+                .unwrap()
+                .mode,
+            Mode::XRechnung
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_solve_rate_without_pairs() {
+        let args = vec!["program".to_string(), "--solve-rate".to_string()];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "No valid gross/net pairs provided"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_verify() {
+        let args = vec![
+            "program".to_string(),
+            "verify".to_string(),
+            "119/100/19".to_string(),
+            "107,00/100,00/7,00".to_string(),
+            "--tolerance".to_string(),
+            "0,02".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::Verify);
+        assert_eq!(result.invoices.len(), 2);
+        assert_eq!(result.invoices[0].vat, 19.0);
+        assert!(result.invoices[1].uses_comma);
+        assert_eq!(result.tolerance, 0.02);
+
+        let args = vec![
+            "program".to_string(),
+            "verify".to_string(),
+            "--file".to_string(),
+            "invoices.csv".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.invoice_file.as_deref(), Some("invoices.csv"));
+        assert!(result.invoices.is_empty());
+    }
+
+    #[test]
+    fn test_parse_arguments_verify_without_invoices() {
+        let args = vec!["program".to_string(), "verify".to_string()];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "No invoices to verify provided"
+        );
+    }
+
+    #[test]
+    fn test_parse_invoice() {
+        let invoice = parse_invoice("119/100/19").unwrap();
+        assert_eq!(invoice.gross, 119.0);
+        assert_eq!(invoice.net, 100.0);
+        assert_eq!(invoice.vat, 19.0);
+        assert_eq!(invoice.rate, None);
+        assert!(parse_invoice("119/100").is_none());
+        assert!(parse_invoice("119/100/19/1").is_none());
+    }
+
+    #[test]
+    fn test_parse_arguments_labels_outside_vat_mode() {
+        let args = |extra: &[&str]| {
+            let mut args = vec!["program".to_string()];
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            parse_arguments(args)
+        };
+        let error = "Labels (Label=amount) are only shown for VAT calculations and --xrechnung";
+        assert_eq!(args(&["Ring=80", "--markup", "30"]).unwrap_err(), error);
+        assert_eq!(
+            args(&["--margin-scheme", "Ring=800/1000"]).unwrap_err(),
+            error
+        );
+        assert_eq!(
+            args(&["Miete=1000", "--installments", "3"]).unwrap_err(),
+            error
+        );
+        assert_eq!(
+            args(&["Essen=250", "--share", "Sales=1"]).unwrap_err(),
+            error
+        );
+        assert!(args(&["Taxi=23,80"]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_arguments_installments() {
+        let args = vec![
+            "program".to_string(),
+            "1000".to_string(),
+            "--installments".to_string(),
+            "3".to_string(),
+        ];
+        assert_eq!(parse_arguments(args).unwrap().mode, Mode::Installments(3));
+
+        let args = vec![
+            "program".to_string(),
+            "1000".to_string(),
+            "--installments".to_string(),
+            "0".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "--installments must be at least 1"
+        );
+
+        let args = vec![
+            "program".to_string(),
+            "1000".to_string(),
+            "500".to_string(),
+            "--installments".to_string(),
+            "3".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "--installments takes a single gross amount"
+        );
+
+        let args = vec![
+            "program".to_string(),
+            "--lines".to_string(),
+            "receipts.csv".to_string(),
+            "--installments".to_string(),
+            "3".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Conflicting modes: --lines and --installments"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_shares() {
+        let args = vec![
+            "program".to_string(),
+            "250".to_string(),
+            "--share".to_string(),
+            "Sales=2".to_string(),
+            "--share".to_string(),
+            "Support=0,5".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::Allocate);
+        assert_eq!(result.shares.len(), 2);
+        assert_eq!(result.shares[0].name, "Sales");
+        assert_eq!(result.shares[1].weight, 0.5);
+
+        let args = vec![
+            "program".to_string(),
+            "250".to_string(),
+            "--share".to_string(),
+            "Sales=0".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Share weights must add up to more than 0"
+        );
+
+        let args = vec![
+            "program".to_string(),
+            "--lines".to_string(),
+            "receipts.csv".to_string(),
+            "--share".to_string(),
+            "Sales=1".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Conflicting modes: --lines and --share"
+        );
+    }
+
+    #[test]
+    fn test_parse_share() {
+        assert_eq!(
+            parse_share("CC-4711=1,5").unwrap(),
+            Share {
+                name: "CC-4711".to_string(),
+                weight: 1.5,
+            }
+        );
+        assert_eq!(parse_share("Sales").unwrap_err(), "Invalid share: Sales");
+        assert_eq!(parse_share("=1").unwrap_err(), "Invalid share: =1");
+        assert_eq!(
+            parse_share("Sales=-1").unwrap_err(),
+            "Invalid share: Sales=-1"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_tip() {
+        let args = vec![
+            "program".to_string(),
+            "45,90".to_string(),
+            "--tip".to_string(),
+            "4,10".to_string(),
+            "12:tip".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.numbers.len(), 3);
+        assert_eq!(result.numbers[0].treatment, TaxTreatment::Standard);
+        assert_eq!(result.numbers[1].value, 4.1);
+        assert_eq!(result.numbers[1].treatment, TaxTreatment::Tip);
+        assert_eq!(result.numbers[2].treatment, TaxTreatment::Tip);
+
+        let args = vec![
+            "program".to_string(),
+            "45,90".to_string(),
+            "--tip".to_string(),
+            "abc".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Invalid --tip value: abc"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_einvoice() {
+        let args = vec![
+            "program".to_string(),
+            "--einvoice".to_string(),
+            "invoice.xml".to_string(),
+            "--tolerance".to_string(),
+            "0,02".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::EInvoice);
+        assert_eq!(result.einvoice_file.as_deref(), Some("invoice.xml"));
+        assert_eq!(result.tolerance, 0.02);
+
+        let args = vec!["program".to_string(), "--einvoice".to_string()];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "--einvoice requires a value"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_xrechnung() {
+        let args = vec![
+            "program".to_string(),
+            "--xrechnung".to_string(),
+            "parties.toml".to_string(),
+            "Beratung=3x100".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::XRechnung);
+        assert_eq!(result.parties_file.as_deref(), Some("parties.toml"));
+        assert_eq!(result.numbers.len(), 1);
+
+        // Lines stay the invoice's source whichever flag comes first
+        let args = vec![
+            "program".to_string(),
+            "--lines".to_string(),
+            "lines.csv".to_string(),
+            "--xrechnung".to_string(),
+            "parties.toml".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::XRechnung);
+        assert_eq!(result.lines_file.as_deref(), Some("lines.csv"));
+
+        let args = vec![
+            "program".to_string(),
+            "--xrechnung".to_string(),
+            "parties.toml".to_string(),
+            "--lines".to_string(),
+            "lines.csv".to_string(),
+        ];
+        assert_eq!(parse_arguments(args).unwrap().mode, Mode::XRechnung);
+
+        let args = vec![
+            "program".to_string(),
+            "--xrechnung".to_string(),
+            "parties.toml".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "No valid numbers provided"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_statement() {
+        let args = vec![
+            "program".to_string(),
+            "--statement".to_string(),
+            "konto.sta".to_string(),
+            "--payee".to_string(),
+            "Deutsche Bahn=7".to_string(),
+            "--payee".to_string(),
+            "Miete=exempt".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::Statement);
+        assert_eq!(result.statement_file.as_deref(), Some("konto.sta"));
+        assert_eq!(result.payee_rules.len(), 2);
+        assert_eq!(result.payee_rules[0].rate, Some(7.0));
+        assert_eq!(result.rules_file, None);
+
+        let args = vec![
+            "program".to_string(),
+            "--lines".to_string(),
+            "belege.csv".to_string(),
+            "--rules".to_string(),
+            "rules.txt".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap().rules_file.as_deref(),
+            Some("rules.txt")
+        );
+
+        let args = vec![
+            "program".to_string(),
+            "--statement".to_string(),
+            "konto.sta".to_string(),
+            "--payee".to_string(),
+            "Bahn".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Invalid --payee value: Bahn"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_lines() {
+        let args = vec![
+            "program".to_string(),
+            "--lines".to_string(),
+            "receipts.csv".to_string(),
+            "--map".to_string(),
+            "amount=Betrag,rate=MwSt".to_string(),
+        ];
+        let result = parse_arguments(args).unwrap();
+        assert_eq!(result.mode, Mode::LineImport);
+        assert_eq!(result.lines_file.as_deref(), Some("receipts.csv"));
+        assert_eq!(result.column_map.amount.as_deref(), Some("Betrag"));
+        assert!(result.numbers.is_empty());
+
+        let args = vec![
+            "program".to_string(),
+            "--lines".to_string(),
+            "receipts.csv".to_string(),
+            "--map".to_string(),
+            "price=Betrag".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Unknown column mapping key: price"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_datev() {
+        let args = |extra: &[&str]| {
+            let mut args = vec!["program".to_string()];
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            parse_arguments(args)
+        };
+        let result = args(&[
+            "--statement",
+            "konto.xml",
+            "--format",
+            "datev",
+            "--accounts",
+            "accounts.toml",
+        ])
+        .unwrap();
+        assert_eq!(result.format, OutputFormat::Datev);
+        assert_eq!(result.accounts_file.as_deref(), Some("accounts.toml"));
+
+        assert_eq!(
+            args(&["--lines", "receipts.csv", "--format", "datev"]).unwrap_err(),
+            "--format datev requires --accounts"
+        );
+        assert_eq!(
+            args(&["119", "--format", "datev", "--accounts", "accounts.toml"]).unwrap_err(),
+            "--format datev needs --lines or --statement"
+        );
+
+        let result = args(&["119", "--format", "hledger"]).unwrap();
+        assert_eq!(result.format, OutputFormat::Hledger);
+        assert_eq!(result.accounts_file, None);
+        assert_eq!(
+            args(&["--solve-rate", "119/100", "--format", "beancount"]).unwrap_err(),
+            "--format hledger|ledger|beancount needs numbers, --lines or --statement"
+        );
+        assert_eq!(
+            args(&["119", "--discount", "2%:total", "--format", "ledger"]).unwrap_err(),
+            "--format hledger|ledger|beancount cannot book :total discounts or surcharges"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_format() {
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--format".to_string(),
+            "json".to_string(),
+        ];
+        assert_eq!(parse_arguments(args).unwrap().format, OutputFormat::Json);
+
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--format".to_string(),
+            "xml".to_string(),
+        ];
+        assert_eq!(
+            parse_arguments(args).unwrap_err(),
+            "Invalid output format: xml"
+        );
+    }
+
     #[test]
     fn test_parse_arguments_no_args() {
         let args = vec!["program".to_string()];
@@ -0,0 +1,236 @@
+use super::format_number;
+use crate::calculator::CalculationResult;
+use crate::invoice::accounts::AccountMap;
+use crate::invoice::lines::{ImportedLine, parse_date};
+use crate::rounding::RoundingMode;
+
+// The leading columns of the Buchungsstapel format; DATEV fills the rest with
+// their defaults
+const COLUMNS: [&str; 14] = [
+    "Umsatz (ohne Soll/Haben-Kz)",
+    "Soll/Haben-Kennzeichen",
+    "WKZ Umsatz",
+    "Kurs",
+    "Basis-Umsatz",
+    "WKZ Basis-Umsatz",
+    "Konto",
+    "Gegenkonto (ohne BU-Schlüssel)",
+    "BU-Schlüssel",
+    "Belegdatum",
+    "Belegfeld 1",
+    "Belegfeld 2",
+    "Skonto",
+    "Buchungstext",
+];
+
+fn text(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+// Writes an EXTF Buchungsstapel (format version 13) with one booking per
+// line: the gross on the line's account against the contra account, the BU
+// key deriving the input VAT. `created` is the export time as
+// YYYYMMDDHHMMSSmmm.
+#[allow(clippy::too_many_arguments)]
+pub fn create_datev_output(
+    lines: &[ImportedLine],
+    results: &[CalculationResult],
+    accounts: &AccountMap,
+    vat_rate: f64,
+    currency: &str,
+    created: &str,
+    precision: usize,
+    mode: RoundingMode,
+) -> Result<String, String> {
+    let dates = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let date = line.date.as_deref().ok_or_else(|| {
+                format!(
+                    "Line {} has no date for the DATEV export (use --map date=<column>)",
+                    index + 1
+                )
+            })?;
+            parse_date(date)
+                .ok_or_else(|| format!("Line {} has an invalid date: {date}", index + 1))
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+    let first = dates.iter().min().map(String::as_str).unwrap_or_default();
+    let last = dates.iter().max().map(String::as_str).unwrap_or_default();
+
+    // A batch lies within one fiscal year
+    let fiscal_year_start = match &accounts.fiscal_year_start {
+        Some(start) => start.clone(),
+        None => format!("{}-01-01", first.get(0..4).unwrap_or_default()),
+    };
+    let next_year: u32 = fiscal_year_start
+        .get(0..4)
+        .and_then(|year| year.parse().ok())
+        .map(|year: u32| year + 1)
+        .ok_or_else(|| format!("Invalid datev.fiscal_year_start: {fiscal_year_start}"))?;
+    let fiscal_year_end = format!("{next_year}{}", &fiscal_year_start[4..]);
+    if first < fiscal_year_start.as_str() || last >= fiscal_year_end.as_str() {
+        return Err(format!(
+            "DATEV batches cover one fiscal year from {fiscal_year_start}; the lines run from {first} to {last}"
+        ));
+    }
+
+    let compact = |date: &str| date.replace('-', "");
+    let header = [
+        text("EXTF"),
+        "700".to_string(),
+        "21".to_string(),
+        text("Buchungsstapel"),
+        "13".to_string(),
+        created.to_string(),
+        String::new(),
+        text("RE"),
+        text(""),
+        text(""),
+        accounts.consultant.clone(),
+        accounts.client.clone(),
+        compact(&fiscal_year_start),
+        accounts.account_length.to_string(),
+        compact(first),
+        compact(last),
+        text("remove-tax"),
+        text(""),
+        "1".to_string(),
+        "0".to_string(),
+        "0".to_string(),
+        text(currency),
+    ];
+    let mut rows = vec![header.join(";"), COLUMNS.map(text).join(";")];
+
+    for ((line, result), date) in lines.iter().zip(results).zip(&dates) {
+        let rate = line.rate.unwrap_or(vat_rate);
+        let key = accounts.tax_key(rate, result.treatment)?;
+        // Refunds are booked on the credit side
+        let side = if result.with_vat < 0.0 { "H" } else { "S" };
+        let booking_text: String = result
+            .label
+            .as_deref()
+            .or(line.description.as_deref())
+            .unwrap_or_default()
+            .chars()
+            .take(60)
+            .collect();
+        rows.push(
+            [
+                format_number(result.with_vat.abs(), true, precision, mode),
+                text(side),
+                text(currency),
+                String::new(),
+                String::new(),
+                String::new(),
+                accounts.account(line.category.as_deref()).to_string(),
+                accounts.contra_account.clone(),
+                text(key),
+                format!("{}{}", &date[8..10], &date[5..7]),
+                text(""),
+                text(""),
+                String::new(),
+                text(&booking_text),
+            ]
+            .join(";"),
+        );
+    }
+    Ok(rows.join("\r\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::process_lines;
+    use crate::invoice::accounts::parse_accounts;
+    use crate::invoice::lines::{ColumnMap, parse_lines};
+    use crate::rounding::RoundingLevel;
+
+    const ACCOUNTS: &str = "[datev]\nconsultant = 1234567\nclient = 10001\n[accounts]\ndefault = 4980\ncontra = 1200\n[categories]\nReisekosten = 4670\n";
+
+    fn sample_lines() -> Vec<ImportedLine> {
+        let csv = r#"description;amount;rate;date;treatment
+"DB ""Sparpreis""";53,50;7;03.10.2025;
+Papier;119,00;;06.10.2025;
+Cloud;100,00;;07.10.2025;rc
+"#;
+        let mut lines = parse_lines(csv, &ColumnMap::default()).unwrap().lines;
+        lines[0].category = Some("Reisekosten".to_string());
+        lines
+    }
+
+    #[test]
+    fn test_create_datev_output() {
+        let lines = sample_lines();
+        let results = process_lines(&lines, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        let accounts = parse_accounts(ACCOUNTS).unwrap();
+        let output = create_datev_output(
+            &lines,
+            &results,
+            &accounts,
+            19.0,
+            "EUR",
+            "20251019120000000",
+            2,
+            RoundingMode::HalfUp,
+        )
+        .unwrap();
+        let rows: Vec<&str> = output.split("\r\n").collect();
+        assert_eq!(
+            rows[0],
+            "\"EXTF\";700;21;\"Buchungsstapel\";13;20251019120000000;;\"RE\";\"\";\"\";1234567;10001;20250101;4;20251003;20251007;\"remove-tax\";\"\";1;0;0;\"EUR\""
+        );
+        assert!(rows[1].starts_with("\"Umsatz (ohne Soll/Haben-Kz)\";\"Soll/Haben-Kennzeichen\""));
+        assert_eq!(
+            rows[2],
+            "53,50;\"S\";\"EUR\";;;;4670;1200;\"8\";0310;\"\";\"\";;\"DB \"\"Sparpreis\"\"\""
+        );
+        assert_eq!(
+            rows[3],
+            "119,00;\"S\";\"EUR\";;;;4980;1200;\"9\";0610;\"\";\"\";;\"Papier\""
+        );
+        assert!(rows[4].starts_with("100,00;\"S\";\"EUR\";;;;4980;1200;\"94\";0710;"));
+    }
+
+    #[test]
+    fn test_create_datev_output_errors() {
+        let mut lines = sample_lines();
+        let accounts = parse_accounts(ACCOUNTS).unwrap();
+        let export = |lines: &[ImportedLine]| {
+            let results = process_lines(lines, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+            create_datev_output(
+                lines,
+                &results,
+                &accounts,
+                19.0,
+                "EUR",
+                "",
+                2,
+                RoundingMode::HalfUp,
+            )
+        };
+
+        lines[1].date = Some("2026-01-02".to_string());
+        assert_eq!(
+            export(&lines).unwrap_err(),
+            "DATEV batches cover one fiscal year from 2025-01-01; the lines run from 2025-10-03 to 2026-01-02"
+        );
+        lines[1].date = None;
+        assert_eq!(
+            export(&lines).unwrap_err(),
+            "Line 2 has no date for the DATEV export (use --map date=<column>)"
+        );
+        lines[1].date = Some("2025103".to_string());
+        assert_eq!(
+            export(&lines).unwrap_err(),
+            "Line 2 has an invalid date: 2025103"
+        );
+        lines[1].date = Some("2025-10-06".to_string());
+        lines[1].rate = Some(16.0);
+        assert_eq!(
+            export(&lines).unwrap_err(),
+            "No tax key for 16% (add it under [tax_keys])"
+        );
+    }
+}
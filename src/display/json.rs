@@ -0,0 +1,743 @@
+use crate::calculator::{
+    AdjustedAmounts, CalculationResult, EInvoiceCheck, MarginSchemeResult, PricingResult,
+    PricingTarget, RateSolution, Share, SplitPart, TaxComponent, TaxTreatment, VerificationResult,
+    calculate_receipt_total, total_results,
+};
+use crate::display::format_number;
+use crate::exchange::Conversion;
+use crate::invoice::EInvoice;
+use crate::invoice::lines::ImportedLine;
+use crate::invoice::statement::Statement;
+use crate::rounding::{RoundingMode, minor_units};
+use crate::utils::json::Json;
+
+// Amounts are emitted with the same rounding and precision as the table.
+// JSON has no NaN or infinity, so those become null.
+fn amount(value: f64, precision: usize, mode: RoundingMode) -> Json {
+    if value.is_finite() {
+        Json::Number(format_number(value, false, precision, mode))
+    } else {
+        Json::Null
+    }
+}
+
+fn adjusted_json(adjusted: &AdjustedAmounts, precision: usize, mode: RoundingMode) -> Json {
+    Json::object(vec![
+        ("with_vat", amount(adjusted.with_vat, precision, mode)),
+        ("without_vat", amount(adjusted.without_vat, precision, mode)),
+        (
+            "vat_correction",
+            amount(adjusted.vat_correction, precision, mode),
+        ),
+    ])
+}
+
+fn result_json(
+    result: &CalculationResult,
+    components: &[TaxComponent],
+    precision: usize,
+    mode: RoundingMode,
+) -> Json {
+    let mut fields = Vec::new();
+    if let Some(label) = &result.label {
+        fields.push(("label", Json::string(label)));
+    }
+    fields.extend([
+        ("with_vat", amount(result.with_vat, precision, mode)),
+        ("without_vat", amount(result.without_vat, precision, mode)),
+        (
+            "vat",
+            amount(result.with_vat - result.without_vat, precision, mode),
+        ),
+        ("treatment", Json::string(result.treatment.code())),
+    ]);
+    if let Some(quantity) = &result.quantity {
+        fields.push(("quantity", Json::plain_number(quantity.count)));
+        fields.push(("unit_price", amount(quantity.unit_price, precision, mode)));
+    }
+    if result.treatment == TaxTreatment::ReverseCharge {
+        fields.push((
+            "self_assessed_vat",
+            amount(result.self_assessed_vat, precision, mode),
+        ));
+    }
+    if !components.is_empty() {
+        let taxes = components
+            .iter()
+            .zip(&result.component_taxes)
+            .map(|(component, tax)| {
+                Json::object(vec![
+                    ("name", Json::string(&component.name)),
+                    ("amount", amount(*tax, precision, mode)),
+                ])
+            })
+            .collect();
+        fields.push(("taxes", Json::Array(taxes)));
+    }
+    if let Some(adjusted) = &result.adjusted {
+        fields.push(("adjusted", adjusted_json(adjusted, precision, mode)));
+    }
+    if let Some(converted) = &result.converted {
+        let converted_precision = minor_units(&converted.currency);
+        fields.push((
+            "converted",
+            Json::object(vec![
+                ("currency", Json::string(&converted.currency)),
+                (
+                    "without_vat",
+                    amount(converted.without_vat, converted_precision, mode),
+                ),
+                ("vat", amount(converted.vat, converted_precision, mode)),
+            ]),
+        ));
+    }
+    Json::object(fields)
+}
+
+pub fn create_json_output(
+    results: &[CalculationResult],
+    vat_rate: f64,
+    components: &[TaxComponent],
+    total: Option<&AdjustedAmounts>,
+    conversion: Option<&Conversion>,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut fields = Vec::new();
+    if components.is_empty() {
+        fields.push(("vat_rate", Json::plain_number(vat_rate)));
+    } else {
+        let components = components
+            .iter()
+            .map(|component| {
+                Json::object(vec![
+                    ("name", Json::string(&component.name)),
+                    ("rate", Json::plain_number(component.rate)),
+                    ("compound", Json::Bool(component.compound)),
+                ])
+            })
+            .collect();
+        fields.push(("tax_components", Json::Array(components)));
+    }
+    fields.push((
+        "results",
+        Json::Array(
+            results
+                .iter()
+                .map(|result| result_json(result, components, precision, mode))
+                .collect(),
+        ),
+    ));
+    if let Some(total) = total {
+        fields.push(("adjusted_total", adjusted_json(total, precision, mode)));
+    }
+    if let Some(receipt) = calculate_receipt_total(results) {
+        fields.push((
+            "receipt_total",
+            Json::object(vec![
+                ("gross", amount(receipt.gross, precision, mode)),
+                ("net", amount(receipt.net, precision, mode)),
+                ("vat", amount(receipt.vat, precision, mode)),
+                ("tip", amount(receipt.tip, precision, mode)),
+            ]),
+        ));
+    }
+    if let Some(conversion) = conversion {
+        fields.push((
+            "conversion",
+            Json::object(vec![
+                ("from", Json::string(&conversion.from)),
+                ("to", Json::string(&conversion.to)),
+                ("rate", Json::plain_number(conversion.rate)),
+                ("date", Json::string(&conversion.date)),
+            ]),
+        ));
+    }
+    Json::object(fields).render()
+}
+
+pub fn create_pricing_json_output(
+    results: &[PricingResult],
+    target: PricingTarget,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let (target_name, target_value) = match target {
+        PricingTarget::Markup(markup) => ("markup", markup),
+        PricingTarget::Margin(margin) => ("margin", margin),
+    };
+    let items = results
+        .iter()
+        .map(|result| {
+            Json::object(vec![
+                ("cost", amount(result.cost, precision, mode)),
+                ("net_price", amount(result.net_price, precision, mode)),
+                ("vat", amount(result.vat, precision, mode)),
+                ("gross_price", amount(result.gross_price, precision, mode)),
+                ("margin", amount(result.margin, precision, mode)),
+            ])
+        })
+        .collect();
+
+    Json::object(vec![
+        ("vat_rate", Json::plain_number(vat_rate)),
+        (target_name, Json::plain_number(target_value)),
+        ("results", Json::Array(items)),
+    ])
+    .render()
+}
+
+fn margin_scheme_json(result: &MarginSchemeResult, precision: usize, mode: RoundingMode) -> Json {
+    Json::object(vec![
+        ("purchase", amount(result.purchase, precision, mode)),
+        ("selling", amount(result.selling, precision, mode)),
+        ("margin", amount(result.margin, precision, mode)),
+        ("vat", amount(result.vat, precision, mode)),
+        (
+            "net_margin",
+            amount(result.margin - result.vat, precision, mode),
+        ),
+    ])
+}
+
+pub fn create_margin_scheme_json_output(
+    results: &[MarginSchemeResult],
+    total: &MarginSchemeResult,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    Json::object(vec![
+        ("vat_rate", Json::plain_number(vat_rate)),
+        (
+            "items",
+            Json::Array(
+                results
+                    .iter()
+                    .map(|result| margin_scheme_json(result, precision, mode))
+                    .collect(),
+            ),
+        ),
+        ("total", margin_scheme_json(total, precision, mode)),
+    ])
+    .render()
+}
+
+fn split_part_json(part: &SplitPart, precision: usize, mode: RoundingMode) -> Json {
+    Json::object(vec![
+        ("gross", amount(part.gross, precision, mode)),
+        ("net", amount(part.net, precision, mode)),
+        ("vat", amount(part.vat, precision, mode)),
+    ])
+}
+
+pub fn create_installment_json_output(
+    installments: &[SplitPart],
+    total: &SplitPart,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    Json::object(vec![
+        ("vat_rate", Json::plain_number(vat_rate)),
+        (
+            "installments",
+            Json::Array(
+                installments
+                    .iter()
+                    .map(|installment| split_part_json(installment, precision, mode))
+                    .collect(),
+            ),
+        ),
+        ("total", split_part_json(total, precision, mode)),
+    ])
+    .render()
+}
+
+pub fn create_allocation_json_output(
+    shares: &[Share],
+    parts: &[SplitPart],
+    total: &SplitPart,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let items = shares
+        .iter()
+        .zip(parts)
+        .map(|(share, part)| {
+            Json::object(vec![
+                ("name", Json::string(&share.name)),
+                ("weight", Json::plain_number(share.weight)),
+                ("gross", amount(part.gross, precision, mode)),
+                ("net", amount(part.net, precision, mode)),
+                ("vat", amount(part.vat, precision, mode)),
+            ])
+        })
+        .collect();
+
+    Json::object(vec![
+        ("vat_rate", Json::plain_number(vat_rate)),
+        ("shares", Json::Array(items)),
+        ("total", split_part_json(total, precision, mode)),
+    ])
+    .render()
+}
+
+pub fn create_rate_solution_json_output(
+    results: &[RateSolution],
+    known_rates: &[f64],
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let items = results
+        .iter()
+        .map(|result| {
+            let mut fields = vec![
+                ("gross", amount(result.gross, precision, mode)),
+                ("net", amount(result.net, precision, mode)),
+                ("vat", amount(result.gross - result.net, precision, mode)),
+            ];
+            if let Some(rate) = result.implied_rate {
+                fields.push(("implied_rate", amount(rate, 2, mode)));
+            }
+            if let Some(rate) = result.matched_rate {
+                fields.push(("known_rate", Json::plain_number(rate)));
+            }
+            fields.push(("matches", Json::Bool(result.matched_rate.is_some())));
+            Json::object(fields)
+        })
+        .collect();
+
+    Json::object(vec![
+        (
+            "known_rates",
+            Json::Array(
+                known_rates
+                    .iter()
+                    .map(|rate| Json::plain_number(*rate))
+                    .collect(),
+            ),
+        ),
+        ("tolerance", Json::plain_number(tolerance)),
+        ("results", Json::Array(items)),
+    ])
+    .render()
+}
+
+pub fn create_verification_json_output(
+    results: &[VerificationResult],
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let items = results
+        .iter()
+        .map(|result| {
+            Json::object(vec![
+                ("gross", amount(result.gross, precision, mode)),
+                ("net", amount(result.net, precision, mode)),
+                ("vat", amount(result.vat, precision, mode)),
+                ("rate", Json::plain_number(result.rate)),
+                ("expected_net", amount(result.expected_net, precision, mode)),
+                ("expected_vat", amount(result.expected_vat, precision, mode)),
+                ("passed", Json::Bool(result.passed)),
+            ])
+        })
+        .collect();
+
+    Json::object(vec![
+        ("tolerance", Json::plain_number(tolerance)),
+        (
+            "passed",
+            Json::Bool(results.iter().all(|result| result.passed)),
+        ),
+        ("results", Json::Array(items)),
+    ])
+    .render()
+}
+
+pub fn create_einvoice_json_output(
+    invoice: &EInvoice,
+    results: &[CalculationResult],
+    check: &EInvoiceCheck,
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let lines = invoice
+        .lines
+        .iter()
+        .zip(results)
+        .map(|(line, result)| {
+            let mut json = result_json(result, &[], precision, mode);
+            if let Json::Object(fields) = &mut json {
+                fields.push(("category".to_string(), Json::string(&line.category)));
+                fields.push(("rate".to_string(), Json::plain_number(line.rate)));
+            }
+            json
+        })
+        .collect();
+    let subtotals = check
+        .subtotals
+        .iter()
+        .map(|subtotal| {
+            Json::object(vec![
+                ("category", Json::string(&subtotal.category)),
+                ("rate", Json::plain_number(subtotal.rate)),
+                ("basis", amount(subtotal.basis, precision, mode)),
+                ("vat", amount(subtotal.vat, precision, mode)),
+                (
+                    "expected_basis",
+                    amount(subtotal.expected_basis, precision, mode),
+                ),
+                (
+                    "expected_vat",
+                    amount(subtotal.expected_vat, precision, mode),
+                ),
+                ("passed", Json::Bool(subtotal.passed)),
+            ])
+        })
+        .collect();
+
+    Json::object(vec![
+        ("number", Json::string(&invoice.number)),
+        ("currency", Json::string(&invoice.currency)),
+        ("tolerance", Json::plain_number(tolerance)),
+        ("passed", Json::Bool(check.passed)),
+        ("lines", Json::Array(lines)),
+        ("tax_subtotals", Json::Array(subtotals)),
+        (
+            "declared_totals",
+            split_part_json(&check.declared, precision, mode),
+        ),
+        (
+            "expected_totals",
+            split_part_json(&check.expected, precision, mode),
+        ),
+    ])
+    .render()
+}
+
+pub fn create_statement_json_output(
+    statement: &Statement,
+    lines: &[ImportedLine],
+    results: &[CalculationResult],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let payments = statement
+        .debits()
+        .zip(lines.iter().zip(results))
+        .map(|(entry, (line, result))| {
+            let mut json = result_json(result, &[], precision, mode);
+            if let Json::Object(fields) = &mut json {
+                fields.push((
+                    "booking_date".to_string(),
+                    Json::string(&entry.booking_date),
+                ));
+                fields.push((
+                    "counterparty".to_string(),
+                    Json::string(&entry.counterparty),
+                ));
+                fields.push(("purpose".to_string(), Json::string(&entry.purpose)));
+                fields.push((
+                    "rate".to_string(),
+                    Json::plain_number(line.rate.unwrap_or(vat_rate)),
+                ));
+                if let Some(category) = &line.category {
+                    fields.push(("category".to_string(), Json::string(category)));
+                }
+            }
+            json
+        })
+        .collect();
+
+    let mut fields = Vec::new();
+    if let Some(account) = &statement.account {
+        fields.push(("account", Json::string(account)));
+    }
+    fields.extend([
+        ("currency", Json::string(&statement.currency)),
+        ("payments", Json::Array(payments)),
+        (
+            "skipped_credits",
+            Json::plain_number((statement.entries.len() - results.len()) as f64),
+        ),
+        (
+            "total",
+            split_part_json(&total_results(results), precision, mode),
+        ),
+    ]);
+    Json::object(fields).render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::ConvertedAmounts;
+
+    #[test]
+    fn test_non_finite_numbers_are_null() {
+        assert_eq!(amount(f64::NAN, 2, RoundingMode::HalfUp), Json::Null);
+        assert_eq!(amount(f64::INFINITY, 2, RoundingMode::HalfUp), Json::Null);
+    }
+
+    #[test]
+    fn test_create_json_output() {
+        let results = vec![CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            uses_comma: true,
+            converted: Some(ConvertedAmounts {
+                currency: "JPY".to_string(),
+                without_vat: 17623.0,
+                vat: 3348.0,
+            }),
+            ..Default::default()
+        }];
+        let conversion = Conversion {
+            from: "EUR".to_string(),
+            to: "JPY".to_string(),
+            rate: 176.23,
+            date: "2025-10-17".to_string(),
+        };
+        let json = create_json_output(
+            &results,
+            19.0,
+            &[],
+            None,
+            Some(&conversion),
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(json.contains("\"vat_rate\": 19,"));
+        assert!(json.contains("\"with_vat\": 119.00,"));
+        assert!(json.contains("\"vat\": 19.00,"));
+        assert!(json.contains("\"treatment\": \"standard\""));
+        assert!(json.contains("\"without_vat\": 17623,"));
+        assert!(json.contains("\"date\": \"2025-10-17\""));
+    }
+
+    #[test]
+    fn test_create_json_output_label() {
+        let results = vec![CalculationResult {
+            with_vat: 23.8,
+            without_vat: 20.0,
+            label: Some("Taxi \"Nord\"".to_string()),
+            ..Default::default()
+        }];
+        let json = create_json_output(&results, 19.0, &[], None, None, 2, RoundingMode::HalfUp);
+        assert!(
+            json.contains("{\n      \"label\": \"Taxi \\\"Nord\\\"\",\n      \"with_vat\": 23.80,")
+        );
+    }
+
+    #[test]
+    fn test_create_einvoice_json_output() {
+        let invoice = EInvoice {
+            number: "R-1".to_string(),
+            currency: "EUR".to_string(),
+            lines: vec![crate::invoice::EInvoiceLine {
+                label: Some("Beratung".to_string()),
+                quantity: 2.0,
+                net: 100.0,
+                category: "S".to_string(),
+                rate: 19.0,
+            }],
+            subtotals: Vec::new(),
+            net_total: 100.0,
+            vat_total: 19.0,
+            gross_total: 119.0,
+        };
+        let results = vec![CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            label: Some("Beratung".to_string()),
+            ..Default::default()
+        }];
+        let check = EInvoiceCheck {
+            subtotals: vec![crate::calculator::SubtotalCheck {
+                category: "S".to_string(),
+                rate: 19.0,
+                basis: 0.0,
+                vat: 0.0,
+                expected_basis: 100.0,
+                expected_vat: 19.0,
+                passed: false,
+            }],
+            declared: SplitPart::default(),
+            expected: SplitPart::default(),
+            passed: false,
+        };
+        let json =
+            create_einvoice_json_output(&invoice, &results, &check, 0.01, 2, RoundingMode::HalfUp);
+        assert!(json.contains("\"number\": \"R-1\","));
+        assert!(json.contains("\"passed\": false,"));
+        assert!(json.contains("\"category\": \"S\",\n      \"rate\": 19"));
+        assert!(json.contains("\"expected_basis\": 100.00,"));
+    }
+
+    #[test]
+    fn test_create_json_output_components() {
+        let results = vec![CalculationResult {
+            with_vat: 115.5,
+            without_vat: 100.0,
+            component_taxes: vec![5.0, 10.5],
+            ..Default::default()
+        }];
+        let components = vec![
+            TaxComponent {
+                name: "GST".to_string(),
+                rate: 5.0,
+                compound: false,
+            },
+            TaxComponent {
+                name: "QST".to_string(),
+                rate: 10.0,
+                compound: true,
+            },
+        ];
+        let json = create_json_output(
+            &results,
+            19.0,
+            &components,
+            None,
+            None,
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(!json.contains("vat_rate"));
+        assert!(json.contains("\"compound\": true"));
+        assert!(json.contains("\"name\": \"QST\",\n          \"amount\": 10.50"));
+    }
+
+    #[test]
+    fn test_create_margin_scheme_json_output() {
+        let results = vec![MarginSchemeResult {
+            purchase: 800.0,
+            selling: 1000.0,
+            margin: 200.0,
+            vat: 31.93,
+            uses_comma: false,
+        }];
+        let json =
+            create_margin_scheme_json_output(&results, &results[0], 19.0, 2, RoundingMode::HalfUp);
+        assert!(json.contains("\"net_margin\": 168.07"));
+        assert!(json.contains("\"total\": {"));
+    }
+
+    #[test]
+    fn test_create_pricing_json_output() {
+        let results = vec![PricingResult {
+            cost: 80.0,
+            net_price: 100.0,
+            gross_price: 119.0,
+            margin: 20.0,
+            vat: 19.0,
+            uses_comma: true,
+        }];
+        let json = create_pricing_json_output(
+            &results,
+            PricingTarget::Margin(20.0),
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(json.contains("\"margin\": 20,"));
+        assert!(json.contains("\"gross_price\": 119.00,"));
+    }
+
+    #[test]
+    fn test_create_rate_solution_json_output() {
+        let results = vec![
+            RateSolution {
+                gross: 119.0,
+                net: 100.0,
+                implied_rate: Some(19.0),
+                matched_rate: Some(19.0),
+                uses_comma: false,
+            },
+            RateSolution {
+                gross: 10.0,
+                net: 0.0,
+                implied_rate: None,
+                matched_rate: None,
+                uses_comma: false,
+            },
+        ];
+        let json =
+            create_rate_solution_json_output(&results, &[19.0, 7.0], 0.1, 2, RoundingMode::HalfUp);
+        assert!(json.contains("\"tolerance\": 0.1"));
+        assert!(json.contains("\"implied_rate\": 19.00,\n      \"known_rate\": 19,"));
+        assert!(json.contains("\"matches\": true"));
+        assert!(json.contains("\"vat\": 10.00,\n      \"matches\": false"));
+    }
+
+    #[test]
+    fn test_create_verification_json_output() {
+        let results = vec![VerificationResult {
+            gross: 119.0,
+            net: 100.0,
+            vat: 7.0,
+            rate: 19.0,
+            expected_net: 100.0,
+            expected_vat: 19.0,
+            passed: false,
+            uses_comma: false,
+        }];
+        let json = create_verification_json_output(&results, 0.01, 2, RoundingMode::HalfUp);
+        assert!(json.starts_with("{\n  \"tolerance\": 0.01,\n  \"passed\": false,"));
+        assert!(json.contains("\"expected_vat\": 19.00,\n      \"passed\": false"));
+    }
+
+    #[test]
+    fn test_create_installment_json_output() {
+        let installments = vec![SplitPart {
+            gross: 333.34,
+            net: 280.12,
+            vat: 53.22,
+            uses_comma: true,
+        }];
+        let json = create_installment_json_output(
+            &installments,
+            &installments[0],
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(json.contains("\"installments\": [\n    {\n      \"gross\": 333.34,"));
+        assert!(json.contains(
+            "\"total\": {\n    \"gross\": 333.34,\n    \"net\": 280.12,\n    \"vat\": 53.22"
+        ));
+    }
+
+    #[test]
+    fn test_create_allocation_json_output() {
+        let shares = vec![Share {
+            name: "Sales".to_string(),
+            weight: 2.0,
+        }];
+        let parts = vec![SplitPart {
+            gross: 166.67,
+            net: 140.06,
+            vat: 26.61,
+            uses_comma: false,
+        }];
+        let json = create_allocation_json_output(
+            &shares,
+            &parts,
+            &parts[0],
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(
+            json.contains("\"name\": \"Sales\",\n      \"weight\": 2,\n      \"gross\": 166.67,")
+        );
+        assert!(json.contains("\"total\": {\n    \"gross\": 166.67,"));
+    }
+}
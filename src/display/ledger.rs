@@ -0,0 +1,268 @@
+use super::{OutputFormat, format_number};
+use crate::calculator::{CalculationResult, TaxTreatment};
+use crate::invoice::accounts::LedgerAccounts;
+use crate::invoice::lines::ImportedLine;
+use crate::rounding::{RoundingMode, round_to_precision};
+
+const BEANCOUNT_ROOTS: [&str; 5] = ["Assets", "Liabilities", "Equity", "Income", "Expenses"];
+
+struct Transaction<'a> {
+    date: &'a str,
+    payee: Option<&'a str>,
+    narration: &'a str,
+    postings: Vec<(&'a str, f64)>,
+}
+
+// Beancount names start with one of the five root types; every component
+// starts with a capital letter or digit
+fn check_beancount_account(name: &str) -> Result<(), String> {
+    let mut components = name.split(':');
+    let root_valid = components
+        .next()
+        .is_some_and(|root| BEANCOUNT_ROOTS.contains(&root));
+    let valid = root_valid
+        && components.all(|component| {
+            component
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_uppercase() || c.is_ascii_digit())
+                && component.chars().all(|c| c.is_alphanumeric() || c == '-')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid beancount account name: {name}"))
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// The net goes to the expense account and the VAT to the input-tax account;
+// reverse-charge VAT is both claimed and owed. Untaxed amounts are booked in
+// full.
+fn postings<'a>(
+    result: &CalculationResult,
+    expense: &'a str,
+    accounts: &'a LedgerAccounts,
+    precision: usize,
+    mode: RoundingMode,
+) -> Vec<(&'a str, f64)> {
+    let round = |value: f64| round_to_precision(value, precision, mode);
+    let gross = round(result.with_vat);
+    let mut postings = match result.treatment {
+        TaxTreatment::Standard => {
+            let net = round(result.without_vat);
+            vec![(expense, net), (accounts.input_vat.as_str(), gross - net)]
+        }
+        TaxTreatment::ReverseCharge => {
+            let vat = round(result.self_assessed_vat);
+            vec![
+                (expense, gross),
+                (accounts.input_vat.as_str(), vat),
+                (accounts.reverse_charge.as_str(), -vat),
+            ]
+        }
+        TaxTreatment::Exempt | TaxTreatment::OutOfScope | TaxTreatment::Tip => {
+            vec![(expense, gross)]
+        }
+    };
+    postings.retain(|(_, amount)| round(*amount) != 0.0);
+    postings.push((accounts.payment.as_str(), -gross));
+    postings
+}
+
+// One balanced transaction per result in hledger, ledger or beancount
+// syntax. Imported lines bring their date, counterparty, description and
+// category; everything else is booked on `today`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_ledger_output(
+    format: OutputFormat,
+    lines: Option<&[ImportedLine]>,
+    results: &[CalculationResult],
+    accounts: &LedgerAccounts,
+    currency: &str,
+    today: &str,
+    precision: usize,
+    mode: RoundingMode,
+) -> Result<String, String> {
+    let transactions: Vec<Transaction> = results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| {
+            let line = lines.and_then(|lines| lines.get(index));
+            let date = line.and_then(|line| line.date.as_deref()).unwrap_or(today);
+            let payee = line.and_then(|line| line.counterparty.as_deref());
+            let narration = line
+                .and_then(|line| line.description.as_deref())
+                .or(result.label.as_deref())
+                .unwrap_or("Expense");
+            let expense = accounts.account(line.and_then(|line| line.category.as_deref()));
+            Transaction {
+                date,
+                payee,
+                narration,
+                postings: postings(result, expense, accounts, precision, mode),
+            }
+        })
+        .collect();
+
+    let mut blocks = Vec::new();
+    if format == OutputFormat::Beancount {
+        // Accounts must be opened before their first posting
+        let mut used: Vec<&str> = transactions
+            .iter()
+            .flat_map(|transaction| transaction.postings.iter().map(|(account, _)| *account))
+            .collect();
+        used.sort();
+        used.dedup();
+        used.iter()
+            .try_for_each(|account| check_beancount_account(account))?;
+        let first = transactions
+            .iter()
+            .map(|transaction| transaction.date)
+            .min()
+            .unwrap_or(today);
+        blocks.push(
+            used.iter()
+                .map(|account| format!("{first} open {account}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+
+    for transaction in &transactions {
+        let header = match (format, transaction.payee) {
+            (OutputFormat::Beancount, Some(payee)) => format!(
+                "{} * {} {}",
+                transaction.date,
+                quote(payee),
+                quote(transaction.narration)
+            ),
+            (OutputFormat::Beancount, None) => {
+                format!("{} * {}", transaction.date, quote(transaction.narration))
+            }
+            // ledger reads the whole description as the payee and keeps
+            // the narration as a note
+            (OutputFormat::Ledger, Some(payee)) => format!(
+                "{} * {payee}\n    ; {}",
+                transaction.date.replace('-', "/"),
+                transaction.narration
+            ),
+            (OutputFormat::Ledger, None) => format!(
+                "{} * {}",
+                transaction.date.replace('-', "/"),
+                transaction.narration
+            ),
+            (_, Some(payee)) => {
+                format!("{} * {payee} | {}", transaction.date, transaction.narration)
+            }
+            (_, None) => format!("{} * {}", transaction.date, transaction.narration),
+        };
+        let width = transaction
+            .postings
+            .iter()
+            .map(|(account, _)| account.chars().count())
+            .max()
+            .unwrap_or_default();
+        let postings = transaction.postings.iter().map(|(account, amount)| {
+            let amount = format_number(*amount, false, precision, mode);
+            format!("    {account:<width$}  {amount:>12} {currency}")
+        });
+        blocks.push(
+            std::iter::once(header)
+                .chain(postings)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+    Ok(blocks.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::process_lines;
+    use crate::invoice::lines::{ColumnMap, parse_lines};
+    use crate::rounding::RoundingLevel;
+
+    fn sample_lines() -> Vec<ImportedLine> {
+        let csv = "date;description;amount;rate;treatment\n2025-10-03;Ticket 4711;53,50;7;\n;Cloud;100,00;;rc\n";
+        let mut lines = parse_lines(csv, &ColumnMap::default()).unwrap().lines;
+        lines[0].counterparty = Some("DB Fernverkehr AG".to_string());
+        lines[0].category = Some("Reisekosten".to_string());
+        lines
+    }
+
+    fn journal(format: OutputFormat, accounts: &LedgerAccounts) -> Result<String, String> {
+        let lines = sample_lines();
+        let results = process_lines(&lines, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        create_ledger_output(
+            format,
+            Some(&lines),
+            &results,
+            accounts,
+            "EUR",
+            "2025-10-19",
+            2,
+            RoundingMode::HalfUp,
+        )
+    }
+
+    fn accounts() -> LedgerAccounts {
+        let mut accounts = LedgerAccounts::default();
+        accounts
+            .categories
+            .insert("Reisekosten".to_string(), "Expenses:Travel".to_string());
+        accounts
+    }
+
+    #[test]
+    fn test_create_hledger_output() {
+        assert_eq!(
+            journal(OutputFormat::Hledger, &accounts()).unwrap(),
+            "2025-10-03 * DB Fernverkehr AG | Ticket 4711
+    Expenses:Travel          50.00 EUR
+    Assets:VAT:Input          3.50 EUR
+    Assets:Bank             -53.50 EUR
+
+2025-10-19 * Cloud
+    Expenses:General                     100.00 EUR
+    Assets:VAT:Input                      19.00 EUR
+    Liabilities:VAT:ReverseCharge        -19.00 EUR
+    Assets:Bank                         -100.00 EUR"
+        );
+    }
+
+    #[test]
+    fn test_create_ledger_output() {
+        let output = journal(OutputFormat::Ledger, &accounts()).unwrap();
+        assert!(output.starts_with("2025/10/03 * DB Fernverkehr AG\n    ; Ticket 4711\n"));
+        assert!(output.contains("\n\n2025/10/19 * Cloud\n"));
+    }
+
+    #[test]
+    fn test_create_beancount_output() {
+        let output = journal(OutputFormat::Beancount, &accounts()).unwrap();
+        assert!(output.starts_with(
+            "2025-10-03 open Assets:Bank
+2025-10-03 open Assets:VAT:Input
+2025-10-03 open Expenses:General
+2025-10-03 open Expenses:Travel
+2025-10-03 open Liabilities:VAT:ReverseCharge
+
+2025-10-03 * \"DB Fernverkehr AG\" \"Ticket 4711\"
+    Expenses:Travel          50.00 EUR
+"
+        ));
+        assert!(output.contains("\n\n2025-10-19 * \"Cloud\"\n"));
+
+        let mut accounts = accounts();
+        accounts.payment = "Bank:Girokonto".to_string();
+        assert_eq!(
+            journal(OutputFormat::Beancount, &accounts).unwrap_err(),
+            "Invalid beancount account name: Bank:Girokonto"
+        );
+    }
+}
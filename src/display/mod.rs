@@ -1,7 +1,144 @@
-use crate::calculator::CalculationResult;
+pub mod datev;
+pub mod json;
+pub mod ledger;
+pub mod xrechnung;
 
-pub fn format_number(value: f64, use_comma: bool) -> String {
-    let formatted = format!("{value:.2}");
+use crate::calculator::{
+    AdjustedAmounts, CalculationResult, ConvertedAmounts, EInvoiceCheck, MarginSchemeResult,
+    PricingResult, PricingTarget, Quantity, RateSolution, Share, SplitPart, TaxComponent,
+    TaxTreatment, VerificationResult, calculate_receipt_total, total_results,
+};
+use crate::exchange::Conversion;
+use crate::invoice::EInvoice;
+use crate::invoice::lines::{ImportedLine, LineImport};
+use crate::invoice::statement::Statement;
+use crate::rounding::{RoundingLevel, RoundingMode, RoundingRule, minor_units, round_to_precision};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Datev,
+    Hledger,
+    Ledger,
+    Beancount,
+}
+
+impl OutputFormat {
+    pub fn parse(input: &str) -> Option<OutputFormat> {
+        match input.to_lowercase().as_str() {
+            "table" => Some(OutputFormat::Table),
+            "json" => Some(OutputFormat::Json),
+            "datev" => Some(OutputFormat::Datev),
+            "hledger" => Some(OutputFormat::Hledger),
+            "ledger" => Some(OutputFormat::Ledger),
+            "beancount" => Some(OutputFormat::Beancount),
+            _ => None,
+        }
+    }
+
+    // The plain-text accounting formats
+    pub fn is_journal(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Hledger | OutputFormat::Ledger | OutputFormat::Beancount
+        )
+    }
+}
+
+pub fn format_number(value: f64, use_comma: bool, precision: usize, mode: RoundingMode) -> String {
+    // Adding 0.0 turns a rounded -0.0 into 0.0 so it is not printed as "-0.00"
+    let rounded = round_to_precision(value, precision, mode) + 0.0;
+    let formatted = format!("{rounded:.precision$}");
+    if use_comma {
+        formatted.replace('.', ",")
+    } else {
+        formatted
+    }
+}
+
+// Width of the label column, or None when no result carries a label
+pub fn label_width(results: &[CalculationResult]) -> Option<usize> {
+    results
+        .iter()
+        .filter_map(|result| result.label.as_deref())
+        .map(|label| label.chars().count())
+        .max()
+        .map(|width| width.max("Label".len()))
+}
+
+// The label column including its separator, empty when there are no labels
+pub fn label_cell(label: &str, width: Option<usize>) -> String {
+    match width {
+        Some(width) => format!("{label:<width$} | "),
+        None => String::new(),
+    }
+}
+
+fn table_width(columns: usize, label_width: Option<usize>) -> usize {
+    columns + label_width.map_or(0, |width| width + 3)
+}
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn print_table_header(vat_rate: f64, label_width: Option<usize>) {
+    let width = table_width(50, label_width);
+    println!("\nVAT Rate: {vat_rate}%");
+    println!("{:-<width$}", "");
+    println!(
+        "{}{:<20} | {:<20}",
+        label_cell("Label", label_width),
+        "With VAT",
+        "Without VAT"
+    );
+    println!("{:-<width$}", "");
+}
+
+pub fn print_table_footer(label_width: Option<usize>) {
+    println!("{:-<width$}", "", width = table_width(50, label_width));
+}
+
+pub fn print_quantity_table_header(vat_rate: f64, label_width: Option<usize>) {
+    let width = table_width(80, label_width);
+    println!("\nVAT Rate: {vat_rate}%");
+    println!("{:-<width$}", "");
+    println!(
+        "{}{:<8} | {:<15} | {:<15} | {:<15} | {:<15}",
+        label_cell("Label", label_width),
+        "Qty",
+        "Unit price",
+        "With VAT",
+        "Without VAT",
+        "VAT"
+    );
+    println!("{:-<width$}", "");
+}
+
+pub fn print_quantity_table_footer(label_width: Option<usize>) {
+    println!("{:-<width$}", "", width = table_width(80, label_width));
+}
+
+pub fn has_quantities(results: &[CalculationResult]) -> bool {
+    results.iter().any(|result| result.quantity.is_some())
+}
+
+// Amounts entered without a quantity count as one unit at their gross
+fn line_quantity(result: &CalculationResult) -> Quantity {
+    result.quantity.unwrap_or(Quantity {
+        count: 1.0,
+        unit_price: result.with_vat,
+    })
+}
+
+pub fn format_count(count: f64, use_comma: bool) -> String {
+    let formatted = count.to_string();
     if use_comma {
         formatted.replace('.', ",")
     } else {
@@ -9,91 +146,1326 @@ pub fn format_number(value: f64, use_comma: bool) -> String {
     }
 }
 
-pub fn print_table_header(vat_rate: f64) {
+fn quantity_cells(result: &CalculationResult, precision: usize, mode: RoundingMode) -> [String; 5] {
+    let quantity = line_quantity(result);
+    let format = |value| format_number(value, result.uses_comma, precision, mode);
+    [
+        format_count(quantity.count, result.uses_comma),
+        format(quantity.unit_price),
+        format(result.with_vat),
+        format(result.without_vat),
+        format(result.with_vat - result.without_vat),
+    ]
+}
+
+pub fn print_converted_table_header(vat_rate: f64, currency: &str, label_width: Option<usize>) {
+    let width = table_width(89, label_width);
     println!("\nVAT Rate: {vat_rate}%");
-    println!("{:-<50}", "");
-    println!("{:<20} | {:<20}", "With VAT", "Without VAT");
-    println!("{:-<50}", "");
+    println!("{:-<width$}", "");
+    println!(
+        "{}{:<20} | {:<20} | {:<20} | {:<20}",
+        label_cell("Label", label_width),
+        "With VAT",
+        "Without VAT",
+        format!("Without VAT {currency}"),
+        format!("VAT {currency}")
+    );
+    println!("{:-<width$}", "");
 }
 
-pub fn print_table_footer() {
-    println!("{:-<50}", "");
+pub fn print_converted_table_footer(label_width: Option<usize>) {
+    println!("{:-<width$}", "", width = table_width(89, label_width));
 }
 
-pub fn print_table_row(with_vat: f64, without_vat: f64, use_comma: bool) {
-    let with_vat_str = format_number(with_vat, use_comma);
-    let without_vat_str = format_number(without_vat, use_comma);
+fn converted_currency(results: &[CalculationResult]) -> Option<&str> {
+    results
+        .iter()
+        .find_map(|result| result.converted.as_ref())
+        .map(|converted| converted.currency.as_str())
+}
+
+fn format_converted_amounts(
+    converted: &ConvertedAmounts,
+    use_comma: bool,
+    mode: RoundingMode,
+) -> (String, String) {
+    let precision = minor_units(&converted.currency);
+    (
+        format_number(converted.without_vat, use_comma, precision, mode),
+        format_number(converted.vat, use_comma, precision, mode),
+    )
+}
+
+pub fn print_conversion(conversion: &Conversion) {
+    println!(
+        "Converted {} to {} at {:.6} (reference rate of {})",
+        conversion.from, conversion.to, conversion.rate, conversion.date
+    );
+}
+
+pub fn print_table_row(
+    with_vat: f64,
+    without_vat: f64,
+    use_comma: bool,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let with_vat_str = format_number(with_vat, use_comma, precision, mode);
+    let without_vat_str = format_number(without_vat, use_comma, precision, mode);
     println!("{with_vat_str:<20} | {without_vat_str:<20}");
 }
 
-pub fn display_results(results: &[CalculationResult], vat_rate: f64) {
-    print_table_header(vat_rate);
+pub fn format_treatment_note(
+    result: &CalculationResult,
+    precision: usize,
+    mode: RoundingMode,
+) -> Option<String> {
+    match result.treatment {
+        TaxTreatment::Standard => None,
+        TaxTreatment::ReverseCharge => Some(format!(
+            "{}, self-assessed VAT: {}",
+            result.treatment.label(),
+            format_number(result.self_assessed_vat, result.uses_comma, precision, mode)
+        )),
+        TaxTreatment::Exempt | TaxTreatment::OutOfScope | TaxTreatment::Tip => {
+            Some(result.treatment.label().to_string())
+        }
+    }
+}
+
+pub fn format_adjusted_amounts(
+    adjusted: &AdjustedAmounts,
+    use_comma: bool,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    format!(
+        "gross {}, net {}, VAT correction {}",
+        format_number(adjusted.with_vat, use_comma, precision, mode),
+        format_number(adjusted.without_vat, use_comma, precision, mode),
+        format_number(adjusted.vat_correction, use_comma, precision, mode)
+    )
+}
+
+fn print_result_notes(result: &CalculationResult, precision: usize, mode: RoundingMode) {
+    if let Some(note) = format_treatment_note(result, precision, mode) {
+        println!("  ↳ {note}");
+    }
+    if let Some(adjusted) = &result.adjusted {
+        println!(
+            "  ↳ adjusted: {}",
+            format_adjusted_amounts(adjusted, result.uses_comma, precision, mode)
+        );
+    }
+}
+
+pub fn print_total_adjustment(
+    results: &[CalculationResult],
+    total: &AdjustedAmounts,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let use_comma = results.first().is_some_and(|r| r.uses_comma);
+    println!(
+        "Adjusted total: {}",
+        format_adjusted_amounts(total, use_comma, precision, mode)
+    );
+}
+
+pub fn print_rounding_rule(rule: &RoundingRule) {
+    println!(
+        "Amounts rounded to {} ({})",
+        rule.increment,
+        rule.mode.label()
+    );
+}
+
+pub fn display_results(
+    results: &[CalculationResult],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let converted = converted_currency(results);
+    let quantities = converted.is_none() && has_quantities(results);
+    let labels = label_width(results);
+    match converted {
+        Some(currency) => print_converted_table_header(vat_rate, currency, labels),
+        None if quantities => print_quantity_table_header(vat_rate, labels),
+        None => print_table_header(vat_rate, labels),
+    }
 
     for result in results {
-        print_table_row(result.with_vat, result.without_vat, result.uses_comma);
+        print!(
+            "{}",
+            label_cell(result.label.as_deref().unwrap_or_default(), labels)
+        );
+        match &result.converted {
+            Some(converted) => {
+                let (without_vat_str, vat_str) =
+                    format_converted_amounts(converted, result.uses_comma, mode);
+                println!(
+                    "{:<20} | {:<20} | {without_vat_str:<20} | {vat_str:<20}",
+                    format_number(result.with_vat, result.uses_comma, precision, mode),
+                    format_number(result.without_vat, result.uses_comma, precision, mode)
+                );
+            }
+            None if quantities => {
+                let [count, unit_price, with_vat, without_vat, vat] =
+                    quantity_cells(result, precision, mode);
+                println!(
+                    "{count:<8} | {unit_price:<15} | {with_vat:<15} | {without_vat:<15} | {vat:<15}"
+                );
+            }
+            None => print_table_row(
+                result.with_vat,
+                result.without_vat,
+                result.uses_comma,
+                precision,
+                mode,
+            ),
+        }
+        print_result_notes(result, precision, mode);
+    }
+
+    match converted {
+        Some(_) => print_converted_table_footer(labels),
+        None if quantities => print_quantity_table_footer(labels),
+        None => print_table_footer(labels),
+    }
+    print_self_assessed_total(results, precision, mode);
+    print_receipt_total(results, precision, mode);
+}
+
+pub fn print_rounding_level(results: &[CalculationResult], level: RoundingLevel) {
+    if has_quantities(results) {
+        println!("Quantities rounded per {}", level.label());
+    }
+}
+
+pub fn print_receipt_total(results: &[CalculationResult], precision: usize, mode: RoundingMode) {
+    if let Some(total) = calculate_receipt_total(results) {
+        let use_comma = results.iter().any(|result| result.uses_comma);
+        let format = |value| format_number(value, use_comma, precision, mode);
+        println!(
+            "Receipt total: {} = net {} + VAT {} + tip {}",
+            format(total.gross),
+            format(total.net),
+            format(total.vat),
+            format(total.tip)
+        );
+    }
+}
+
+pub fn print_self_assessed_total(
+    results: &[CalculationResult],
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let self_assessed: Vec<_> = results
+        .iter()
+        .filter(|result| result.treatment == TaxTreatment::ReverseCharge)
+        .collect();
+    if let Some(first) = self_assessed.first() {
+        let total: f64 = self_assessed.iter().map(|r| r.self_assessed_vat).sum();
+        println!(
+            "Self-assessed VAT owed (reverse charge): {}",
+            format_number(total, first.uses_comma, precision, mode)
+        );
+    }
+}
+
+pub fn format_components(components: &[TaxComponent]) -> String {
+    components
+        .iter()
+        .map(|component| {
+            if component.compound {
+                format!("{} {}% (compound)", component.name, component.rate)
+            } else {
+                format!("{} {}%", component.name, component.rate)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+pub fn display_component_results(
+    results: &[CalculationResult],
+    components: &[TaxComponent],
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let converted = converted_currency(results);
+    let labels = label_width(results);
+    let columns = components.len() + 2 + if converted.is_some() { 2 } else { 0 };
+    let width = table_width(15 * columns + 3 * (columns - 1), labels);
+
+    println!("\nTaxes: {}", format_components(components));
+    println!("{:-<width$}", "");
+    let mut header = format!(
+        "{}{:<15} | {:<15}",
+        label_cell("Label", labels),
+        "Gross",
+        "Net"
+    );
+    for component in components {
+        header.push_str(&format!(" | {:<15}", component.name));
+    }
+    if let Some(currency) = converted {
+        header.push_str(&format!(
+            " | {:<15} | {:<15}",
+            format!("Net {currency}"),
+            format!("Taxes {currency}")
+        ));
+    }
+    println!("{header}");
+    println!("{:-<width$}", "");
+
+    for result in results {
+        let mut row = format!(
+            "{}{:<15} | {:<15}",
+            label_cell(result.label.as_deref().unwrap_or_default(), labels),
+            format_number(result.with_vat, result.uses_comma, precision, mode),
+            format_number(result.without_vat, result.uses_comma, precision, mode)
+        );
+        for tax in &result.component_taxes {
+            row.push_str(&format!(
+                " | {:<15}",
+                format_number(*tax, result.uses_comma, precision, mode)
+            ));
+        }
+        if let Some(converted) = &result.converted {
+            let (without_vat_str, vat_str) =
+                format_converted_amounts(converted, result.uses_comma, mode);
+            row.push_str(&format!(" | {without_vat_str:<15} | {vat_str:<15}"));
+        }
+        println!("{row}");
+        print_result_notes(result, precision, mode);
+    }
+
+    println!("{:-<width$}", "");
+    print_self_assessed_total(results, precision, mode);
+    print_receipt_total(results, precision, mode);
+}
+
+pub fn format_pricing_target(target: PricingTarget) -> String {
+    match target {
+        PricingTarget::Markup(markup) => format!("Markup: {markup}%"),
+        PricingTarget::Margin(margin) => format!("Margin: {margin}%"),
+    }
+}
+
+pub fn display_pricing_results(
+    results: &[PricingResult],
+    target: PricingTarget,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    println!("\n{}, VAT Rate: {vat_rate}%", format_pricing_target(target));
+    println!("{:-<87}", "");
+    println!(
+        "{:<15} | {:<15} | {:<15} | {:<15} | {:<15}",
+        "Cost", "Net price", "VAT", "Gross price", "Margin"
+    );
+    println!("{:-<87}", "");
+
+    for result in results {
+        println!(
+            "{:<15} | {:<15} | {:<15} | {:<15} | {:<15}",
+            format_number(result.cost, result.uses_comma, precision, mode),
+            format_number(result.net_price, result.uses_comma, precision, mode),
+            format_number(result.vat, result.uses_comma, precision, mode),
+            format_number(result.gross_price, result.uses_comma, precision, mode),
+            format_number(result.margin, result.uses_comma, precision, mode)
+        );
+    }
+
+    println!("{:-<87}", "");
+}
+
+pub fn create_pricing_clipboard_content(
+    results: &[PricingResult],
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    results
+        .iter()
+        .map(|result| format_number(result.net_price, result.uses_comma, precision, mode))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn create_pricing_clipboard_html(
+    results: &[PricingResult],
+    target: PricingTarget,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut html = String::from("<table>\n");
+    html.push_str(&format!(
+        "<caption>{}, VAT Rate: {vat_rate}%</caption>\n",
+        format_pricing_target(target)
+    ));
+    html.push_str(
+        "<thead><tr><th>Cost</th><th>Net price</th><th>VAT</th><th>Gross price</th><th>Margin</th></tr></thead>\n",
+    );
+    html.push_str("<tbody>\n");
+
+    for result in results {
+        html.push_str("<tr>");
+        for value in [
+            result.cost,
+            result.net_price,
+            result.vat,
+            result.gross_price,
+            result.margin,
+        ] {
+            html.push_str(&format!(
+                "<td>{}</td>",
+                format_number(value, result.uses_comma, precision, mode)
+            ));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+pub fn display_margin_scheme_results(
+    results: &[MarginSchemeResult],
+    total: &MarginSchemeResult,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let print_row = |label: &str, result: &MarginSchemeResult| {
+        let format = |value| format_number(value, result.uses_comma, precision, mode);
+        println!(
+            "{label:<6} | {:<15} | {:<15} | {:<15} | {:<15} | {:<15}",
+            format(result.purchase),
+            format(result.selling),
+            format(result.margin),
+            format(result.vat),
+            format(result.margin - result.vat)
+        );
+    };
+
+    println!("\nMargin scheme (§25a UStG), VAT Rate: {vat_rate}%");
+    println!("{:-<96}", "");
+    println!(
+        "{:<6} | {:<15} | {:<15} | {:<15} | {:<15} | {:<15}",
+        "Item", "Purchase", "Selling", "Margin", "VAT", "Net margin"
+    );
+    println!("{:-<96}", "");
+    for (index, result) in results.iter().enumerate() {
+        print_row(&(index + 1).to_string(), result);
+    }
+    println!("{:-<96}", "");
+    print_row("Total", total);
+    println!("{:-<96}", "");
+}
+
+pub fn create_margin_scheme_clipboard_content(
+    results: &[MarginSchemeResult],
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    results
+        .iter()
+        .map(|result| {
+            format_number(
+                result.margin - result.vat,
+                result.uses_comma,
+                precision,
+                mode,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn create_margin_scheme_clipboard_html(
+    results: &[MarginSchemeResult],
+    total: &MarginSchemeResult,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut html = String::from("<table>\n");
+    html.push_str(&format!(
+        "<caption>Margin scheme (§25a UStG), VAT Rate: {vat_rate}%</caption>\n"
+    ));
+    html.push_str(
+        "<thead><tr><th>Item</th><th>Purchase</th><th>Selling</th><th>Margin</th><th>VAT</th><th>Net margin</th></tr></thead>\n",
+    );
+    html.push_str("<tbody>\n");
+
+    let rows = results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| ((index + 1).to_string(), result))
+        .chain([("Total".to_string(), total)]);
+    for (label, result) in rows {
+        html.push_str(&format!("<tr><td>{label}</td>"));
+        for value in [
+            result.purchase,
+            result.selling,
+            result.margin,
+            result.vat,
+            result.margin - result.vat,
+        ] {
+            html.push_str(&format!(
+                "<td>{}</td>",
+                format_number(value, result.uses_comma, precision, mode)
+            ));
+        }
+        html.push_str("</tr>\n");
     }
 
-    print_table_footer();
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+fn format_rate(rate: Option<f64>, use_comma: bool, mode: RoundingMode) -> String {
+    rate.map_or("n/a".to_string(), |rate| {
+        format!("{}%", format_number(rate, use_comma, 2, mode))
+    })
+}
+
+pub fn format_known_rates(known_rates: &[f64]) -> String {
+    known_rates
+        .iter()
+        .map(|rate| format!("{rate}%"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn rate_solution_cells(result: &RateSolution, precision: usize, mode: RoundingMode) -> [String; 5] {
+    let format = |value| format_number(value, result.uses_comma, precision, mode);
+    [
+        format(result.gross),
+        format(result.net),
+        format(result.gross - result.net),
+        format_rate(result.implied_rate, result.uses_comma, mode),
+        result
+            .matched_rate
+            .map_or("no match".to_string(), |rate| format!("{rate}%")),
+    ]
+}
+
+pub fn display_rate_solutions(
+    results: &[RateSolution],
+    known_rates: &[f64],
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    println!(
+        "\nImplied VAT rates (known: {}, tolerance: ±{tolerance} points)",
+        format_known_rates(known_rates)
+    );
+    println!("{:-<96}", "");
+    println!(
+        "{:<6} | {:<15} | {:<15} | {:<15} | {:<15} | {:<15}",
+        "Item", "Gross", "Net", "VAT", "Implied rate", "Known rate"
+    );
+    println!("{:-<96}", "");
+    for (index, result) in results.iter().enumerate() {
+        let [gross, net, vat, implied, matched] = rate_solution_cells(result, precision, mode);
+        println!(
+            "{:<6} | {gross:<15} | {net:<15} | {vat:<15} | {implied:<15} | {matched:<15}",
+            index + 1
+        );
+    }
+    println!("{:-<96}", "");
+
+    let mismatches: Vec<String> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| result.matched_rate.is_none())
+        .map(|(index, _)| (index + 1).to_string())
+        .collect();
+    if !mismatches.is_empty() {
+        println!("⚠ No known rate matches item(s) {}", mismatches.join(", "));
+    }
 }
 
-pub fn create_clipboard_content(results: &[CalculationResult]) -> String {
+pub fn create_rate_solution_clipboard_content(
+    results: &[RateSolution],
+    mode: RoundingMode,
+) -> String {
     results
         .iter()
-        .map(|result| format_number(result.without_vat, result.uses_comma))
+        .map(|result| format_rate(result.implied_rate, result.uses_comma, mode))
         .collect::<Vec<_>>()
         .join("\n")
 }
 
+pub fn create_rate_solution_clipboard_html(
+    results: &[RateSolution],
+    known_rates: &[f64],
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut html = String::from("<table>\n");
+    html.push_str(&format!(
+        "<caption>Implied VAT rates (known: {})</caption>\n",
+        format_known_rates(known_rates)
+    ));
+    html.push_str(
+        "<thead><tr><th>Item</th><th>Gross</th><th>Net</th><th>VAT</th><th>Implied rate</th><th>Known rate</th></tr></thead>\n",
+    );
+    html.push_str("<tbody>\n");
+    for (index, result) in results.iter().enumerate() {
+        html.push_str(&format!("<tr><td>{}</td>", index + 1));
+        for cell in rate_solution_cells(result, precision, mode) {
+            html.push_str(&format!("<td>{cell}</td>"));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+pub fn display_verification_results(
+    results: &[VerificationResult],
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    println!("\nInvoice verification, tolerance: {tolerance}");
+    println!("{:-<105}", "");
+    println!(
+        "{:<6} | {:<12} | {:<12} | {:<12} | {:<6} | {:<12} | {:<12} | {:<8}",
+        "Item", "Gross", "Net", "VAT", "Rate", "Expected net", "Expected VAT", "Status"
+    );
+    println!("{:-<105}", "");
+    for (index, result) in results.iter().enumerate() {
+        let format = |value| format_number(value, result.uses_comma, precision, mode);
+        println!(
+            "{:<6} | {:<12} | {:<12} | {:<12} | {:<6} | {:<12} | {:<12} | {:<8}",
+            index + 1,
+            format(result.gross),
+            format(result.net),
+            format(result.vat),
+            format!("{}%", result.rate),
+            format(result.expected_net),
+            format(result.expected_vat),
+            if result.passed { "OK" } else { "MISMATCH" }
+        );
+    }
+    println!("{:-<105}", "");
+
+    let failed: Vec<String> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| !result.passed)
+        .map(|(index, _)| (index + 1).to_string())
+        .collect();
+    if failed.is_empty() {
+        println!("✓ All {} invoice(s) match", results.len());
+    } else {
+        println!(
+            "✗ {} of {} invoice(s) differ beyond the tolerance: item(s) {}",
+            failed.len(),
+            results.len(),
+            failed.join(", ")
+        );
+    }
+}
+
+pub fn display_installments(
+    installments: &[SplitPart],
+    total: &SplitPart,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let print_row = |label: &str, installment: &SplitPart| {
+        let format = |value| format_number(value, installment.uses_comma, precision, mode);
+        println!(
+            "{label:<6} | {:<15} | {:<15} | {:<15}",
+            format(installment.gross),
+            format(installment.net),
+            format(installment.vat)
+        );
+    };
+
+    println!(
+        "\nInstallment plan: {} payments, VAT Rate: {vat_rate}%",
+        installments.len()
+    );
+    println!("{:-<60}", "");
+    println!(
+        "{:<6} | {:<15} | {:<15} | {:<15}",
+        "No.", "Gross", "Net", "VAT"
+    );
+    println!("{:-<60}", "");
+    for (index, installment) in installments.iter().enumerate() {
+        print_row(&(index + 1).to_string(), installment);
+    }
+    println!("{:-<60}", "");
+    print_row("Total", total);
+    println!("{:-<60}", "");
+}
+
+pub fn create_installment_clipboard_content(
+    installments: &[SplitPart],
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    installments
+        .iter()
+        .map(|installment| format_number(installment.net, installment.uses_comma, precision, mode))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn create_installment_clipboard_html(
+    installments: &[SplitPart],
+    total: &SplitPart,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut html = String::from("<table>\n");
+    html.push_str(&format!(
+        "<caption>Installment plan: {} payments, VAT Rate: {vat_rate}%</caption>\n",
+        installments.len()
+    ));
+    html.push_str("<thead><tr><th>No.</th><th>Gross</th><th>Net</th><th>VAT</th></tr></thead>\n");
+    html.push_str("<tbody>\n");
+
+    let rows = installments
+        .iter()
+        .enumerate()
+        .map(|(index, installment)| ((index + 1).to_string(), installment))
+        .chain([("Total".to_string(), total)]);
+    for (label, installment) in rows {
+        html.push_str(&format!("<tr><td>{label}</td>"));
+        for value in [installment.gross, installment.net, installment.vat] {
+            html.push_str(&format!(
+                "<td>{}</td>",
+                format_number(value, installment.uses_comma, precision, mode)
+            ));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+pub fn display_allocation(
+    shares: &[Share],
+    parts: &[SplitPart],
+    total: &SplitPart,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let print_row = |name: &str, weight: &str, part: &SplitPart| {
+        let format = |value| format_number(value, part.uses_comma, precision, mode);
+        println!(
+            "{name:<20} | {weight:<8} | {:<15} | {:<15} | {:<15}",
+            format(part.gross),
+            format(part.net),
+            format(part.vat)
+        );
+    };
+
+    println!(
+        "\nBill allocation: {} shares, VAT Rate: {vat_rate}%",
+        shares.len()
+    );
+    println!("{:-<86}", "");
+    println!(
+        "{:<20} | {:<8} | {:<15} | {:<15} | {:<15}",
+        "Party", "Weight", "Gross", "Net", "VAT"
+    );
+    println!("{:-<86}", "");
+    for (share, part) in shares.iter().zip(parts) {
+        print_row(&share.name, &share.weight.to_string(), part);
+    }
+    println!("{:-<86}", "");
+    let total_weight: f64 = shares.iter().map(|share| share.weight).sum();
+    print_row("Total", &total_weight.to_string(), total);
+    println!("{:-<86}", "");
+}
+
+// Tab-separated with a header row, so it pastes into spreadsheet columns
+pub fn create_allocation_clipboard_content(
+    shares: &[Share],
+    parts: &[SplitPart],
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut lines = vec!["Party\tWeight\tGross\tNet\tVAT".to_string()];
+    for (share, part) in shares.iter().zip(parts) {
+        let format = |value| format_number(value, part.uses_comma, precision, mode);
+        lines.push(format!(
+            "{}\t{}\t{}\t{}\t{}",
+            share.name,
+            share.weight,
+            format(part.gross),
+            format(part.net),
+            format(part.vat)
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn create_allocation_clipboard_html(
+    shares: &[Share],
+    parts: &[SplitPart],
+    total: &SplitPart,
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut html = String::from("<table>\n");
+    html.push_str(&format!(
+        "<caption>Bill allocation: {} shares, VAT Rate: {vat_rate}%</caption>\n",
+        shares.len()
+    ));
+    html.push_str(
+        "<thead><tr><th>Party</th><th>Weight</th><th>Gross</th><th>Net</th><th>VAT</th></tr></thead>\n",
+    );
+    html.push_str("<tbody>\n");
+
+    let total_weight: f64 = shares.iter().map(|share| share.weight).sum();
+    let rows = shares
+        .iter()
+        .zip(parts)
+        .map(|(share, part)| (share.name.as_str(), share.weight, part))
+        .chain([("Total", total_weight, total)]);
+    for (name, weight, part) in rows {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{weight}</td>",
+            escape_html(name)
+        ));
+        for value in [part.gross, part.net, part.vat] {
+            html.push_str(&format!(
+                "<td>{}</td>",
+                format_number(value, part.uses_comma, precision, mode)
+            ));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+// The original rows with net and VAT appended, in the file's own delimiter
+// and decimal style
+pub fn create_line_import_output(
+    import: &LineImport,
+    results: &[CalculationResult],
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let delimiter = import.delimiter;
+    let cell = |value: f64| {
+        let number = format_number(value, import.decimal_comma, precision, mode);
+        if delimiter == ',' && import.decimal_comma {
+            format!("\"{number}\"")
+        } else {
+            number
+        }
+    };
+
+    // Categories assigned by rules get a column of their own
+    let categories = import.lines.iter().any(|line| line.category.is_some());
+    let mut header = format!("{}{delimiter}Net{delimiter}VAT", import.header);
+    if categories {
+        header.push_str(&format!("{delimiter}Category"));
+    }
+    let mut rows = vec![header];
+    for (line, result) in import.lines.iter().zip(results) {
+        let mut row = format!(
+            "{}{delimiter}{}{delimiter}{}",
+            line.row,
+            cell(result.without_vat),
+            cell(result.with_vat - result.without_vat)
+        );
+        if categories {
+            let category = line.category.as_deref().unwrap_or_default();
+            row.push(delimiter);
+            if category.contains([delimiter, '"']) {
+                row.push_str(&format!("\"{}\"", category.replace('"', "\"\"")));
+            } else {
+                row.push_str(category);
+            }
+        }
+        rows.push(row);
+    }
+    rows.join("\n")
+}
+
+fn format_split_totals(part: &SplitPart, precision: usize, mode: RoundingMode) -> String {
+    format!(
+        "net {}, VAT {}, gross {}",
+        format_number(part.net, part.uses_comma, precision, mode),
+        format_number(part.vat, part.uses_comma, precision, mode),
+        format_number(part.gross, part.uses_comma, precision, mode)
+    )
+}
+
+pub fn display_einvoice(
+    invoice: &EInvoice,
+    results: &[CalculationResult],
+    check: &EInvoiceCheck,
+    tolerance: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    println!(
+        "\nE-invoice {} ({}), {} line(s)",
+        invoice.number,
+        invoice.currency,
+        invoice.lines.len()
+    );
+
+    // The line table shows a single rate, so there is one per rate
+    let mut rates: Vec<f64> = Vec::new();
+    for line in &invoice.lines {
+        if !rates.contains(&line.rate) {
+            rates.push(line.rate);
+        }
+    }
+    for rate in rates {
+        let group: Vec<CalculationResult> = invoice
+            .lines
+            .iter()
+            .zip(results)
+            .filter(|(line, _)| line.rate == rate)
+            .map(|(_, result)| result.clone())
+            .collect();
+        display_results(&group, rate, precision, mode);
+    }
+
+    println!("\nVAT breakdown, tolerance: {tolerance}");
+    println!("{:-<90}", "");
+    println!(
+        "{:<8} | {:<6} | {:<12} | {:<12} | {:<14} | {:<12} | {:<8}",
+        "Category", "Rate", "Basis", "VAT", "Expected basis", "Expected VAT", "Status"
+    );
+    println!("{:-<90}", "");
+    for subtotal in &check.subtotals {
+        let format = |value| format_number(value, false, precision, mode);
+        println!(
+            "{:<8} | {:<6} | {:<12} | {:<12} | {:<14} | {:<12} | {:<8}",
+            subtotal.category,
+            format!("{}%", subtotal.rate),
+            format(subtotal.basis),
+            format(subtotal.vat),
+            format(subtotal.expected_basis),
+            format(subtotal.expected_vat),
+            if subtotal.passed { "OK" } else { "MISMATCH" }
+        );
+    }
+    println!("{:-<90}", "");
+    println!(
+        "Declared totals:   {}",
+        format_split_totals(&check.declared, precision, mode)
+    );
+    println!(
+        "Recomputed totals: {}",
+        format_split_totals(&check.expected, precision, mode)
+    );
+    if check.passed {
+        println!("✓ Declared tax totals match");
+    } else {
+        println!("✗ Declared tax totals differ beyond the tolerance");
+    }
+}
+
+// Only outgoing payments carry input VAT; incoming ones are counted but not
+// calculated. The results have one rate each, like e-invoice lines.
+pub fn display_statement(
+    statement: &Statement,
+    lines: &[ImportedLine],
+    results: &[CalculationResult],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) {
+    let rates: Vec<f64> = lines
+        .iter()
+        .map(|line| line.rate.unwrap_or(vat_rate))
+        .collect();
+    let credits = statement.entries.len() - results.len();
+    match &statement.account {
+        Some(account) => println!(
+            "\nBank statement {account} ({}), {} debit(s), {credits} credit(s) skipped",
+            statement.currency,
+            results.len()
+        ),
+        None => println!(
+            "\nBank statement ({}), {} debit(s), {credits} credit(s) skipped",
+            statement.currency,
+            results.len()
+        ),
+    }
+
+    let mut distinct: Vec<f64> = Vec::new();
+    for rate in &rates {
+        if !distinct.contains(rate) {
+            distinct.push(*rate);
+        }
+    }
+    for rate in distinct {
+        let group: Vec<CalculationResult> = results
+            .iter()
+            .zip(&rates)
+            .filter(|(_, line_rate)| **line_rate == rate)
+            .map(|(result, _)| result.clone())
+            .collect();
+        display_results(&group, rate, precision, mode);
+    }
+    println!(
+        "Total payments: {}",
+        format_split_totals(&total_results(results), precision, mode)
+    );
+
+    let mut categories: Vec<Option<&str>> = Vec::new();
+    for line in lines {
+        if !categories.contains(&line.category.as_deref()) {
+            categories.push(line.category.as_deref());
+        }
+    }
+    if categories.iter().any(Option::is_some) {
+        println!("By category:");
+        for category in categories {
+            let group: Vec<CalculationResult> = lines
+                .iter()
+                .zip(results)
+                .filter(|(line, _)| line.category.as_deref() == category)
+                .map(|(_, result)| result.clone())
+                .collect();
+            println!(
+                "  {}: {}",
+                category.unwrap_or("(none)"),
+                format_split_totals(&total_results(&group), precision, mode)
+            );
+        }
+    }
+}
+
+// Goes to stderr, so redirected CSV or XML output stays clean
+pub fn print_unmatched_lines(lines: &[ImportedLine], unmatched: &[usize], vat_rate: f64) {
+    if unmatched.is_empty() {
+        eprintln!("Rules matched all {} line(s)", lines.len());
+        return;
+    }
+    eprintln!(
+        "Rules matched {} of {} line(s); unmatched lines keep their own or the default rate of {vat_rate}%:",
+        lines.len() - unmatched.len(),
+        lines.len()
+    );
+    for line in unmatched.iter().filter_map(|&index| lines.get(index)) {
+        eprintln!("  {}", line.input.label.as_deref().unwrap_or(&line.row));
+    }
+}
+
+pub fn create_clipboard_content(
+    results: &[CalculationResult],
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    results
+        .iter()
+        .map(|result| {
+            let (_, without_vat) = result.adjusted_amounts();
+            let without_vat = format_number(without_vat, result.uses_comma, precision, mode);
+            match &result.label {
+                Some(label) => format!("{label}\t{without_vat}"),
+                None => without_vat,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn label_html(result: &CalculationResult) -> String {
+    format!(
+        "<td>{}</td>",
+        escape_html(result.label.as_deref().unwrap_or_default())
+    )
+}
+
+pub fn create_clipboard_html(
+    results: &[CalculationResult],
+    vat_rate: f64,
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut html = String::from("<table>\n");
+    html.push_str(&format!("<caption>VAT Rate: {vat_rate}%</caption>\n"));
+    let quantities = has_quantities(results);
+    let labels = label_width(results).is_some();
+    html.push_str("<thead><tr>");
+    if labels {
+        html.push_str("<th>Label</th>");
+    }
+    if quantities {
+        html.push_str("<th>Qty</th><th>Unit price</th>");
+    }
+    html.push_str("<th>With VAT</th><th>Without VAT</th>");
+    if let Some(currency) = converted_currency(results) {
+        html.push_str(&format!(
+            "<th>Without VAT {currency}</th><th>VAT {currency}</th>"
+        ));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+
+    for result in results {
+        let (with_vat, without_vat) = result.adjusted_amounts();
+        let with_vat_str = format_number(with_vat, result.uses_comma, precision, mode);
+        let without_vat_str = format_number(without_vat, result.uses_comma, precision, mode);
+        html.push_str("<tr>");
+        if labels {
+            html.push_str(&label_html(result));
+        }
+        if quantities {
+            let [count, unit_price, ..] = quantity_cells(result, precision, mode);
+            html.push_str(&format!("<td>{count}</td><td>{unit_price}</td>"));
+        }
+        html.push_str(&format!(
+            "<td>{with_vat_str}</td><td>{without_vat_str}</td>"
+        ));
+        if let Some(converted) = &result.converted {
+            let (without_vat_str, vat_str) =
+                format_converted_amounts(converted, result.uses_comma, mode);
+            html.push_str(&format!("<td>{without_vat_str}</td><td>{vat_str}</td>"));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+pub fn create_component_clipboard_html(
+    results: &[CalculationResult],
+    components: &[TaxComponent],
+    precision: usize,
+    mode: RoundingMode,
+) -> String {
+    let mut html = String::from("<table>\n");
+    html.push_str(&format!(
+        "<caption>Taxes: {}</caption>\n",
+        escape_html(&format_components(components))
+    ));
+    let labels = label_width(results).is_some();
+    html.push_str("<thead><tr>");
+    if labels {
+        html.push_str("<th>Label</th>");
+    }
+    html.push_str("<th>Gross</th><th>Net</th>");
+    for component in components {
+        html.push_str(&format!("<th>{}</th>", escape_html(&component.name)));
+    }
+    if let Some(currency) = converted_currency(results) {
+        html.push_str(&format!("<th>Net {currency}</th><th>Taxes {currency}</th>"));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+
+    for result in results {
+        html.push_str("<tr>");
+        if labels {
+            html.push_str(&label_html(result));
+        }
+        let (with_vat, without_vat) = result.adjusted_amounts();
+        html.push_str(&format!(
+            "<td>{}</td><td>{}</td>",
+            format_number(with_vat, result.uses_comma, precision, mode),
+            format_number(without_vat, result.uses_comma, precision, mode)
+        ));
+        for tax in &result.component_taxes {
+            html.push_str(&format!(
+                "<td>{}</td>",
+                format_number(*tax, result.uses_comma, precision, mode)
+            ));
+        }
+        if let Some(converted) = &result.converted {
+            let (without_vat_str, vat_str) =
+                format_converted_amounts(converted, result.uses_comma, mode);
+            html.push_str(&format!("<td>{without_vat_str}</td><td>{vat_str}</td>"));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::calculator::CalculationResult;
+    use crate::calculator::{
+        AdjustedAmounts, CalculationResult, ConvertedAmounts, MarginSchemeResult, PricingResult,
+        PricingTarget, RateSolution, Share, SplitPart, TaxComponent, TaxTreatment,
+        VerificationResult,
+    };
+    use crate::rounding::RoundingMode;
+
+    fn components() -> Vec<TaxComponent> {
+        vec![
+            TaxComponent {
+                name: "GST".to_string(),
+                rate: 5.0,
+                compound: false,
+            },
+            TaxComponent {
+                name: "QST".to_string(),
+                rate: 9.975,
+                compound: true,
+            },
+        ]
+    }
 
     #[test]
     fn test_format_number_with_comma() {
-        assert_eq!(format_number(123.456, true), "123,46");
+        assert_eq!(
+            format_number(123.456, true, 2, RoundingMode::HalfUp),
+            "123,46"
+        );
     }
 
     #[test]
     fn test_format_number_with_dot() {
-        assert_eq!(format_number(123.456, false), "123.46");
+        assert_eq!(
+            format_number(123.456, false, 2, RoundingMode::HalfUp),
+            "123.46"
+        );
     }
 
     #[test]
     fn test_format_number_zero() {
-        assert_eq!(format_number(0.0, false), "0.00");
-        assert_eq!(format_number(0.0, true), "0,00");
+        assert_eq!(format_number(0.0, false, 2, RoundingMode::HalfUp), "0.00");
+        assert_eq!(format_number(0.0, true, 2, RoundingMode::HalfUp), "0,00");
     }
 
     #[test]
     fn test_format_number_negative() {
-        assert_eq!(format_number(-123.456, false), "-123.46");
-        assert_eq!(format_number(-123.456, true), "-123,46");
+        assert_eq!(
+            format_number(-123.456, false, 2, RoundingMode::HalfUp),
+            "-123.46"
+        );
+        assert_eq!(
+            format_number(-123.456, true, 2, RoundingMode::HalfUp),
+            "-123,46"
+        );
     }
 
     #[test]
     fn test_format_number_very_large() {
-        assert_eq!(format_number(999999999.999, false), "1000000000.00");
-        assert_eq!(format_number(999999999.999, true), "1000000000,00");
+        assert_eq!(
+            format_number(999999999.999, false, 2, RoundingMode::HalfUp),
+            "1000000000.00"
+        );
+        assert_eq!(
+            format_number(999999999.999, true, 2, RoundingMode::HalfUp),
+            "1000000000,00"
+        );
     }
 
     #[test]
     fn test_format_number_very_small() {
-        assert_eq!(format_number(0.001, false), "0.00");
-        assert_eq!(format_number(0.009, false), "0.01");
+        assert_eq!(format_number(0.001, false, 2, RoundingMode::HalfUp), "0.00");
+        assert_eq!(format_number(0.009, false, 2, RoundingMode::HalfUp), "0.01");
     }
 
     #[test]
     fn test_format_number_rounding() {
-        assert_eq!(format_number(123.454, false), "123.45");
-        assert_eq!(format_number(123.455, false), "123.45");
-        assert_eq!(format_number(123.456, false), "123.46");
+        assert_eq!(
+            format_number(123.454, false, 2, RoundingMode::HalfUp),
+            "123.45"
+        );
+        assert_eq!(
+            format_number(123.455, false, 2, RoundingMode::HalfUp),
+            "123.46"
+        );
+        assert_eq!(
+            format_number(123.456, false, 2, RoundingMode::HalfUp),
+            "123.46"
+        );
+    }
+
+    #[test]
+    fn test_format_number_tie_cases() {
+        let cases = [
+            (0.125, "0.13", "0.12"),
+            (0.135, "0.14", "0.14"),
+            (1.005, "1.01", "1.00"),
+            (2.675, "2.68", "2.68"),
+            (123.445, "123.45", "123.44"),
+            (-0.125, "-0.13", "-0.12"),
+        ];
+        for (value, half_up, half_even) in cases {
+            assert_eq!(
+                format_number(value, false, 2, RoundingMode::HalfUp),
+                half_up
+            );
+            assert_eq!(
+                format_number(value, false, 2, RoundingMode::HalfEven),
+                half_even
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_number_floor_and_ceil() {
+        assert_eq!(
+            format_number(123.459, false, 2, RoundingMode::Floor),
+            "123.45"
+        );
+        assert_eq!(
+            format_number(123.451, true, 2, RoundingMode::Ceil),
+            "123,46"
+        );
+    }
+
+    #[test]
+    fn test_format_number_negative_zero() {
+        assert_eq!(
+            format_number(-0.001, false, 2, RoundingMode::HalfUp),
+            "0.00"
+        );
+    }
+
+    #[test]
+    fn test_format_number_precision() {
+        assert_eq!(
+            format_number(1234.5, false, 0, RoundingMode::HalfUp),
+            "1235"
+        );
+        assert_eq!(
+            format_number(1234.5, false, 0, RoundingMode::HalfEven),
+            "1234"
+        );
+        assert_eq!(
+            format_number(1.7895, true, 3, RoundingMode::HalfUp),
+            "1,790"
+        );
+        assert_eq!(
+            format_number(12.3456, false, 3, RoundingMode::Floor),
+            "12.345"
+        );
     }
 
     #[test]
     fn test_create_clipboard_content_empty() {
         let results = vec![];
-        assert_eq!(create_clipboard_content(&results), "");
+        assert_eq!(
+            create_clipboard_content(&results, 2, RoundingMode::HalfUp),
+            ""
+        );
     }
 
     #[test]
@@ -102,8 +1474,12 @@ mod tests {
             with_vat: 119.0,
             without_vat: 100.0,
             uses_comma: false,
+            ..Default::default()
         }];
-        assert_eq!(create_clipboard_content(&results), "100.00");
+        assert_eq!(
+            create_clipboard_content(&results, 2, RoundingMode::HalfUp),
+            "100.00"
+        );
     }
 
     #[test]
@@ -113,14 +1489,19 @@ mod tests {
                 with_vat: 119.0,
                 without_vat: 100.0,
                 uses_comma: false,
+                ..Default::default()
             },
             CalculationResult {
                 with_vat: 238.0,
                 without_vat: 200.0,
                 uses_comma: true,
+                ..Default::default()
             },
         ];
-        assert_eq!(create_clipboard_content(&results), "100.00\n200,00");
+        assert_eq!(
+            create_clipboard_content(&results, 2, RoundingMode::HalfUp),
+            "100.00\n200,00"
+        );
     }
 
     #[test]
@@ -130,19 +1511,641 @@ mod tests {
                 with_vat: 119.50,
                 without_vat: 100.42,
                 uses_comma: true,
+                ..Default::default()
             },
             CalculationResult {
                 with_vat: 238.00,
                 without_vat: 200.00,
                 uses_comma: false,
+                ..Default::default()
             },
             CalculationResult {
                 with_vat: 357.00,
                 without_vat: 300.00,
                 uses_comma: true,
+                ..Default::default()
             },
         ];
-        assert_eq!(create_clipboard_content(&results), "100,42\n200.00\n300,00");
+        assert_eq!(
+            create_clipboard_content(&results, 2, RoundingMode::HalfUp),
+            "100,42\n200.00\n300,00"
+        );
+    }
+
+    #[test]
+    fn test_create_clipboard_html_empty() {
+        let results = vec![];
+        assert_eq!(
+            create_clipboard_html(&results, 19.0, 2, RoundingMode::HalfUp),
+            "<table>\n<caption>VAT Rate: 19%</caption>\n\
+             <thead><tr><th>With VAT</th><th>Without VAT</th></tr></thead>\n\
+             <tbody>\n</tbody>\n</table>"
+        );
+    }
+
+    #[test]
+    fn test_create_clipboard_html_rows() {
+        let results = vec![
+            CalculationResult {
+                with_vat: 119.0,
+                without_vat: 100.0,
+                uses_comma: false,
+                ..Default::default()
+            },
+            CalculationResult {
+                with_vat: 119.50,
+                without_vat: 100.42,
+                uses_comma: true,
+                ..Default::default()
+            },
+        ];
+        let html = create_clipboard_html(&results, 19.0, 2, RoundingMode::HalfUp);
+        assert!(html.contains("<tr><td>119.00</td><td>100.00</td></tr>"));
+        assert!(html.contains("<tr><td>119,50</td><td>100,42</td></tr>"));
+    }
+
+    #[test]
+    fn test_create_clipboard_html_quantities() {
+        let results = vec![
+            CalculationResult {
+                with_vat: 38.97,
+                without_vat: 32.75,
+                uses_comma: true,
+                quantity: Some(Quantity {
+                    count: 3.0,
+                    unit_price: 12.99,
+                }),
+                ..Default::default()
+            },
+            CalculationResult {
+                with_vat: 5.0,
+                without_vat: 4.2,
+                uses_comma: false,
+                ..Default::default()
+            },
+        ];
+        let html = create_clipboard_html(&results, 19.0, 2, RoundingMode::HalfUp);
+        assert!(html.contains(
+            "<thead><tr><th>Qty</th><th>Unit price</th><th>With VAT</th><th>Without VAT</th></tr></thead>"
+        ));
+        assert!(html.contains("<tr><td>3</td><td>12,99</td><td>38,97</td><td>32,75</td></tr>"));
+        assert!(html.contains("<tr><td>1</td><td>5.00</td><td>5.00</td><td>4.20</td></tr>"));
+    }
+
+    #[test]
+    fn test_labels_in_clipboard_output() {
+        let results = vec![
+            CalculationResult {
+                with_vat: 23.8,
+                without_vat: 20.0,
+                uses_comma: true,
+                label: Some("Taxi & <Bahn>".to_string()),
+                ..Default::default()
+            },
+            CalculationResult {
+                with_vat: 119.0,
+                without_vat: 100.0,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            create_clipboard_content(&results, 2, RoundingMode::HalfUp),
+            "Taxi & <Bahn>\t20,00\n100.00"
+        );
+        let html = create_clipboard_html(&results, 19.0, 2, RoundingMode::HalfUp);
+        assert!(html.contains("<thead><tr><th>Label</th><th>With VAT</th>"));
+        assert!(
+            html.contains("<tr><td>Taxi &amp; &lt;Bahn&gt;</td><td>23,80</td><td>20,00</td></tr>")
+        );
+        assert!(html.contains("<tr><td></td><td>119.00</td><td>100.00</td></tr>"));
+    }
+
+    #[test]
+    fn test_label_width() {
+        let mut results = vec![CalculationResult::default()];
+        assert_eq!(label_width(&results), None);
+        assert_eq!(label_cell("Taxi", None), "");
+
+        results[0].label = Some("Taxi".to_string());
+        assert_eq!(label_width(&results), Some(5));
+        results[0].label = Some("Übernachtung".to_string());
+        assert_eq!(label_width(&results), Some(12));
+        assert_eq!(label_cell("Taxi", Some(6)), "Taxi   | ");
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(3.0, true), "3");
+        assert_eq!(format_count(1.5, true), "1,5");
+        assert_eq!(format_count(1.5, false), "1.5");
+    }
+
+    #[test]
+    fn test_format_treatment_note() {
+        let mut result = CalculationResult {
+            with_vat: 100.0,
+            without_vat: 100.0,
+            uses_comma: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_treatment_note(&result, 2, RoundingMode::HalfUp),
+            None
+        );
+
+        result.treatment = TaxTreatment::Exempt;
+        assert_eq!(
+            format_treatment_note(&result, 2, RoundingMode::HalfUp).unwrap(),
+            "tax-exempt (§4 UStG)"
+        );
+
+        result.treatment = TaxTreatment::Tip;
+        assert_eq!(
+            format_treatment_note(&result, 2, RoundingMode::HalfUp).unwrap(),
+            "tip (not subject to VAT)"
+        );
+
+        result.treatment = TaxTreatment::ReverseCharge;
+        result.self_assessed_vat = 19.0;
+        assert_eq!(
+            format_treatment_note(&result, 2, RoundingMode::HalfUp).unwrap(),
+            "reverse charge (§13b UStG), self-assessed VAT: 19,00"
+        );
+    }
+
+    #[test]
+    fn test_format_components() {
+        assert_eq!(
+            format_components(&components()),
+            "GST 5% + QST 9.975% (compound)"
+        );
+    }
+
+    #[test]
+    fn test_create_component_clipboard_html() {
+        let results = vec![CalculationResult {
+            with_vat: 115.5,
+            without_vat: 100.0,
+            uses_comma: false,
+            component_taxes: vec![5.0, 10.5],
+            ..Default::default()
+        }];
+        let html =
+            create_component_clipboard_html(&results, &components(), 2, RoundingMode::HalfUp);
+        assert!(html.contains("<th>Gross</th><th>Net</th><th>GST</th><th>QST</th>"));
+        assert!(
+            html.contains("<tr><td>115.50</td><td>100.00</td><td>5.00</td><td>10.50</td></tr>")
+        );
+    }
+
+    #[test]
+    fn test_create_component_clipboard_html_escapes_names() {
+        let components = vec![TaxComponent {
+            name: "A<b>".to_string(),
+            rate: 5.0,
+            compound: false,
+        }];
+        let results = vec![CalculationResult {
+            with_vat: 105.0,
+            without_vat: 100.0,
+            component_taxes: vec![5.0],
+            ..Default::default()
+        }];
+        let html = create_component_clipboard_html(&results, &components, 2, RoundingMode::HalfUp);
+        assert!(html.contains("<caption>Taxes: A&lt;b&gt; 5%</caption>"));
+        assert!(html.contains("<th>A&lt;b&gt;</th>"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn test_display_component_results() {
+        let results = vec![CalculationResult {
+            with_vat: 115.5,
+            without_vat: 100.0,
+            uses_comma: true,
+            component_taxes: vec![5.0, 10.5],
+            ..Default::default()
+        }];
+        display_component_results(&results, &components(), 2, RoundingMode::HalfUp);
+    }
+
+    #[test]
+    fn test_create_clipboard_content_uses_adjusted_net() {
+        let results = vec![CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            uses_comma: true,
+            adjusted: Some(AdjustedAmounts {
+                with_vat: 115.43,
+                without_vat: 97.0,
+                vat_correction: -0.57,
+            }),
+            ..Default::default()
+        }];
+        assert_eq!(
+            create_clipboard_content(&results, 2, RoundingMode::HalfUp),
+            "97,00"
+        );
+    }
+
+    #[test]
+    fn test_create_clipboard_html_uses_adjusted_amounts() {
+        let results = vec![CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            uses_comma: true,
+            adjusted: Some(AdjustedAmounts {
+                with_vat: 116.62,
+                without_vat: 98.0,
+                vat_correction: -0.38,
+            }),
+            component_taxes: vec![18.62],
+            ..Default::default()
+        }];
+        let html = create_clipboard_html(&results, 19.0, 2, RoundingMode::HalfUp);
+        assert!(html.contains("<tr><td>116,62</td><td>98,00</td></tr>"));
+        let components = [TaxComponent {
+            name: "VAT".to_string(),
+            rate: 19.0,
+            compound: false,
+        }];
+        let html = create_component_clipboard_html(&results, &components, 2, RoundingMode::HalfUp);
+        assert!(html.contains("<td>116,62</td><td>98,00</td>"));
+    }
+
+    #[test]
+    fn test_format_adjusted_amounts() {
+        let adjusted = AdjustedAmounts {
+            with_vat: 115.43,
+            without_vat: 97.0,
+            vat_correction: -0.57,
+        };
+        assert_eq!(
+            format_adjusted_amounts(&adjusted, true, 2, RoundingMode::HalfUp),
+            "gross 115,43, net 97,00, VAT correction -0,57"
+        );
+    }
+
+    fn converted_result() -> CalculationResult {
+        CalculationResult {
+            with_vat: 119.0,
+            without_vat: 100.0,
+            uses_comma: true,
+            converted: Some(ConvertedAmounts {
+                currency: "JPY".to_string(),
+                without_vat: 17623.0,
+                vat: 3348.0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_create_clipboard_html_converted_columns() {
+        let html = create_clipboard_html(&[converted_result()], 19.0, 2, RoundingMode::HalfUp);
+        assert!(html.contains("<th>Without VAT JPY</th><th>VAT JPY</th>"));
+        assert!(
+            html.contains("<tr><td>119,00</td><td>100,00</td><td>17623</td><td>3348</td></tr>")
+        );
+    }
+
+    #[test]
+    fn test_display_results_converted() {
+        display_results(&[converted_result()], 19.0, 2, RoundingMode::HalfUp);
+    }
+
+    fn pricing_result() -> PricingResult {
+        PricingResult {
+            cost: 49.99,
+            net_price: 71.41,
+            gross_price: 84.98,
+            margin: 21.42,
+            vat: 13.57,
+            uses_comma: true,
+        }
+    }
+
+    #[test]
+    fn test_format_pricing_target() {
+        assert_eq!(
+            format_pricing_target(PricingTarget::Markup(25.0)),
+            "Markup: 25%"
+        );
+        assert_eq!(
+            format_pricing_target(PricingTarget::Margin(30.0)),
+            "Margin: 30%"
+        );
+    }
+
+    #[test]
+    fn test_create_pricing_clipboard_content() {
+        let results = vec![pricing_result()];
+        assert_eq!(
+            create_pricing_clipboard_content(&results, 2, RoundingMode::HalfUp),
+            "71,41"
+        );
+    }
+
+    #[test]
+    fn test_create_pricing_clipboard_html() {
+        let results = vec![pricing_result()];
+        let html = create_pricing_clipboard_html(
+            &results,
+            PricingTarget::Margin(30.0),
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(html.contains("<caption>Margin: 30%, VAT Rate: 19%</caption>"));
+        assert!(html.contains(
+            "<tr><td>49,99</td><td>71,41</td><td>13,57</td><td>84,98</td><td>21,42</td></tr>"
+        ));
+    }
+
+    #[test]
+    fn test_display_pricing_results() {
+        display_pricing_results(
+            &[pricing_result()],
+            PricingTarget::Markup(42.85),
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+    }
+
+    #[test]
+    fn test_output_format_parse() {
+        assert_eq!(OutputFormat::parse("JSON"), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::parse("table"), Some(OutputFormat::Table));
+        assert_eq!(OutputFormat::parse("datev"), Some(OutputFormat::Datev));
+        assert_eq!(
+            OutputFormat::parse("Beancount"),
+            Some(OutputFormat::Beancount)
+        );
+        assert_eq!(OutputFormat::parse("csv"), None);
+    }
+
+    fn margin_scheme_result() -> MarginSchemeResult {
+        MarginSchemeResult {
+            purchase: 800.0,
+            selling: 1000.0,
+            margin: 200.0,
+            vat: 31.93,
+            uses_comma: true,
+        }
+    }
+
+    #[test]
+    fn test_create_margin_scheme_clipboard_content() {
+        let results = vec![margin_scheme_result()];
+        assert_eq!(
+            create_margin_scheme_clipboard_content(&results, 2, RoundingMode::HalfUp),
+            "168,07"
+        );
+    }
+
+    #[test]
+    fn test_create_margin_scheme_clipboard_html() {
+        let results = vec![margin_scheme_result()];
+        let html = create_margin_scheme_clipboard_html(
+            &results,
+            &results[0],
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(html.contains(
+            "<tr><td>1</td><td>800,00</td><td>1000,00</td><td>200,00</td><td>31,93</td><td>168,07</td></tr>"
+        ));
+        assert!(html.contains("<tr><td>Total</td>"));
+    }
+
+    fn rate_solutions() -> Vec<RateSolution> {
+        vec![
+            RateSolution {
+                gross: 119.0,
+                net: 100.0,
+                implied_rate: Some(19.0),
+                matched_rate: Some(19.0),
+                uses_comma: false,
+            },
+            RateSolution {
+                gross: 116.0,
+                net: 100.0,
+                implied_rate: Some(16.0),
+                matched_rate: None,
+                uses_comma: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_create_rate_solution_clipboard_content() {
+        assert_eq!(
+            create_rate_solution_clipboard_content(&rate_solutions(), RoundingMode::HalfUp),
+            "19.00%\n16,00%"
+        );
+    }
+
+    #[test]
+    fn test_create_rate_solution_clipboard_html() {
+        let html = create_rate_solution_clipboard_html(
+            &rate_solutions(),
+            &[19.0, 7.0],
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(html.contains("<caption>Implied VAT rates (known: 19%, 7%)</caption>"));
+        assert!(html.contains("<td>19.00</td><td>19.00%</td><td>19%</td>"));
+        assert!(html.contains("<td>16,00%</td><td>no match</td>"));
+    }
+
+    #[test]
+    fn test_display_rate_solutions() {
+        display_rate_solutions(
+            &rate_solutions(),
+            &[19.0, 7.0],
+            0.1,
+            2,
+            RoundingMode::HalfUp,
+        );
+    }
+
+    #[test]
+    fn test_display_verification_results() {
+        let results = vec![VerificationResult {
+            gross: 119.0,
+            net: 100.0,
+            vat: 7.0,
+            rate: 19.0,
+            expected_net: 100.0,
+            expected_vat: 19.0,
+            passed: false,
+            uses_comma: false,
+        }];
+        display_verification_results(&results, 0.01, 2, RoundingMode::HalfUp);
+    }
+
+    fn installments() -> Vec<SplitPart> {
+        vec![
+            SplitPart {
+                gross: 500.0,
+                net: 420.17,
+                vat: 79.83,
+                uses_comma: true,
+            },
+            SplitPart {
+                gross: 500.0,
+                net: 420.17,
+                vat: 79.83,
+                uses_comma: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_create_installment_clipboard_content() {
+        assert_eq!(
+            create_installment_clipboard_content(&installments(), 2, RoundingMode::HalfUp),
+            "420,17\n420,17"
+        );
+    }
+
+    #[test]
+    fn test_create_installment_clipboard_html() {
+        let installments = installments();
+        let total = SplitPart {
+            gross: 1000.0,
+            net: 840.34,
+            vat: 159.66,
+            uses_comma: true,
+        };
+        let html =
+            create_installment_clipboard_html(&installments, &total, 19.0, 2, RoundingMode::HalfUp);
+        assert!(html.contains("<caption>Installment plan: 2 payments, VAT Rate: 19%</caption>"));
+        assert!(html.contains("<tr><td>1</td><td>500,00</td><td>420,17</td><td>79,83</td></tr>"));
+        assert!(
+            html.contains("<tr><td>Total</td><td>1000,00</td><td>840,34</td><td>159,66</td></tr>")
+        );
+    }
+
+    #[test]
+    fn test_display_installments() {
+        let installments = installments();
+        display_installments(
+            &installments,
+            &installments[0],
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+    }
+
+    fn shares() -> Vec<Share> {
+        vec![
+            Share {
+                name: "Sales".to_string(),
+                weight: 1.0,
+            },
+            Share {
+                name: "Support".to_string(),
+                weight: 1.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_create_allocation_clipboard_content() {
+        assert_eq!(
+            create_allocation_clipboard_content(
+                &shares(),
+                &installments(),
+                2,
+                RoundingMode::HalfUp
+            ),
+            "Party\tWeight\tGross\tNet\tVAT\nSales\t1\t500,00\t420,17\t79,83\nSupport\t1\t500,00\t420,17\t79,83"
+        );
+    }
+
+    #[test]
+    fn test_create_allocation_clipboard_html() {
+        let parts = installments();
+        let html = create_allocation_clipboard_html(
+            &shares(),
+            &parts,
+            &parts[0],
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(html.contains("<caption>Bill allocation: 2 shares, VAT Rate: 19%</caption>"));
+        assert!(html.contains("<tr><td>Support</td><td>1</td><td>500,00</td>"));
+        assert!(html.contains("<tr><td>Total</td><td>2</td>"));
+    }
+
+    #[test]
+    fn test_create_allocation_clipboard_html_escapes_names() {
+        let parts = installments();
+        let mut shares = shares();
+        shares[0].name = "R&D <Team>".to_string();
+        let html = create_allocation_clipboard_html(
+            &shares,
+            &parts,
+            &parts[0],
+            19.0,
+            2,
+            RoundingMode::HalfUp,
+        );
+        assert!(html.contains("<tr><td>R&amp;D &lt;Team&gt;</td>"));
+        assert!(!html.contains("<Team>"));
+    }
+
+    #[test]
+    fn test_display_allocation() {
+        let parts = installments();
+        display_allocation(&shares(), &parts, &parts[0], 19.0, 2, RoundingMode::HalfUp);
+    }
+
+    #[test]
+    fn test_create_line_import_output() {
+        let import = LineImport {
+            header: "description,amount".to_string(),
+            delimiter: ',',
+            decimal_comma: true,
+            lines: Vec::new(),
+        };
+        let results = vec![CalculationResult {
+            with_vat: 23.8,
+            without_vat: 20.0,
+            ..Default::default()
+        }];
+        assert_eq!(
+            create_line_import_output(&import, &results, 2, RoundingMode::HalfUp),
+            "description,amount,Net,VAT"
+        );
+
+        let import = crate::invoice::lines::parse_lines(
+            "Text;Betrag\nTaxi;23,80\n",
+            &crate::invoice::lines::ColumnMap::parse("amount=Betrag").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            create_line_import_output(&import, &results, 2, RoundingMode::HalfUp),
+            "Text;Betrag;Net;VAT\nTaxi;23,80;20,00;3,80"
+        );
+
+        let mut import = import;
+        import.lines[0].category = Some("Reise;Taxi \"Nord\"".to_string());
+        assert_eq!(
+            create_line_import_output(&import, &results, 2, RoundingMode::HalfUp),
+            "Text;Betrag;Net;VAT;Category\nTaxi;23,80;20,00;3,80;\"Reise;Taxi \"\"Nord\"\"\""
+        );
+    }
+
+    #[test]
+    fn test_display_margin_scheme_results() {
+        let results = vec![margin_scheme_result()];
+        display_margin_scheme_results(&results, &results[0], 19.0, 2, RoundingMode::HalfUp);
     }
 
     #[test]
@@ -152,17 +2155,18 @@ mod tests {
             with_vat: 119.0,
             without_vat: 100.0,
             uses_comma: false,
+            ..Default::default()
         }];
 
         // We can't easily capture stdout in tests, but we can ensure the function runs without panic
-        display_results(&results, 19.0);
+        display_results(&results, 19.0, 2, RoundingMode::HalfUp);
         // If this doesn't panic, the test passes
     }
 
     #[test]
     fn test_print_table_row_coverage() {
         // Test to ensure print_table_row is covered
-        print_table_row(119.0, 100.0, false);
-        print_table_row(119.0, 100.0, true);
+        print_table_row(119.0, 100.0, false, 2, RoundingMode::HalfUp);
+        print_table_row(119.0, 100.0, true, 2, RoundingMode::HalfUp);
     }
 }
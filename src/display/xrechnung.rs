@@ -0,0 +1,409 @@
+use super::{escape_html, format_number};
+use crate::invoice::EInvoice;
+use crate::invoice::parties::{InvoiceParties, Party};
+use crate::rounding::RoundingMode;
+
+const BUSINESS_PROCESS: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
+const GUIDELINE: &str = "urn:cen.eu:en16931:2017#compliant#urn:xeinkauf.de:kosit:xrechnung_3.0";
+
+// Categories other than standard and zero rated need a stated reason (BR-E-10,
+// BR-AE-10, BR-O-10)
+fn exemption_reason(category: &str) -> Option<&'static str> {
+    match category {
+        "E" => Some("Steuerfrei nach § 4 UStG"),
+        "AE" => Some("Steuerschuldnerschaft des Leistungsempfängers (§ 13b UStG)"),
+        "O" => Some("Nicht steuerbar"),
+        _ => None,
+    }
+}
+
+// Collects indented elements in the order the CII schema demands
+struct XmlWriter {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl XmlWriter {
+    fn open(&mut self, tag: &str) {
+        self.lines
+            .push(format!("{}<{tag}>", "  ".repeat(self.depth)));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.lines
+            .push(format!("{}</{tag}>", "  ".repeat(self.depth)));
+    }
+
+    fn element(&mut self, tag: &str, attributes: &str, text: &str) {
+        self.lines.push(format!(
+            "{}<{tag}{attributes}>{}</{tag}>",
+            "  ".repeat(self.depth),
+            escape_html(text)
+        ));
+    }
+
+    fn text(&mut self, tag: &str, text: &str) {
+        self.element(tag, "", text);
+    }
+}
+
+// Out-of-scope invoices carry no VAT identifiers (BR-O-02)
+fn write_party(xml: &mut XmlWriter, tag: &str, party: &Party, vat_id: bool) {
+    xml.open(tag);
+    xml.text("ram:Name", &party.name);
+    if let Some(contact) = &party.contact {
+        xml.open("ram:DefinedTradeContact");
+        xml.text("ram:PersonName", contact);
+        if let Some(phone) = &party.phone {
+            xml.open("ram:TelephoneUniversalCommunication");
+            xml.text("ram:CompleteNumber", phone);
+            xml.close("ram:TelephoneUniversalCommunication");
+        }
+        xml.open("ram:EmailURIUniversalCommunication");
+        xml.text("ram:URIID", &party.email);
+        xml.close("ram:EmailURIUniversalCommunication");
+        xml.close("ram:DefinedTradeContact");
+    }
+    xml.open("ram:PostalTradeAddress");
+    xml.text("ram:PostcodeCode", &party.postcode);
+    if let Some(street) = &party.street {
+        xml.text("ram:LineOne", street);
+    }
+    xml.text("ram:CityName", &party.city);
+    xml.text("ram:CountryID", &party.country);
+    xml.close("ram:PostalTradeAddress");
+    // The electronic address (BT-34/BT-49) is mandatory in XRechnung
+    xml.open("ram:URIUniversalCommunication");
+    xml.element("ram:URIID", " schemeID=\"EM\"", &party.email);
+    xml.close("ram:URIUniversalCommunication");
+    let vat_id = party.vat_id.as_ref().filter(|_| vat_id);
+    for (scheme, id) in [("VA", vat_id), ("FC", party.tax_number.as_ref())] {
+        if let Some(id) = id {
+            xml.open("ram:SpecifiedTaxRegistration");
+            xml.element("ram:ID", &format!(" schemeID=\"{scheme}\""), id);
+            xml.close("ram:SpecifiedTaxRegistration");
+        }
+    }
+    xml.close(tag);
+}
+
+// Checks the rules the calculated invoice itself could break: line prices
+// need a quantity, an invoice is not a credit note (BR-O-11 keeps out-of-scope
+// lines apart from taxed ones), reverse charge names both VAT identifiers
+// (BR-AE-02) and out-of-scope invoices identify the seller by tax number.
+pub fn check_xrechnung(invoice: &EInvoice, parties: &InvoiceParties) -> Result<(), String> {
+    if let Some(index) = invoice.lines.iter().position(|line| line.quantity == 0.0) {
+        return Err(format!("Line {} has a zero quantity", index + 1));
+    }
+    if invoice.gross_total < 0.0 {
+        return Err(
+            "The invoice total is negative; XRechnung credit notes are not supported".to_string(),
+        );
+    }
+    let out_of_scope = invoice
+        .lines
+        .iter()
+        .filter(|line| line.category == "O")
+        .count();
+    if out_of_scope > 0 && out_of_scope < invoice.lines.len() {
+        return Err(
+            "Out-of-scope lines (:tip, :oos) cannot share an XRechnung with taxed lines"
+                .to_string(),
+        );
+    }
+    if out_of_scope > 0 && parties.seller.tax_number.is_none() {
+        return Err(
+            "Out-of-scope invoices carry no VAT identifiers and need seller.tax_number in the parties file"
+                .to_string(),
+        );
+    }
+    let reverse_charge = invoice.lines.iter().any(|line| line.category == "AE");
+    if reverse_charge && (parties.seller.vat_id.is_none() || parties.buyer.vat_id.is_none()) {
+        return Err(
+            "Reverse-charge lines need seller.vat_id and buyer.vat_id in the parties file"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+// Writes an XRechnung 3.0 invoice in UN/CEFACT CII syntax. The VAT breakdown
+// and totals are taken from the invoice as calculated; amounts always carry
+// two decimals (BR-DEC).
+pub fn create_xrechnung_output(
+    invoice: &EInvoice,
+    parties: &InvoiceParties,
+    mode: RoundingMode,
+) -> String {
+    let amount = |value: f64| format_number(value, false, 2, mode);
+    let rate = |value: f64| format_number(value, false, 2, RoundingMode::HalfUp);
+    let mut xml = XmlWriter {
+        lines: vec![
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+            "<rsm:CrossIndustryInvoice xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\" xmlns:ram=\"urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100\" xmlns:udt=\"urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100\">".to_string(),
+        ],
+        depth: 1,
+    };
+
+    xml.open("rsm:ExchangedDocumentContext");
+    xml.open("ram:BusinessProcessSpecifiedDocumentContextParameter");
+    xml.text("ram:ID", BUSINESS_PROCESS);
+    xml.close("ram:BusinessProcessSpecifiedDocumentContextParameter");
+    xml.open("ram:GuidelineSpecifiedDocumentContextParameter");
+    xml.text("ram:ID", GUIDELINE);
+    xml.close("ram:GuidelineSpecifiedDocumentContextParameter");
+    xml.close("rsm:ExchangedDocumentContext");
+
+    xml.open("rsm:ExchangedDocument");
+    xml.text("ram:ID", &invoice.number);
+    xml.text("ram:TypeCode", "380");
+    xml.open("ram:IssueDateTime");
+    xml.element(
+        "udt:DateTimeString",
+        " format=\"102\"",
+        &parties.date.replace('-', ""),
+    );
+    xml.close("ram:IssueDateTime");
+    xml.close("rsm:ExchangedDocument");
+
+    xml.open("rsm:SupplyChainTradeTransaction");
+    for (index, line) in invoice.lines.iter().enumerate() {
+        let number = (index + 1).to_string();
+        xml.open("ram:IncludedSupplyChainTradeLineItem");
+        xml.open("ram:AssociatedDocumentLineDocument");
+        xml.text("ram:LineID", &number);
+        xml.close("ram:AssociatedDocumentLineDocument");
+        xml.open("ram:SpecifiedTradeProduct");
+        let name = format!("Position {number}");
+        xml.text("ram:Name", line.label.as_deref().unwrap_or(&name));
+        xml.close("ram:SpecifiedTradeProduct");
+        xml.open("ram:SpecifiedLineTradeAgreement");
+        xml.open("ram:NetPriceProductTradePrice");
+        // Two more places keep the price times the quantity at the line net.
+        // Prices are never negative (BR-27), refunds are negative quantities.
+        xml.text(
+            "ram:ChargeAmount",
+            &format_number((line.net / line.quantity).abs(), false, 4, mode),
+        );
+        xml.close("ram:NetPriceProductTradePrice");
+        xml.close("ram:SpecifiedLineTradeAgreement");
+        xml.open("ram:SpecifiedLineTradeDelivery");
+        xml.element(
+            "ram:BilledQuantity",
+            " unitCode=\"C62\"",
+            &line.quantity.abs().copysign(line.net).to_string(),
+        );
+        xml.close("ram:SpecifiedLineTradeDelivery");
+        xml.open("ram:SpecifiedLineTradeSettlement");
+        xml.open("ram:ApplicableTradeTax");
+        xml.text("ram:TypeCode", "VAT");
+        xml.text("ram:CategoryCode", &line.category);
+        if line.category != "O" {
+            xml.text("ram:RateApplicablePercent", &rate(line.rate));
+        }
+        xml.close("ram:ApplicableTradeTax");
+        xml.open("ram:SpecifiedTradeSettlementLineMonetarySummation");
+        xml.text("ram:LineTotalAmount", &amount(line.net));
+        xml.close("ram:SpecifiedTradeSettlementLineMonetarySummation");
+        xml.close("ram:SpecifiedLineTradeSettlement");
+        xml.close("ram:IncludedSupplyChainTradeLineItem");
+    }
+
+    xml.open("ram:ApplicableHeaderTradeAgreement");
+    xml.text("ram:BuyerReference", &parties.buyer_reference);
+    let vat_ids = invoice.lines.iter().all(|line| line.category != "O");
+    write_party(&mut xml, "ram:SellerTradeParty", &parties.seller, vat_ids);
+    write_party(&mut xml, "ram:BuyerTradeParty", &parties.buyer, vat_ids);
+    xml.close("ram:ApplicableHeaderTradeAgreement");
+    xml.lines.push(format!(
+        "{}<ram:ApplicableHeaderTradeDelivery/>",
+        "  ".repeat(xml.depth)
+    ));
+
+    xml.open("ram:ApplicableHeaderTradeSettlement");
+    xml.text("ram:InvoiceCurrencyCode", &invoice.currency);
+    xml.open("ram:SpecifiedTradeSettlementPaymentMeans");
+    xml.text("ram:TypeCode", "58");
+    xml.open("ram:PayeePartyCreditorFinancialAccount");
+    xml.text("ram:IBANID", &parties.iban);
+    xml.close("ram:PayeePartyCreditorFinancialAccount");
+    xml.close("ram:SpecifiedTradeSettlementPaymentMeans");
+    for subtotal in &invoice.subtotals {
+        xml.open("ram:ApplicableTradeTax");
+        xml.text("ram:CalculatedAmount", &amount(subtotal.vat));
+        xml.text("ram:TypeCode", "VAT");
+        if let Some(reason) = exemption_reason(&subtotal.category) {
+            xml.text("ram:ExemptionReason", reason);
+        }
+        xml.text("ram:BasisAmount", &amount(subtotal.basis));
+        xml.text("ram:CategoryCode", &subtotal.category);
+        if subtotal.category != "O" {
+            xml.text("ram:RateApplicablePercent", &rate(subtotal.rate));
+        }
+        xml.close("ram:ApplicableTradeTax");
+    }
+    xml.open("ram:SpecifiedTradePaymentTerms");
+    xml.text(
+        "ram:Description",
+        parties
+            .payment_terms
+            .as_deref()
+            .unwrap_or("Zahlbar sofort ohne Abzug"),
+    );
+    xml.close("ram:SpecifiedTradePaymentTerms");
+    xml.open("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+    xml.text("ram:LineTotalAmount", &amount(invoice.net_total));
+    xml.text("ram:TaxBasisTotalAmount", &amount(invoice.net_total));
+    xml.element(
+        "ram:TaxTotalAmount",
+        &format!(" currencyID=\"{}\"", escape_html(&invoice.currency)),
+        &amount(invoice.vat_total),
+    );
+    xml.text("ram:GrandTotalAmount", &amount(invoice.gross_total));
+    xml.text("ram:DuePayableAmount", &amount(invoice.gross_total));
+    xml.close("ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+    xml.close("ram:ApplicableHeaderTradeSettlement");
+    xml.close("rsm:SupplyChainTradeTransaction");
+
+    xml.lines.push("</rsm:CrossIndustryInvoice>".to_string());
+    xml.lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calculator::{TaxTreatment, build_einvoice, process_numbers, verify_einvoice};
+    use crate::cli::parse_number;
+    use crate::invoice::parse_einvoice;
+    use crate::invoice::parties::parse_parties;
+    use crate::rounding::RoundingLevel;
+
+    const PARTIES: &str = r#"
+[invoice]
+number = "R-7"
+date = "2025-10-19"
+buyer_reference = "991-12345-67"
+
+[seller]
+name = "Müller & Söhne"
+postcode = "10115"
+city = "Berlin"
+email = "rechnung@mueller.example"
+contact = "Anna Müller"
+phone = "030 123"
+tax_number = "12/345/67890"
+iban = "DE02120300000000202051"
+
+[buyer]
+name = "Kunde <GmbH>"
+postcode = "80331"
+city = "München"
+email = "buchhaltung@kunde.example"
+vat_id = "DE999999999"
+"#;
+
+    fn sample_invoice() -> EInvoice {
+        let numbers: Vec<_> = ["Beratung=3x119", "Fahrtkosten=23,80", "Schulung=500:exempt"]
+            .iter()
+            .map(|input| parse_number(input).unwrap())
+            .collect();
+        assert_eq!(numbers[2].treatment, TaxTreatment::Exempt);
+        let results = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        build_einvoice(&results, &[19.0; 3], "R-7", "eur", 2, RoundingMode::HalfUp)
+    }
+
+    #[test]
+    fn test_create_xrechnung_output() {
+        let parties = parse_parties(PARTIES).unwrap();
+        let xml = create_xrechnung_output(&sample_invoice(), &parties, RoundingMode::HalfUp);
+        assert!(xml.contains(GUIDELINE));
+        assert!(xml.contains("<udt:DateTimeString format=\"102\">20251019</udt:DateTimeString>"));
+        assert!(xml.contains("<ram:BilledQuantity unitCode=\"C62\">3</ram:BilledQuantity>"));
+        assert!(xml.contains("<ram:ChargeAmount>100.0000</ram:ChargeAmount>"));
+        assert!(xml.contains("<ram:Name>Müller &amp; Söhne</ram:Name>"));
+        assert!(xml.contains("<ram:Name>Kunde &lt;GmbH&gt;</ram:Name>"));
+        assert!(xml.contains("<ram:ID schemeID=\"FC\">12/345/67890</ram:ID>"));
+        assert!(
+            xml.contains("<ram:ExemptionReason>Steuerfrei nach § 4 UStG</ram:ExemptionReason>")
+        );
+        assert!(xml.contains("<ram:TaxTotalAmount currencyID=\"EUR\">60.80</ram:TaxTotalAmount>"));
+        assert!(xml.contains("<ram:DuePayableAmount>880.80</ram:DuePayableAmount>"));
+    }
+
+    #[test]
+    fn test_xrechnung_output_passes_verification() {
+        let parties = parse_parties(PARTIES).unwrap();
+        let xml = create_xrechnung_output(&sample_invoice(), &parties, RoundingMode::HalfUp);
+        let invoice = parse_einvoice(&xml).unwrap();
+        assert_eq!(invoice.lines, sample_invoice().lines);
+        assert_eq!(invoice.gross_total, 880.8);
+        assert!(verify_einvoice(&invoice, 0.0, 2, RoundingMode::HalfUp).passed);
+    }
+
+    fn invoice(inputs: &[&str]) -> EInvoice {
+        let numbers: Vec<_> = inputs
+            .iter()
+            .map(|input| parse_number(input).unwrap())
+            .collect();
+        let results = process_numbers(&numbers, 19.0, 2, RoundingMode::HalfUp, RoundingLevel::Line);
+        let rates = vec![19.0; numbers.len()];
+        build_einvoice(&results, &rates, "R-7", "EUR", 2, RoundingMode::HalfUp)
+    }
+
+    #[test]
+    fn test_xrechnung_refund_line() {
+        let parties = parse_parties(PARTIES).unwrap();
+        let xml = create_xrechnung_output(
+            &invoice(&["Beratung=3x119", "Gutschrift=2x-11,90"]),
+            &parties,
+            RoundingMode::HalfUp,
+        );
+        assert!(xml.contains("<ram:BilledQuantity unitCode=\"C62\">-2</ram:BilledQuantity>"));
+        assert!(xml.contains("<ram:ChargeAmount>10.0000</ram:ChargeAmount>"));
+        assert!(xml.contains("<ram:LineTotalAmount>-20.00</ram:LineTotalAmount>"));
+        assert!(!xml.contains("<ram:ChargeAmount>-"));
+    }
+
+    #[test]
+    fn test_xrechnung_out_of_scope() {
+        let mut parties = parse_parties(PARTIES).unwrap();
+        let tips = invoice(&["50:tip"]);
+        check_xrechnung(&tips, &parties).unwrap();
+        let xml = create_xrechnung_output(&tips, &parties, RoundingMode::HalfUp);
+        assert!(!xml.contains("schemeID=\"VA\""));
+        assert!(xml.contains("<ram:ID schemeID=\"FC\">12/345/67890</ram:ID>"));
+
+        parties.seller.tax_number = None;
+        parties.seller.vat_id = Some("DE123456789".to_string());
+        assert_eq!(
+            check_xrechnung(&tips, &parties).unwrap_err(),
+            "Out-of-scope invoices carry no VAT identifiers and need seller.tax_number in the parties file"
+        );
+    }
+
+    #[test]
+    fn test_check_xrechnung_errors() {
+        let parties = parse_parties(PARTIES).unwrap();
+        check_xrechnung(&sample_invoice(), &parties).unwrap();
+        assert_eq!(
+            check_xrechnung(&invoice(&["119", "10:tip"]), &parties).unwrap_err(),
+            "Out-of-scope lines (:tip, :oos) cannot share an XRechnung with taxed lines"
+        );
+        assert_eq!(
+            check_xrechnung(&invoice(&["-119"]), &parties).unwrap_err(),
+            "The invoice total is negative; XRechnung credit notes are not supported"
+        );
+        assert_eq!(
+            check_xrechnung(&invoice(&["100:rc"]), &parties).unwrap_err(),
+            "Reverse-charge lines need seller.vat_id and buyer.vat_id in the parties file"
+        );
+        let mut zero = invoice(&["119"]);
+        zero.lines[0].quantity = 0.0;
+        assert_eq!(
+            check_xrechnung(&zero, &parties).unwrap_err(),
+            "Line 1 has a zero quantity"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::invoice::lines::parse_date;
+
+// ECB reference rates are quoted as units of foreign currency per 1 EUR.
+const BASE_CURRENCY: &str = "EUR";
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+// The daily CSV writes "17 October 2025", the historical files ISO dates
+fn parse_rate_date(input: &str) -> Option<String> {
+    if let Some(date) = parse_date(input) {
+        return Some(date);
+    }
+    let mut parts = input.split_whitespace();
+    let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+    let month = MONTHS
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(month))?;
+    parse_date(&format!("{year}-{}-{day}", month + 1))
+}
+
+#[derive(Debug)]
+pub struct RateTable {
+    pub date: String,
+    rates: HashMap<String, f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    pub from: String,
+    pub to: String,
+    pub rate: f64,
+    pub date: String,
+}
+
+impl RateTable {
+    // `date` picks the day of a historical file (YYYY-MM-DD); without it the
+    // file must hold a single day
+    pub fn load(path: &str, date: Option<&str>) -> Result<RateTable, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read exchange rate file {path}: {e}"))?;
+
+        let days = if content.trim_start().starts_with('<') {
+            RateTable::parse_ecb_xml(&content)?
+        } else {
+            RateTable::parse_ecb_csv(&content)?
+        };
+        RateTable::select(days, date)
+    }
+
+    pub fn select(days: Vec<RateTable>, date: Option<&str>) -> Result<RateTable, String> {
+        match date {
+            Some(date) => days
+                .into_iter()
+                .find(|day| parse_rate_date(&day.date).as_deref() == Some(date))
+                .ok_or_else(|| format!("Exchange rate file has no rates for {date}")),
+            None if days.len() > 1 => Err(
+                "Exchange rate file holds rates for several days; pick one with --rate-date"
+                    .to_string(),
+            ),
+            None => days
+                .into_iter()
+                .next()
+                .ok_or_else(|| "Exchange rate file has no rates".to_string()),
+        }
+    }
+
+    // eurofxref.csv: a header row "Date, USD, JPY, ..." followed by one row of
+    // rates per date (a single one in the daily file).
+    pub fn parse_ecb_csv(content: &str) -> Result<Vec<RateTable>, String> {
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().ok_or("Exchange rate file is empty")?;
+        let mut columns = header.split(',').map(str::trim);
+        if !columns
+            .next()
+            .is_some_and(|c| c.eq_ignore_ascii_case("date"))
+        {
+            return Err("Exchange rate file does not start with a Date column".to_string());
+        }
+        let currencies: Vec<&str> = columns.collect();
+
+        let days: Vec<RateTable> = lines
+            .map(|values| {
+                let mut fields = values.split(',').map(str::trim);
+                let date = fields.next().unwrap_or_default().to_string();
+                let mut rates = HashMap::new();
+                for (currency, rate) in currencies.iter().zip(fields) {
+                    if currency.is_empty() {
+                        continue;
+                    }
+                    // The ECB publishes "N/A" for currencies without a fixing that day
+                    if let Ok(rate) = rate.parse::<f64>() {
+                        rates.insert(currency.to_uppercase(), rate);
+                    }
+                }
+                RateTable { date, rates }
+            })
+            .collect();
+        if days.is_empty() {
+            return Err("Exchange rate file has no rates".to_string());
+        }
+        Ok(days)
+    }
+
+    // eurofxref-daily.xml: <Cube time="..."><Cube currency="USD" rate="..."/>,
+    // one dated Cube per day in the historical files
+    pub fn parse_ecb_xml(content: &str) -> Result<Vec<RateTable>, String> {
+        let document = roxmltree::Document::parse(content)
+            .map_err(|e| format!("Invalid exchange rate XML: {e}"))?;
+
+        let mut days = Vec::new();
+        for day in document
+            .descendants()
+            .filter(|node| node.has_tag_name("Cube") && node.has_attribute("time"))
+        {
+            let mut rates = HashMap::new();
+            for cube in day.children().filter(|node| node.has_tag_name("Cube")) {
+                if let (Some(currency), Some(rate)) =
+                    (cube.attribute("currency"), cube.attribute("rate"))
+                {
+                    let rate = rate
+                        .parse()
+                        .map_err(|_| format!("Invalid exchange rate for {currency}: {rate}"))?;
+                    rates.insert(currency.to_uppercase(), rate);
+                }
+            }
+            days.push(RateTable {
+                date: day.attribute("time").unwrap_or_default().to_string(),
+                rates,
+            });
+        }
+        if days.is_empty() {
+            return Err("Exchange rate XML contains no dated rates".to_string());
+        }
+        Ok(days)
+    }
+
+    fn rate_per_base(&self, currency: &str) -> Option<f64> {
+        let currency = currency.to_uppercase();
+        if currency == BASE_CURRENCY {
+            Some(1.0)
+        } else {
+            self.rates.get(&currency).copied()
+        }
+    }
+
+    pub fn conversion(&self, from: &str, to: &str) -> Result<Conversion, String> {
+        let missing = |currency: &str| format!("No exchange rate for {currency} on {}", self.date);
+        let from_rate = self.rate_per_base(from).ok_or_else(|| missing(from))?;
+        let to_rate = self.rate_per_base(to).ok_or_else(|| missing(to))?;
+
+        Ok(Conversion {
+            from: from.to_uppercase(),
+            to: to.to_uppercase(),
+            rate: to_rate / from_rate,
+            date: self.date.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ECB_CSV: &str =
+        "Date, USD, JPY, BGN, CHF, \n17 October 2025, 1.1689, 176.23, N/A, 0.9270, \n";
+
+    const ECB_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+    <gesmes:subject>Reference rates</gesmes:subject>
+    <Cube>
+        <Cube time='2025-10-17'>
+            <Cube currency='USD' rate='1.1689'/>
+            <Cube currency='GBP' rate='0.8695'/>
+        </Cube>
+    </Cube>
+</gesmes:Envelope>"#;
+
+    #[test]
+    fn test_parse_ecb_csv() {
+        let table = RateTable::parse_ecb_csv(ECB_CSV).unwrap().remove(0);
+        assert_eq!(table.date, "17 October 2025");
+        assert_eq!(table.rate_per_base("usd"), Some(1.1689));
+        assert_eq!(table.rate_per_base("CHF"), Some(0.927));
+        assert_eq!(table.rate_per_base("BGN"), None);
+        assert_eq!(table.rate_per_base("EUR"), Some(1.0));
+    }
+
+    #[test]
+    fn test_select_rate_date() {
+        let history = "Date,USD,JPY,\n2025-10-17,1.1689,176.23,\n2025-10-16,1.1681,175.81,\n";
+        let days = RateTable::parse_ecb_csv(history).unwrap();
+        let table = RateTable::select(days, Some("2025-10-16")).unwrap();
+        assert_eq!(table.rate_per_base("USD"), Some(1.1681));
+
+        let days = RateTable::parse_ecb_csv(history).unwrap();
+        assert_eq!(
+            RateTable::select(days, Some("2025-10-18")).unwrap_err(),
+            "Exchange rate file has no rates for 2025-10-18"
+        );
+        let days = RateTable::parse_ecb_csv(history).unwrap();
+        assert_eq!(
+            RateTable::select(days, None).unwrap_err(),
+            "Exchange rate file holds rates for several days; pick one with --rate-date"
+        );
+
+        let days = RateTable::parse_ecb_csv(ECB_CSV).unwrap();
+        let table = RateTable::select(days, Some("2025-10-17")).unwrap();
+        assert_eq!(table.date, "17 October 2025");
+
+        let history = ECB_XML.replace(
+            "</Cube>\n    </Cube>",
+            "</Cube>\n        <Cube time='2025-10-16'><Cube currency='USD' rate='1.1681'/></Cube>\n    </Cube>",
+        );
+        let days = RateTable::parse_ecb_xml(&history).unwrap();
+        assert_eq!(days.len(), 2);
+        let table = RateTable::select(days, Some("2025-10-16")).unwrap();
+        assert_eq!(table.rate_per_base("USD"), Some(1.1681));
+    }
+
+    #[test]
+    fn test_parse_ecb_csv_invalid() {
+        assert!(RateTable::parse_ecb_csv("").is_err());
+        assert!(RateTable::parse_ecb_csv("USD, JPY\n1.1, 170").is_err());
+        assert!(RateTable::parse_ecb_csv("Date, USD\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_ecb_xml() {
+        let table = RateTable::parse_ecb_xml(ECB_XML).unwrap().remove(0);
+        assert_eq!(table.date, "2025-10-17");
+        assert_eq!(table.rate_per_base("GBP"), Some(0.8695));
+    }
+
+    #[test]
+    fn test_parse_ecb_xml_invalid() {
+        assert!(RateTable::parse_ecb_xml("<Cube>").is_err());
+        assert!(RateTable::parse_ecb_xml("<Cube><Cube currency='USD' rate='1'/></Cube>").is_err());
+    }
+
+    #[test]
+    fn test_conversion_to_base() {
+        let table = RateTable::parse_ecb_xml(ECB_XML).unwrap().remove(0);
+        let conversion = table.conversion("usd", "EUR").unwrap();
+        assert_eq!(conversion.from, "USD");
+        assert_eq!(conversion.to, "EUR");
+        assert_eq!(conversion.date, "2025-10-17");
+        assert!((conversion.rate - 1.0 / 1.1689).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_conversion_cross_rate() {
+        let table = RateTable::parse_ecb_xml(ECB_XML).unwrap().remove(0);
+        let conversion = table.conversion("GBP", "USD").unwrap();
+        assert!((conversion.rate - 1.1689 / 0.8695).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_conversion_unknown_currency() {
+        let table = RateTable::parse_ecb_xml(ECB_XML).unwrap().remove(0);
+        assert_eq!(
+            table.conversion("XYZ", "EUR").unwrap_err(),
+            "No exchange rate for XYZ on 2025-10-17"
+        );
+    }
+}
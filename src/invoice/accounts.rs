@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fs;
+
+use super::lines::parse_date;
+use super::toml::parse_toml_tables;
+use crate::calculator::TaxTreatment;
+
+// Which accounts the DATEV export uses, from the [datev], [accounts],
+// [categories] and [tax_keys] tables of the accounts file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AccountMap {
+    pub consultant: String,
+    pub client: String,
+    pub fiscal_year_start: Option<String>,
+    pub account_length: usize,
+    pub default_account: String,
+    pub contra_account: String,
+    pub categories: HashMap<String, String>,
+    pub tax_keys: Vec<(f64, String)>,
+    pub reverse_charge_keys: Vec<(f64, String)>,
+}
+
+fn find_key(keys: &[(f64, String)], rate: f64) -> Option<&str> {
+    keys.iter()
+        .find(|(key_rate, _)| *key_rate == rate)
+        .map(|(_, key)| key.as_str())
+}
+
+impl AccountMap {
+    // Lines without a category, or with one the file does not list, go to the
+    // default account
+    pub fn account(&self, category: Option<&str>) -> &str {
+        category
+            .and_then(|category| self.categories.get(category))
+            .unwrap_or(&self.default_account)
+    }
+
+    // The DATEV BU key that makes the booking derive its input VAT. Amounts
+    // without VAT are booked without a key.
+    pub fn tax_key(&self, rate: f64, treatment: TaxTreatment) -> Result<&str, String> {
+        match treatment {
+            TaxTreatment::Standard if rate == 0.0 => Ok(""),
+            TaxTreatment::Standard => find_key(&self.tax_keys, rate)
+                .ok_or_else(|| format!("No tax key for {rate}% (add it under [tax_keys])")),
+            TaxTreatment::ReverseCharge => find_key(&self.reverse_charge_keys, rate).ok_or_else(|| {
+                format!(
+                    "No reverse-charge tax key for {rate}% (add it under [tax_keys.reverse_charge])"
+                )
+            }),
+            TaxTreatment::Exempt | TaxTreatment::OutOfScope | TaxTreatment::Tip => Ok(""),
+        }
+    }
+}
+
+// Account names for the plain-text accounting formats, from the [ledger] and
+// [ledger.categories] tables
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerAccounts {
+    pub expense: String,
+    pub input_vat: String,
+    pub reverse_charge: String,
+    pub payment: String,
+    pub categories: HashMap<String, String>,
+}
+
+impl Default for LedgerAccounts {
+    fn default() -> Self {
+        LedgerAccounts {
+            expense: "Expenses:General".to_string(),
+            input_vat: "Assets:VAT:Input".to_string(),
+            reverse_charge: "Liabilities:VAT:ReverseCharge".to_string(),
+            payment: "Assets:Bank".to_string(),
+            categories: HashMap::new(),
+        }
+    }
+}
+
+impl LedgerAccounts {
+    pub fn account(&self, category: Option<&str>) -> &str {
+        category
+            .and_then(|category| self.categories.get(category))
+            .unwrap_or(&self.expense)
+    }
+}
+
+fn read_accounts_file(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Could not read accounts file {path}: {e}"))
+}
+
+pub fn load_accounts(path: &str) -> Result<AccountMap, String> {
+    parse_accounts(&read_accounts_file(path)?)
+}
+
+pub fn load_ledger_accounts(path: &str) -> Result<LedgerAccounts, String> {
+    parse_ledger_accounts(&read_accounts_file(path)?)
+}
+
+// Every account is optional; the [datev] tables may sit in the same file
+pub fn parse_ledger_accounts(content: &str) -> Result<LedgerAccounts, String> {
+    let tables = parse_toml_tables(content, "accounts file")?;
+    let mut accounts = LedgerAccounts::default();
+    if let Some(ledger) = tables.get("ledger") {
+        for (key, account) in [
+            ("expense", &mut accounts.expense),
+            ("vat", &mut accounts.input_vat),
+            ("reverse_charge", &mut accounts.reverse_charge),
+            ("payment", &mut accounts.payment),
+        ] {
+            if let Some(name) = ledger.get(key) {
+                *account = name.clone();
+            }
+        }
+    }
+    if let Some(categories) = tables.get("ledger.categories") {
+        accounts.categories = categories.clone();
+    }
+    Ok(accounts)
+}
+
+// Keys by rate from one of the tax key tables, overriding the defaults
+fn rate_keys(
+    tables: &HashMap<String, HashMap<String, String>>,
+    table: &str,
+    defaults: &[(f64, &str)],
+) -> Result<Vec<(f64, String)>, String> {
+    let mut keys: Vec<(f64, String)> = defaults
+        .iter()
+        .map(|(rate, key)| (*rate, key.to_string()))
+        .collect();
+    let mut configured: Vec<(&String, &String)> = tables
+        .get(table)
+        .map(|keys| keys.iter().collect())
+        .unwrap_or_default();
+    configured.sort();
+    for (rate, key) in configured {
+        let rate: f64 = rate
+            .trim_end_matches('%')
+            .replace(',', ".")
+            .parse()
+            .map_err(|_| format!("Invalid tax key rate in accounts file: {rate}"))?;
+        keys.retain(|(key_rate, _)| *key_rate != rate);
+        keys.push((rate, key.clone()));
+    }
+    Ok(keys)
+}
+
+// The tax keys default to the standard DATEV ones: 9 for 19% and 8 for 7%
+// input VAT, 94 and 91 for reverse charge at 19% and 7%
+pub fn parse_accounts(content: &str) -> Result<AccountMap, String> {
+    let tables = parse_toml_tables(content, "accounts file")?;
+    let get = |table: &str, key: &str| {
+        tables
+            .get(table)
+            .and_then(|values| values.get(key))
+            .cloned()
+    };
+    let required = |table: &str, key: &str| {
+        get(table, key).ok_or_else(|| format!("Accounts file has no {table}.{key}"))
+    };
+
+    let account_length = match get("datev", "account_length") {
+        Some(length) => length
+            .parse()
+            .ok()
+            .filter(|length| (4..=9).contains(length))
+            .ok_or_else(|| format!("Invalid datev.account_length (expected 4 to 9): {length}"))?,
+        None => 4,
+    };
+    let tax_keys = rate_keys(&tables, "tax_keys", &[(19.0, "9"), (7.0, "8")])?;
+    let reverse_charge_keys = rate_keys(
+        &tables,
+        "tax_keys.reverse_charge",
+        &[(19.0, "94"), (7.0, "91")],
+    )?;
+
+    Ok(AccountMap {
+        consultant: required("datev", "consultant")?,
+        client: required("datev", "client")?,
+        fiscal_year_start: get("datev", "fiscal_year_start")
+            .map(|date| {
+                parse_date(&date).ok_or_else(|| format!("Invalid datev.fiscal_year_start: {date}"))
+            })
+            .transpose()?,
+        account_length,
+        default_account: required("accounts", "default")?,
+        contra_account: required("accounts", "contra")?,
+        categories: tables.get("categories").cloned().unwrap_or_default(),
+        tax_keys,
+        reverse_charge_keys,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+[datev]
+consultant = 1234567
+client = 10001
+
+[accounts]
+default = 4980   # Betriebsbedarf
+contra = 1200    # Bank
+
+[categories]
+Reisekosten = 4670
+"Büro Bedarf" = 4930
+
+[tax_keys]
+5 = "5"
+
+[tax_keys.reverse_charge]
+19 = "46"
+"#;
+
+    #[test]
+    fn test_parse_accounts() {
+        let accounts = parse_accounts(SAMPLE).unwrap();
+        assert_eq!(accounts.consultant, "1234567");
+        assert_eq!(accounts.account_length, 4);
+        assert_eq!(accounts.account(Some("Reisekosten")), "4670");
+        assert_eq!(accounts.account(Some("Büro Bedarf")), "4930");
+        assert_eq!(accounts.account(Some("Sonstiges")), "4980");
+        assert_eq!(accounts.account(None), "4980");
+        assert_eq!(accounts.contra_account, "1200");
+    }
+
+    #[test]
+    fn test_tax_key() {
+        let accounts = parse_accounts(SAMPLE).unwrap();
+        assert_eq!(accounts.tax_key(19.0, TaxTreatment::Standard), Ok("9"));
+        assert_eq!(accounts.tax_key(7.0, TaxTreatment::Standard), Ok("8"));
+        assert_eq!(accounts.tax_key(5.0, TaxTreatment::Standard), Ok("5"));
+        assert_eq!(accounts.tax_key(0.0, TaxTreatment::Standard), Ok(""));
+        assert_eq!(accounts.tax_key(19.0, TaxTreatment::Exempt), Ok(""));
+        assert_eq!(
+            accounts.tax_key(19.0, TaxTreatment::ReverseCharge),
+            Ok("46")
+        );
+        assert_eq!(accounts.tax_key(7.0, TaxTreatment::ReverseCharge), Ok("91"));
+        assert_eq!(
+            accounts
+                .tax_key(5.0, TaxTreatment::ReverseCharge)
+                .unwrap_err(),
+            "No reverse-charge tax key for 5% (add it under [tax_keys.reverse_charge])"
+        );
+        assert_eq!(
+            accounts.tax_key(16.0, TaxTreatment::Standard).unwrap_err(),
+            "No tax key for 16% (add it under [tax_keys])"
+        );
+    }
+
+    #[test]
+    fn test_parse_ledger_accounts() {
+        let accounts = parse_ledger_accounts(&format!(
+            "{SAMPLE}\n[ledger]\nexpense = \"Aufwand:Sonstiges\"\npayment = \"Aktiva:Girokonto\"\n\n[ledger.categories]\nReisekosten = \"Aufwand:Reisen\"\n"
+        ))
+        .unwrap();
+        assert_eq!(accounts.account(Some("Reisekosten")), "Aufwand:Reisen");
+        assert_eq!(accounts.account(None), "Aufwand:Sonstiges");
+        assert_eq!(accounts.payment, "Aktiva:Girokonto");
+        assert_eq!(accounts.input_vat, "Assets:VAT:Input");
+        assert_eq!(
+            parse_ledger_accounts("").unwrap(),
+            LedgerAccounts::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_accounts_errors() {
+        assert_eq!(
+            parse_accounts(&SAMPLE.replace("contra = 1200", "")).unwrap_err(),
+            "Accounts file has no accounts.contra"
+        );
+        assert_eq!(
+            parse_accounts(&SAMPLE.replace("client = 10001", "client = 1\naccount_length = 12"))
+                .unwrap_err(),
+            "Invalid datev.account_length (expected 4 to 9): 12"
+        );
+        assert_eq!(
+            parse_accounts(&SAMPLE.replace("5 = \"5\"", "\"fünf\" = \"5\"")).unwrap_err(),
+            "Invalid tax key rate in accounts file: fünf"
+        );
+        assert_eq!(
+            parse_accounts(&SAMPLE.replace(
+                "client = 10001",
+                "client = 1\nfiscal_year_start = \"1.7.25\""
+            ))
+            .unwrap_err(),
+            "Invalid datev.fiscal_year_start: 1.7.25"
+        );
+    }
+}
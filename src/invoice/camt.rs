@@ -0,0 +1,161 @@
+use roxmltree::Node;
+
+use super::lines::parse_date;
+use super::statement::{Statement, StatementEntry};
+use super::{xml_child, xml_text};
+
+// ISO 20022 bank-to-customer statement (camt.053), any version
+pub fn parse_camt(content: &str) -> Result<Statement, String> {
+    let document =
+        roxmltree::Document::parse(content).map_err(|e| format!("Invalid CAMT XML: {e}"))?;
+    let root = document.root_element();
+    let report = xml_child(root, &["BkToCstmrStmt"])
+        .ok_or("Unsupported bank statement XML (expected CAMT.053 BkToCstmrStmt)")?;
+    let statements: Vec<Node> = report
+        .children()
+        .filter(|node| node.has_tag_name("Stmt"))
+        .collect();
+    let first = statements.first().ok_or("CAMT statement has no Stmt")?;
+
+    let mut entries = Vec::new();
+    for statement in &statements {
+        for entry in statement
+            .children()
+            .filter(|node| node.has_tag_name("Ntry"))
+        {
+            entries.push(parse_entry(entry, entries.len() + 1)?);
+        }
+    }
+
+    let currency = xml_text(*first, &["Acct", "Ccy"])
+        .or_else(|| xml_child(*first, &["Ntry", "Amt"]).and_then(|amount| amount.attribute("Ccy")))
+        .unwrap_or("EUR");
+    Ok(Statement {
+        account: xml_text(*first, &["Acct", "Id", "IBAN"])
+            .or_else(|| xml_text(*first, &["Acct", "Id", "Othr", "Id"]))
+            .map(str::to_string),
+        currency: currency.to_string(),
+        decimal_comma: false,
+        entries,
+    })
+}
+
+fn parse_entry(entry: Node, number: usize) -> Result<StatementEntry, String> {
+    let context = format!("CAMT entry {number}");
+    let amount: f64 = xml_text(entry, &["Amt"])
+        .and_then(|amount| amount.parse().ok())
+        .ok_or_else(|| format!("{context} has no valid Amt"))?;
+    let debit = match xml_text(entry, &["CdtDbtInd"]) {
+        Some("DBIT") => true,
+        Some("CRDT") => false,
+        _ => return Err(format!("{context} has no valid CdtDbtInd")),
+    };
+    // Some banks only fill in the date with a time
+    let booking_date = match xml_text(entry, &["BookgDt", "Dt"])
+        .or_else(|| xml_text(entry, &["BookgDt", "DtTm"]))
+    {
+        Some(date) => parse_date(&date.chars().take(10).collect::<String>())
+            .ok_or_else(|| format!("{context} has no valid BookgDt"))?,
+        None => String::new(),
+    };
+
+    // The other party is the creditor of a debit and the debtor of a credit;
+    // from version 08 on its name sits one level deeper, under Pty
+    let transaction = xml_child(entry, &["NtryDtls", "TxDtls"]);
+    let party = if debit { "Cdtr" } else { "Dbtr" };
+    let counterparty = transaction
+        .and_then(|details| {
+            xml_text(details, &["RltdPties", party, "Nm"])
+                .or_else(|| xml_text(details, &["RltdPties", party, "Pty", "Nm"]))
+        })
+        .unwrap_or_default();
+    let purpose = transaction
+        .and_then(|details| xml_child(details, &["RmtInf"]))
+        .map(|remittance| {
+            remittance
+                .children()
+                .filter(|node| node.has_tag_name("Ustrd"))
+                .filter_map(|node| node.text())
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|purpose| !purpose.is_empty())
+        .or_else(|| xml_text(entry, &["AddtlNtryInf"]).map(str::to_string))
+        .unwrap_or_default();
+
+    Ok(StatementEntry {
+        booking_date,
+        counterparty: counterparty.to_string(),
+        purpose,
+        amount: if debit { -amount } else { amount },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE02120300000000202051</IBAN></Id><Ccy>EUR</Ccy></Acct>
+      <Ntry>
+        <Amt Ccy="EUR">53.50</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2025-10-03</Dt></BookgDt>
+        <NtryDtls><TxDtls>
+          <RltdPties><Cdtr><Pty><Nm>DB Fernverkehr AG</Nm></Pty></Cdtr></RltdPties>
+          <RmtInf><Ustrd>Ticket 4711</Ustrd><Ustrd>Berlin-Hamburg</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">1190.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><DtTm>2025-10-05T10:00:00</DtTm></BookgDt>
+        <NtryDtls><TxDtls>
+          <RltdPties><Dbtr><Nm>Kunde GmbH</Nm></Dbtr></RltdPties>
+        </TxDtls></NtryDtls>
+        <AddtlNtryInf>Rechnung 2025-042</AddtlNtryInf>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn test_parse_camt() {
+        let statement = parse_camt(SAMPLE).unwrap();
+        assert_eq!(statement.account.as_deref(), Some("DE02120300000000202051"));
+        assert_eq!(statement.currency, "EUR");
+        assert_eq!(
+            statement.entries[0],
+            StatementEntry {
+                booking_date: "2025-10-03".to_string(),
+                counterparty: "DB Fernverkehr AG".to_string(),
+                purpose: "Ticket 4711 Berlin-Hamburg".to_string(),
+                amount: -53.5,
+            }
+        );
+        assert_eq!(statement.entries[1].booking_date, "2025-10-05");
+        assert_eq!(statement.entries[1].counterparty, "Kunde GmbH");
+        assert_eq!(statement.entries[1].purpose, "Rechnung 2025-042");
+        assert_eq!(statement.entries[1].amount, 1190.0);
+    }
+
+    #[test]
+    fn test_parse_camt_errors() {
+        assert_eq!(
+            parse_camt("<Document><BkToCstmrNtfctn/></Document>").unwrap_err(),
+            "Unsupported bank statement XML (expected CAMT.053 BkToCstmrStmt)"
+        );
+        assert_eq!(
+            parse_camt(&SAMPLE.replace("<CdtDbtInd>DBIT</CdtDbtInd>", "")).unwrap_err(),
+            "CAMT entry 1 has no valid CdtDbtInd"
+        );
+        assert_eq!(
+            parse_camt(&SAMPLE.replacen("<Dt>2025-10-03</Dt>", "<Dt>2025103</Dt>", 1)).unwrap_err(),
+            "CAMT entry 1 has no valid BookgDt"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use roxmltree::Node;
+
+use super::{EInvoice, EInvoiceLine, TaxSubtotal, xml_amount, xml_child, xml_text};
+
+// ZUGFeRD / Factur-X and XRechnung in UN/CEFACT CII syntax
+pub fn parse_cii(root: Node) -> Result<EInvoice, String> {
+    let transaction = xml_child(root, &["SupplyChainTradeTransaction"])
+        .ok_or("CII invoice has no SupplyChainTradeTransaction")?;
+    let settlement = xml_child(transaction, &["ApplicableHeaderTradeSettlement"])
+        .ok_or("CII invoice has no ApplicableHeaderTradeSettlement")?;
+    let currency = xml_text(settlement, &["InvoiceCurrencyCode"])
+        .unwrap_or("EUR")
+        .to_string();
+
+    let mut lines = transaction
+        .children()
+        .filter(|node| node.has_tag_name("IncludedSupplyChainTradeLineItem"))
+        .enumerate()
+        .map(|(index, item)| parse_line(item, index + 1))
+        .collect::<Result<Vec<_>, String>>()?;
+    for charge in settlement
+        .children()
+        .filter(|node| node.has_tag_name("SpecifiedTradeAllowanceCharge"))
+    {
+        lines.push(parse_allowance_charge(charge)?);
+    }
+
+    let subtotals = settlement
+        .children()
+        .filter(|node| node.has_tag_name("ApplicableTradeTax"))
+        .map(parse_subtotal)
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let summation = xml_child(
+        settlement,
+        &["SpecifiedTradeSettlementHeaderMonetarySummation"],
+    )
+    .ok_or("CII invoice has no SpecifiedTradeSettlementHeaderMonetarySummation")?;
+    let total = |name: &str| {
+        xml_amount(summation, &[name]).ok_or_else(|| format!("CII invoice has no valid {name}"))
+    };
+    // TaxTotalAmount is repeated in the accounting currency when that differs
+    let vat_total = summation
+        .children()
+        .filter(|node| node.has_tag_name("TaxTotalAmount"))
+        .find(|node| node.attribute("currencyID").is_none_or(|id| id == currency))
+        .and_then(|node| node.text())
+        .and_then(|text| text.trim().parse().ok())
+        .ok_or("CII invoice has no valid TaxTotalAmount")?;
+
+    Ok(EInvoice {
+        number: xml_text(root, &["ExchangedDocument", "ID"])
+            .unwrap_or_default()
+            .to_string(),
+        lines,
+        subtotals,
+        net_total: total("TaxBasisTotalAmount")?,
+        vat_total,
+        gross_total: total("GrandTotalAmount")?,
+        currency,
+    })
+}
+
+fn parse_category(tax: Node, context: &str) -> Result<(String, f64), String> {
+    let category =
+        xml_text(tax, &["CategoryCode"]).ok_or_else(|| format!("{context} has no tax category"))?;
+    // Exempt and reverse-charge categories may omit the rate
+    let rate = match xml_text(tax, &["RateApplicablePercent"]) {
+        Some(rate) => rate
+            .parse()
+            .map_err(|_| format!("{context} has an invalid tax rate: {rate}"))?,
+        None => 0.0,
+    };
+    Ok((category.to_string(), rate))
+}
+
+fn parse_line(item: Node, number: usize) -> Result<EInvoiceLine, String> {
+    let context = format!("CII invoice line {number}");
+    let settlement = xml_child(item, &["SpecifiedLineTradeSettlement"])
+        .ok_or_else(|| format!("{context} has no SpecifiedLineTradeSettlement"))?;
+    let net = xml_amount(
+        settlement,
+        &[
+            "SpecifiedTradeSettlementLineMonetarySummation",
+            "LineTotalAmount",
+        ],
+    )
+    .ok_or_else(|| format!("{context} has no valid LineTotalAmount"))?;
+    let tax = xml_child(settlement, &["ApplicableTradeTax"])
+        .ok_or_else(|| format!("{context} has no ApplicableTradeTax"))?;
+    let (category, rate) = parse_category(tax, &context)?;
+
+    Ok(EInvoiceLine {
+        label: xml_text(item, &["SpecifiedTradeProduct", "Name"]).map(str::to_string),
+        quantity: xml_amount(item, &["SpecifiedLineTradeDelivery", "BilledQuantity"])
+            .unwrap_or(1.0),
+        net,
+        category,
+        rate,
+    })
+}
+
+// Document-level allowances and charges count towards the VAT breakdown of
+// their category like a line, so they are kept as one
+fn parse_allowance_charge(node: Node) -> Result<EInvoiceLine, String> {
+    let is_charge = xml_text(node, &["ChargeIndicator", "Indicator"]) == Some("true");
+    let kind = if is_charge { "Charge" } else { "Allowance" };
+    let amount = xml_amount(node, &["ActualAmount"]).ok_or_else(|| {
+        format!(
+            "CII invoice {} has no valid ActualAmount",
+            kind.to_lowercase()
+        )
+    })?;
+    let tax = xml_child(node, &["CategoryTradeTax"])
+        .ok_or_else(|| format!("CII invoice {} has no tax category", kind.to_lowercase()))?;
+    let (category, rate) = parse_category(tax, &format!("CII invoice {}", kind.to_lowercase()))?;
+
+    Ok(EInvoiceLine {
+        label: Some(match xml_text(node, &["Reason"]) {
+            Some(reason) => format!("{kind}: {reason}"),
+            None => kind.to_string(),
+        }),
+        quantity: 1.0,
+        net: if is_charge { amount } else { -amount },
+        category,
+        rate,
+    })
+}
+
+fn parse_subtotal(tax: Node) -> Result<TaxSubtotal, String> {
+    let context = "CII invoice VAT breakdown";
+    let (category, rate) = parse_category(tax, context)?;
+    Ok(TaxSubtotal {
+        category,
+        rate,
+        basis: xml_amount(tax, &["BasisAmount"])
+            .ok_or_else(|| format!("{context} has no valid BasisAmount"))?,
+        vat: xml_amount(tax, &["CalculatedAmount"])
+            .ok_or_else(|| format!("{context} has no valid CalculatedAmount"))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::invoice::parse_einvoice;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rsm:CrossIndustryInvoice xmlns:rsm="urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100" xmlns:ram="urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100" xmlns:udt="urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100">
+  <rsm:ExchangedDocument>
+    <ram:ID>R-2025-0042</ram:ID>
+    <ram:TypeCode>380</ram:TypeCode>
+  </rsm:ExchangedDocument>
+  <rsm:SupplyChainTradeTransaction>
+    <ram:IncludedSupplyChainTradeLineItem>
+      <ram:SpecifiedTradeProduct><ram:Name>Beratung</ram:Name></ram:SpecifiedTradeProduct>
+      <ram:SpecifiedLineTradeDelivery><ram:BilledQuantity unitCode="HUR">3</ram:BilledQuantity></ram:SpecifiedLineTradeDelivery>
+      <ram:SpecifiedLineTradeSettlement>
+        <ram:ApplicableTradeTax>
+          <ram:TypeCode>VAT</ram:TypeCode>
+          <ram:CategoryCode>S</ram:CategoryCode>
+          <ram:RateApplicablePercent>19</ram:RateApplicablePercent>
+        </ram:ApplicableTradeTax>
+        <ram:SpecifiedTradeSettlementLineMonetarySummation>
+          <ram:LineTotalAmount>300.00</ram:LineTotalAmount>
+        </ram:SpecifiedTradeSettlementLineMonetarySummation>
+      </ram:SpecifiedLineTradeSettlement>
+    </ram:IncludedSupplyChainTradeLineItem>
+    <ram:IncludedSupplyChainTradeLineItem>
+      <ram:SpecifiedTradeProduct><ram:Name>Fachbuch</ram:Name></ram:SpecifiedTradeProduct>
+      <ram:SpecifiedLineTradeDelivery><ram:BilledQuantity unitCode="H87">1</ram:BilledQuantity></ram:SpecifiedLineTradeDelivery>
+      <ram:SpecifiedLineTradeSettlement>
+        <ram:ApplicableTradeTax>
+          <ram:TypeCode>VAT</ram:TypeCode>
+          <ram:CategoryCode>S</ram:CategoryCode>
+          <ram:RateApplicablePercent>7</ram:RateApplicablePercent>
+        </ram:ApplicableTradeTax>
+        <ram:SpecifiedTradeSettlementLineMonetarySummation>
+          <ram:LineTotalAmount>49.90</ram:LineTotalAmount>
+        </ram:SpecifiedTradeSettlementLineMonetarySummation>
+      </ram:SpecifiedLineTradeSettlement>
+    </ram:IncludedSupplyChainTradeLineItem>
+    <ram:ApplicableHeaderTradeSettlement>
+      <ram:InvoiceCurrencyCode>EUR</ram:InvoiceCurrencyCode>
+      <ram:ApplicableTradeTax>
+        <ram:CalculatedAmount>55.10</ram:CalculatedAmount>
+        <ram:TypeCode>VAT</ram:TypeCode>
+        <ram:BasisAmount>290.00</ram:BasisAmount>
+        <ram:CategoryCode>S</ram:CategoryCode>
+        <ram:RateApplicablePercent>19</ram:RateApplicablePercent>
+      </ram:ApplicableTradeTax>
+      <ram:ApplicableTradeTax>
+        <ram:CalculatedAmount>3.49</ram:CalculatedAmount>
+        <ram:TypeCode>VAT</ram:TypeCode>
+        <ram:BasisAmount>49.90</ram:BasisAmount>
+        <ram:CategoryCode>S</ram:CategoryCode>
+        <ram:RateApplicablePercent>7</ram:RateApplicablePercent>
+      </ram:ApplicableTradeTax>
+      <ram:SpecifiedTradeAllowanceCharge>
+        <ram:ChargeIndicator><udt:Indicator>false</udt:Indicator></ram:ChargeIndicator>
+        <ram:ActualAmount>10.00</ram:ActualAmount>
+        <ram:Reason>Treuerabatt</ram:Reason>
+        <ram:CategoryTradeTax>
+          <ram:TypeCode>VAT</ram:TypeCode>
+          <ram:CategoryCode>S</ram:CategoryCode>
+          <ram:RateApplicablePercent>19</ram:RateApplicablePercent>
+        </ram:CategoryTradeTax>
+      </ram:SpecifiedTradeAllowanceCharge>
+      <ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+        <ram:LineTotalAmount>349.90</ram:LineTotalAmount>
+        <ram:AllowanceTotalAmount>10.00</ram:AllowanceTotalAmount>
+        <ram:TaxBasisTotalAmount>339.90</ram:TaxBasisTotalAmount>
+        <ram:TaxTotalAmount currencyID="EUR">58.59</ram:TaxTotalAmount>
+        <ram:GrandTotalAmount>398.49</ram:GrandTotalAmount>
+        <ram:DuePayableAmount>398.49</ram:DuePayableAmount>
+      </ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+    </ram:ApplicableHeaderTradeSettlement>
+  </rsm:SupplyChainTradeTransaction>
+</rsm:CrossIndustryInvoice>
+"#;
+
+    #[test]
+    fn test_parse_cii() {
+        let invoice = parse_einvoice(SAMPLE).unwrap();
+        assert_eq!(invoice.number, "R-2025-0042");
+        assert_eq!(invoice.currency, "EUR");
+        assert_eq!(invoice.lines.len(), 3);
+        assert_eq!(invoice.lines[0].label.as_deref(), Some("Beratung"));
+        assert_eq!(invoice.lines[0].quantity, 3.0);
+        assert_eq!(invoice.lines[0].net, 300.0);
+        assert_eq!(invoice.lines[1].rate, 7.0);
+        assert_eq!(
+            invoice.lines[2].label.as_deref(),
+            Some("Allowance: Treuerabatt")
+        );
+        assert_eq!(invoice.lines[2].net, -10.0);
+        assert_eq!(invoice.subtotals.len(), 2);
+        assert_eq!(invoice.subtotals[0].basis, 290.0);
+        assert_eq!(invoice.subtotals[1].vat, 3.49);
+        assert_eq!(invoice.net_total, 339.9);
+        assert_eq!(invoice.vat_total, 58.59);
+        assert_eq!(invoice.gross_total, 398.49);
+    }
+
+    #[test]
+    fn test_parse_cii_errors() {
+        let without_total = SAMPLE.replace("<ram:LineTotalAmount>49.90</ram:LineTotalAmount>", "");
+        assert_eq!(
+            parse_einvoice(&without_total).unwrap_err(),
+            "CII invoice line 2 has no valid LineTotalAmount"
+        );
+        let bad_rate = SAMPLE.replacen(
+            "<ram:RateApplicablePercent>19<",
+            "<ram:RateApplicablePercent>x<",
+            1,
+        );
+        assert_eq!(
+            parse_einvoice(&bad_rate).unwrap_err(),
+            "CII invoice line 1 has an invalid tax rate: x"
+        );
+        assert!(
+            parse_einvoice("<rsm:CrossIndustryInvoice xmlns:rsm=\"urn:x\"/>")
+                .unwrap_err()
+                .contains("SupplyChainTradeTransaction")
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use std::fs;
+
+use crate::calculator::{Quantity, TaxTreatment};
+use crate::cli::NumberInput;
+
+// Which CSV column holds which value, by header name or 1-based position.
+// Unmapped values fall back to a column of the same name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ColumnMap {
+    pub description: Option<String>,
+    pub amount: Option<String>,
+    pub rate: Option<String>,
+    pub quantity: Option<String>,
+    pub date: Option<String>,
+    pub treatment: Option<String>,
+}
+
+impl ColumnMap {
+    // "amount=Betrag,rate=MwSt,quantity=3"
+    pub fn parse(input: &str) -> Result<ColumnMap, String> {
+        let mut map = ColumnMap::default();
+        for entry in input.split(',') {
+            let (key, column) = entry
+                .split_once('=')
+                .filter(|(_, column)| !column.trim().is_empty())
+                .ok_or_else(|| format!("Invalid column mapping: {entry}"))?;
+            let column = Some(column.trim().to_string());
+            match key.trim().to_lowercase().as_str() {
+                "description" => map.description = column,
+                "amount" => map.amount = column,
+                "rate" => map.rate = column,
+                "quantity" => map.quantity = column,
+                "date" => map.date = column,
+                "treatment" => map.treatment = column,
+                _ => return Err(format!("Unknown column mapping key: {key}")),
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[derive(Debug)]
+pub struct ImportedLine {
+    pub row: String,
+    pub input: NumberInput,
+    pub rate: Option<f64>,
+    // ISO date, from a date column or the booking date of a statement
+    pub date: Option<String>,
+    // What rules match against, and the category a matching rule assigns
+    pub description: Option<String>,
+    pub counterparty: Option<String>,
+    pub category: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct LineImport {
+    pub header: String,
+    pub delimiter: char,
+    pub decimal_comma: bool,
+    pub lines: Vec<ImportedLine>,
+}
+
+pub fn load_lines(path: &str, map: &ColumnMap) -> Result<LineImport, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read line item file {path}: {e}"))?;
+    parse_lines(&content, map)
+}
+
+// The delimiter is whichever candidate occurs most often in the header row
+pub fn detect_delimiter(header: &str) -> char {
+    [';', '\t', '|', ',']
+        .into_iter()
+        .max_by_key(|candidate| header.matches(*candidate).count())
+        .filter(|candidate| header.contains(*candidate))
+        .unwrap_or(',')
+}
+
+// Splits one row, keeping delimiters inside double quotes
+pub fn split_fields(row: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+// The decimal separator is the last separator in an amount. "1,234" alone is
+// ambiguous and does not count either way.
+pub fn detect_decimal_comma<'a>(amounts: impl Iterator<Item = &'a str>) -> bool {
+    let (mut comma, mut dot) = (0, 0);
+    for amount in amounts {
+        match amount.rfind([',', '.']) {
+            Some(position) if amount[position..].starts_with(',') => {
+                let ambiguous = !amount.contains('.') && amount.len() - position - 1 == 3;
+                if !ambiguous {
+                    comma += 1;
+                }
+            }
+            Some(_) => dot += 1,
+            None => {}
+        }
+    }
+    comma > dot
+}
+
+// Drops currency signs, spaces and thousands separators
+fn normalize_amount(input: &str, decimal_comma: bool) -> String {
+    let thousands = if decimal_comma { '.' } else { ',' };
+    input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '€' && *c != thousands)
+        .collect()
+}
+
+// A plain amount as a file holds it, without the command-line syntax of
+// labels, quantities and treatments
+pub fn parse_amount(input: &str, decimal_comma: bool) -> Option<f64> {
+    let normalized = normalize_amount(input, decimal_comma);
+    let normalized = if decimal_comma {
+        normalized.replace(',', ".")
+    } else {
+        normalized
+    };
+    normalized
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+fn parse_rate(input: &str) -> Option<f64> {
+    input
+        .trim_end_matches('%')
+        .trim()
+        .replace(',', ".")
+        .parse()
+        .ok()
+}
+
+// "03.10.2025" or "2025-10-03" as an ISO date
+pub fn parse_date(input: &str) -> Option<String> {
+    let (year, month, day) = match input.split_once('.') {
+        Some((day, rest)) => {
+            let (month, year) = rest.split_once('.')?;
+            (year, month, day)
+        }
+        None => {
+            let mut parts = input.splitn(3, '-');
+            (parts.next()?, parts.next()?, parts.next()?)
+        }
+    };
+    let year: u32 = year.trim().parse().ok()?;
+    let month: u32 = month.trim().parse().ok()?;
+    let day: u32 = day.trim().parse().ok()?;
+    let valid =
+        (1000..=9999).contains(&year) && (1..=12).contains(&month) && (1..=31).contains(&day);
+    valid.then(|| format!("{year}-{month:02}-{day:02}"))
+}
+
+fn resolve_column(
+    columns: &[String],
+    key: &str,
+    mapped: Option<&str>,
+) -> Result<Option<usize>, String> {
+    let Some(column) = mapped else {
+        return Ok(columns.iter().position(|c| c.eq_ignore_ascii_case(key)));
+    };
+    if let Ok(position) = column.parse::<usize>() {
+        if position >= 1 && position <= columns.len() {
+            return Ok(Some(position - 1));
+        }
+    }
+    columns
+        .iter()
+        .position(|c| c.eq_ignore_ascii_case(column))
+        .map(Some)
+        .ok_or_else(|| format!("Column not found for {key}: {column}"))
+}
+
+pub fn parse_lines(content: &str, map: &ColumnMap) -> Result<LineImport, String> {
+    let mut rows = content.lines().filter(|line| !line.trim().is_empty());
+    let header = rows.next().ok_or("Line item file is empty")?;
+    let delimiter = detect_delimiter(header);
+    let columns = split_fields(header, delimiter);
+
+    let amount_column = resolve_column(&columns, "amount", map.amount.as_deref())?
+        .ok_or("Line item file has no amount column (use --map amount=<column>)")?;
+    let description_column = resolve_column(&columns, "description", map.description.as_deref())?;
+    let rate_column = resolve_column(&columns, "rate", map.rate.as_deref())?;
+    let quantity_column = resolve_column(&columns, "quantity", map.quantity.as_deref())?;
+    let date_column = resolve_column(&columns, "date", map.date.as_deref())?;
+    let treatment_column = resolve_column(&columns, "treatment", map.treatment.as_deref())?;
+
+    let rows: Vec<(&str, Vec<String>)> = rows
+        .map(|row| (row, split_fields(row, delimiter)))
+        .collect();
+    let decimal_comma = detect_decimal_comma(
+        rows.iter()
+            .filter_map(|(_, fields)| fields.get(amount_column))
+            .map(String::as_str),
+    );
+
+    let lines = rows
+        .into_iter()
+        .enumerate()
+        .map(|(index, (row, fields))| {
+            let cell = |column: Option<usize>| {
+                column
+                    .and_then(|position| fields.get(position))
+                    .filter(|value| !value.is_empty())
+            };
+            let invalid = |what: &str| match cell(description_column) {
+                Some(description) => {
+                    format!(
+                        "Invalid {what} in line item row {} ({description})",
+                        index + 1
+                    )
+                }
+                None => format!("Invalid {what} in line item row {}", index + 1),
+            };
+
+            let amount = cell(Some(amount_column))
+                .and_then(|value| parse_amount(value, decimal_comma))
+                .ok_or_else(|| invalid("amount"))?;
+            let quantity = match cell(quantity_column) {
+                Some(value) => Some(Quantity {
+                    count: parse_amount(value, decimal_comma)
+                        .filter(|count| *count > 0.0)
+                        .ok_or_else(|| invalid("quantity"))?,
+                    unit_price: amount,
+                }),
+                None => None,
+            };
+            let rate = match cell(rate_column) {
+                Some(value) => Some(parse_rate(value).ok_or_else(|| invalid("rate"))?),
+                None => None,
+            };
+            let treatment = match cell(treatment_column) {
+                Some(value) => TaxTreatment::parse(value).ok_or_else(|| invalid("treatment"))?,
+                None => TaxTreatment::Standard,
+            };
+            let date = match cell(date_column) {
+                Some(value) => Some(parse_date(value).ok_or_else(|| invalid("date"))?),
+                None => None,
+            };
+
+            Ok(ImportedLine {
+                row: row.to_string(),
+                input: NumberInput {
+                    value: quantity.map_or(amount, |q| q.count * q.unit_price),
+                    uses_comma: decimal_comma,
+                    treatment,
+                    quantity,
+                    label: cell(description_column).cloned(),
+                },
+                rate,
+                date,
+                description: cell(description_column).cloned(),
+                counterparty: None,
+                category: None,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(LineImport {
+        header: header.to_string(),
+        delimiter,
+        decimal_comma,
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_map_parse() {
+        let map = ColumnMap::parse("amount=Betrag, rate=MwSt,quantity=3,date=Datum,treatment=Art")
+            .unwrap();
+        assert_eq!(map.amount.as_deref(), Some("Betrag"));
+        assert_eq!(map.rate.as_deref(), Some("MwSt"));
+        assert_eq!(map.quantity.as_deref(), Some("3"));
+        assert_eq!(map.date.as_deref(), Some("Datum"));
+        assert_eq!(map.treatment.as_deref(), Some("Art"));
+        assert_eq!(map.description, None);
+
+        assert_eq!(
+            ColumnMap::parse("price=Betrag").unwrap_err(),
+            "Unknown column mapping key: price"
+        );
+        assert_eq!(
+            ColumnMap::parse("amount").unwrap_err(),
+            "Invalid column mapping: amount"
+        );
+    }
+
+    #[test]
+    fn test_detect_delimiter() {
+        assert_eq!(detect_delimiter("Text;Betrag;MwSt"), ';');
+        assert_eq!(detect_delimiter("description,amount"), ',');
+        assert_eq!(detect_delimiter("description\tamount"), '\t');
+        assert_eq!(detect_delimiter("amount"), ',');
+    }
+
+    #[test]
+    fn test_split_fields() {
+        assert_eq!(
+            split_fields(r#""Taxi, Berlin",23,80 ,"say ""hi""""#, ','),
+            vec!["Taxi, Berlin", "23", "80", r#"say "hi""#]
+        );
+    }
+
+    #[test]
+    fn test_detect_decimal_comma() {
+        assert!(detect_decimal_comma(["12,99", "1.234,50"].into_iter()));
+        assert!(!detect_decimal_comma(["12.99", "1,234.50"].into_iter()));
+        assert!(!detect_decimal_comma(["1,234", "5"].into_iter()));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("03.10.2025").as_deref(), Some("2025-10-03"));
+        assert_eq!(parse_date("2025-10-03").as_deref(), Some("2025-10-03"));
+        assert_eq!(parse_date("3.1.2026").as_deref(), Some("2026-01-03"));
+        assert_eq!(parse_date("03.10.25"), None);
+        assert_eq!(parse_date("2025-13-01"), None);
+        assert_eq!(parse_date("heute"), None);
+    }
+
+    #[test]
+    fn test_parse_lines_with_map() {
+        let content =
+            "Text;Menge;Betrag;MwSt\nTaxi;1;23,80;19 %\nBuch;2;1.070,00;7\nPorto;1;0,85;\n";
+        let map =
+            ColumnMap::parse("description=Text,amount=Betrag,rate=MwSt,quantity=Menge").unwrap();
+        let import = parse_lines(content, &map).unwrap();
+        assert_eq!(import.delimiter, ';');
+        assert!(import.decimal_comma);
+        assert_eq!(import.lines.len(), 3);
+        assert_eq!(import.lines[0].row, "Taxi;1;23,80;19 %");
+        assert_eq!(import.lines[0].rate, Some(19.0));
+        assert_eq!(import.lines[1].input.value, 2140.0);
+        assert_eq!(
+            import.lines[1].input.quantity,
+            Some(Quantity {
+                count: 2.0,
+                unit_price: 1070.0
+            })
+        );
+        assert_eq!(import.lines[0].input.quantity.unwrap().count, 1.0);
+        assert!(import.lines[1].input.uses_comma);
+        assert_eq!(import.lines[2].rate, None);
+    }
+
+    #[test]
+    fn test_parse_lines_default_columns() {
+        let import = parse_lines(
+            "description,amount,date\n\"Taxi, Berlin\",23.80,03.10.2025\n",
+            &ColumnMap::default(),
+        )
+        .unwrap();
+        assert_eq!(import.delimiter, ',');
+        assert!(!import.decimal_comma);
+        assert_eq!(import.lines[0].input.value, 23.8);
+        assert_eq!(import.lines[0].input.quantity, None);
+        assert_eq!(import.lines[0].input.label.as_deref(), Some("Taxi, Berlin"));
+        assert_eq!(import.lines[0].date.as_deref(), Some("2025-10-03"));
+    }
+
+    #[test]
+    fn test_parse_lines_treatment_column() {
+        let import = parse_lines(
+            "description;amount;treatment\nHosting;100,00;rc\nTaxi=5;3x2;\n",
+            &ColumnMap::default(),
+        );
+        assert_eq!(
+            import.unwrap_err(),
+            "Invalid amount in line item row 2 (Taxi=5)"
+        );
+
+        let import = parse_lines(
+            "description;amount;treatment\nHosting;100,00;rc\nTaxi=5;12,00;\n",
+            &ColumnMap::default(),
+        )
+        .unwrap();
+        assert_eq!(import.lines[0].input.treatment, TaxTreatment::ReverseCharge);
+        assert_eq!(import.lines[1].input.treatment, TaxTreatment::Standard);
+        assert_eq!(import.lines[1].input.label.as_deref(), Some("Taxi=5"));
+
+        assert_eq!(
+            parse_lines("amount\n100:rc\n", &ColumnMap::default()).unwrap_err(),
+            "Invalid amount in line item row 1"
+        );
+        assert_eq!(
+            parse_lines(
+                "description;amount;treatment\nHosting;100;zero\n",
+                &ColumnMap::default()
+            )
+            .unwrap_err(),
+            "Invalid treatment in line item row 1 (Hosting)"
+        );
+    }
+
+    #[test]
+    fn test_parse_lines_errors() {
+        assert_eq!(
+            parse_lines("text,price\nTaxi,23.80\n", &ColumnMap::default()).unwrap_err(),
+            "Line item file has no amount column (use --map amount=<column>)"
+        );
+        let map = ColumnMap::parse("amount=Betrag").unwrap();
+        assert_eq!(
+            parse_lines("text,price\nTaxi,23.80\n", &map).unwrap_err(),
+            "Column not found for amount: Betrag"
+        );
+        assert_eq!(
+            parse_lines("amount\nabc\n", &ColumnMap::default()).unwrap_err(),
+            "Invalid amount in line item row 1"
+        );
+        assert_eq!(
+            parse_lines(
+                "description;amount;rate\nTaxi;23,80;x\n",
+                &ColumnMap::default()
+            )
+            .unwrap_err(),
+            "Invalid rate in line item row 1 (Taxi)"
+        );
+        for (row, error) in [
+            ("A;10;0", "Invalid quantity in line item row 1 (A)"),
+            ("B;10;-2", "Invalid quantity in line item row 1 (B)"),
+            ("C;10;2x3", "Invalid quantity in line item row 1 (C)"),
+            ("D;10;inf", "Invalid quantity in line item row 1 (D)"),
+        ] {
+            assert_eq!(
+                parse_lines(
+                    &format!("description;amount;quantity\n{row}\n"),
+                    &ColumnMap::default()
+                )
+                .unwrap_err(),
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("1.234,56 €", true), Some(1234.56));
+        assert_eq!(parse_amount("1,234.56", false), Some(1234.56));
+        assert_eq!(parse_amount("-0,85", true), Some(-0.85));
+        assert_eq!(parse_amount("NaN", false), None);
+        assert_eq!(parse_amount("inf", false), None);
+        assert_eq!(parse_amount("2x3", false), None);
+    }
+}
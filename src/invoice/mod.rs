@@ -0,0 +1,313 @@
+pub mod accounts;
+pub mod camt;
+pub mod cii;
+pub mod lines;
+pub mod mt940;
+pub mod parties;
+pub mod rules;
+pub mod statement;
+pub mod toml;
+pub mod ubl;
+
+use std::fs;
+
+use roxmltree::Node;
+
+use self::lines::{detect_decimal_comma, parse_amount, split_fields};
+
+use crate::cli::InvoiceInput;
+use crate::utils::json::Json;
+
+// An invoice line or document-level allowance/charge of an e-invoice. Amounts
+// are net, as EN 16931 states them; allowances are negative.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EInvoiceLine {
+    pub label: Option<String>,
+    pub quantity: f64,
+    pub net: f64,
+    pub category: String,
+    pub rate: f64,
+}
+
+// A declared VAT breakdown entry (BG-23): one per category and rate
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxSubtotal {
+    pub category: String,
+    pub rate: f64,
+    pub basis: f64,
+    pub vat: f64,
+}
+
+// The syntax-independent content of an e-invoice that the checks work on
+#[derive(Debug, Clone, PartialEq)]
+pub struct EInvoice {
+    pub number: String,
+    pub currency: String,
+    pub lines: Vec<EInvoiceLine>,
+    pub subtotals: Vec<TaxSubtotal>,
+    pub net_total: f64,
+    pub vat_total: f64,
+    pub gross_total: f64,
+}
+
+pub fn load_einvoice(path: &str) -> Result<EInvoice, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Could not read e-invoice {path}: {e}"))?;
+    parse_einvoice(&content)
+}
+
+// The syntax is told apart by the root element
+pub fn parse_einvoice(content: &str) -> Result<EInvoice, String> {
+    let document =
+        roxmltree::Document::parse(content).map_err(|e| format!("Invalid e-invoice XML: {e}"))?;
+    let root = document.root_element();
+    match root.tag_name().name() {
+        "CrossIndustryInvoice" => cii::parse_cii(root),
+        "Invoice" | "CreditNote" => ubl::parse_ubl(root),
+        other => Err(format!("Unsupported e-invoice syntax: {other}")),
+    }
+}
+
+// Follows a path of child elements by local name, ignoring namespaces
+fn xml_child<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| {
+        node.children().find(|child| child.has_tag_name(*name))
+    })
+}
+
+fn xml_text<'a>(node: Node<'a, '_>, path: &[&str]) -> Option<&'a str> {
+    xml_child(node, path)
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn xml_amount(node: Node, path: &[&str]) -> Option<f64> {
+    xml_text(node, path).and_then(|text| text.parse().ok())
+}
+
+pub fn load_invoices(path: &str) -> Result<Vec<InvoiceInput>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Could not read invoice file {path}: {e}"))?;
+
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        parse_invoice_json(&content)
+    } else {
+        parse_invoice_csv(&content)
+    }
+}
+
+// A header row naming gross, net and vat (plus an optional rate) in any order.
+// Spreadsheets with decimal commas export with ';' as the delimiter.
+pub fn parse_invoice_csv(content: &str) -> Result<Vec<InvoiceInput>, String> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next().ok_or("Invoice file is empty")?;
+    let delimiter = if header.contains(';') { ';' } else { ',' };
+
+    let columns: Vec<String> = split_fields(header, delimiter)
+        .iter()
+        .map(|column| column.to_lowercase())
+        .collect();
+    let column = |name: &str| {
+        columns
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| format!("Invoice file has no {name} column"))
+    };
+    let gross_column = column("gross")?;
+    let net_column = column("net")?;
+    let vat_column = column("vat")?;
+    let rate_column = column("rate").ok();
+
+    let rows: Vec<Vec<String>> = lines.map(|line| split_fields(line, delimiter)).collect();
+    let decimal_comma = detect_decimal_comma(
+        rows.iter()
+            .flat_map(|fields| [gross_column, net_column, vat_column].map(|c| fields.get(c)))
+            .flatten()
+            .map(String::as_str),
+    );
+    rows.iter()
+        .enumerate()
+        .map(|(index, fields)| {
+            let field = |position: usize| {
+                fields
+                    .get(position)
+                    .and_then(|field| parse_amount(field, decimal_comma))
+                    .ok_or_else(|| format!("Invalid amount in invoice row {}", index + 1))
+            };
+            let rate = match rate_column {
+                // Rates never carry thousands separators
+                Some(position) => Some(
+                    fields
+                        .get(position)
+                        .and_then(|field| parse_amount(&field.replace(',', "."), false))
+                        .ok_or_else(|| format!("Invalid amount in invoice row {}", index + 1))?,
+                ),
+                None => None,
+            };
+            Ok(InvoiceInput {
+                gross: field(gross_column)?,
+                net: field(net_column)?,
+                vat: field(vat_column)?,
+                rate,
+                uses_comma: decimal_comma,
+            })
+        })
+        .collect()
+}
+
+// Either an array of {"gross", "net", "vat", "rate"?} objects or an object
+// holding that array under "invoices". Amounts may be numbers or strings.
+pub fn parse_invoice_json(content: &str) -> Result<Vec<InvoiceInput>, String> {
+    let document = Json::parse(content).map_err(|e| format!("Invalid invoice JSON: {e}"))?;
+    let items = match document.get("invoices").unwrap_or(&document) {
+        Json::Array(items) => items,
+        _ => return Err("Invoice JSON must be an array of invoices".to_string()),
+    };
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            // Numbers use a decimal point; strings may be written either way
+            let field = |name: &str| match item.get(name) {
+                Some(Json::Number(value)) => parse_amount(value, false).map(|v| (v, false)),
+                Some(Json::String(value)) => {
+                    let decimal_comma = detect_decimal_comma(std::iter::once(value.as_str()));
+                    parse_amount(value, decimal_comma).map(|v| (v, decimal_comma))
+                }
+                _ => None,
+            };
+            let required = |name: &str| {
+                field(name)
+                    .ok_or_else(|| format!("Invoice {} has no valid {name} amount", index + 1))
+            };
+            let (gross, gross_comma) = required("gross")?;
+            let (net, net_comma) = required("net")?;
+            let (vat, vat_comma) = required("vat")?;
+            Ok(InvoiceInput {
+                gross,
+                net,
+                vat,
+                rate: field("rate").map(|(rate, _)| rate),
+                uses_comma: gross_comma || net_comma || vat_comma,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_invoice_csv() {
+        let invoices =
+            parse_invoice_csv("gross,net,vat\n119.00,100.00,19.00\n107,100,7\n").unwrap();
+        assert_eq!(invoices.len(), 2);
+        assert_eq!(invoices[0].gross, 119.0);
+        assert_eq!(invoices[1].vat, 7.0);
+        assert_eq!(invoices[1].rate, None);
+        assert!(!invoices[0].uses_comma);
+    }
+
+    #[test]
+    fn test_parse_invoice_csv_semicolon_with_rate() {
+        let invoices = parse_invoice_csv("Rate;VAT;Net;Gross\n7;7,00;100,00;107,00\n").unwrap();
+        assert_eq!(invoices[0].gross, 107.0);
+        assert_eq!(invoices[0].net, 100.0);
+        assert_eq!(invoices[0].rate, Some(7.0));
+        assert!(invoices[0].uses_comma);
+    }
+
+    #[test]
+    fn test_parse_invoice_csv_quoted_fields() {
+        let invoices =
+            parse_invoice_csv("\"gross\",\"net\",\"vat\"\n\"1190,00\",\"1000,00\",\"190,00\"\n")
+                .unwrap();
+        assert_eq!(invoices[0].gross, 1190.0);
+        assert_eq!(invoices[0].net, 1000.0);
+        assert!(invoices[0].uses_comma);
+    }
+
+    #[test]
+    fn test_parse_invoice_csv_errors() {
+        assert_eq!(
+            parse_invoice_csv("gross,net\n119,100\n").unwrap_err(),
+            "Invoice file has no vat column"
+        );
+        assert_eq!(
+            parse_invoice_csv("gross,net,vat\n119,abc,19\n").unwrap_err(),
+            "Invalid amount in invoice row 1"
+        );
+        assert_eq!(
+            parse_invoice_csv("gross,net,vat\nNaN,100,19\n").unwrap_err(),
+            "Invalid amount in invoice row 1"
+        );
+        assert!(parse_invoice_csv("gross,net,vat\ninf,100,19\n").is_err());
+        assert!(parse_invoice_csv("").is_err());
+    }
+
+    #[test]
+    fn test_parse_invoice_csv_thousands_separator() {
+        let invoices = parse_invoice_csv("gross;net;vat\n1.469,11;1.234,55;234,56\n").unwrap();
+        assert!((invoices[0].gross - 1469.11).abs() < 1e-9);
+        assert!((invoices[0].net - 1234.55).abs() < 1e-9);
+        assert!(invoices[0].uses_comma);
+    }
+
+    #[test]
+    fn test_parse_invoice_json() {
+        let invoices = parse_invoice_json(
+            r#"[{"gross": 119, "net": 100, "vat": 19}, {"gross": "107,00", "net": "100,00", "vat": "7,00", "rate": 7}]"#,
+        )
+        .unwrap();
+        assert_eq!(invoices.len(), 2);
+        assert_eq!(invoices[0].net, 100.0);
+        assert_eq!(invoices[1].rate, Some(7.0));
+        assert!(invoices[1].uses_comma);
+
+        let invoices =
+            parse_invoice_json(r#"{"invoices": [{"gross": 119, "net": 100, "vat": 19}]}"#).unwrap();
+        assert_eq!(invoices.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_invoice_json_errors() {
+        assert_eq!(
+            parse_invoice_json(r#"[{"gross": 119, "net": 100}]"#).unwrap_err(),
+            "Invoice 1 has no valid vat amount"
+        );
+        assert!(parse_invoice_json(r#"{"gross": 119}"#).is_err());
+        assert!(parse_invoice_json(r#"[{"gross": "NaN", "net": "inf", "vat": 19}]"#).is_err());
+        assert!(parse_invoice_json("[").is_err());
+    }
+
+    #[test]
+    fn test_parse_einvoice_unsupported() {
+        assert_eq!(
+            parse_einvoice("<Order/>").unwrap_err(),
+            "Unsupported e-invoice syntax: Order"
+        );
+        assert!(
+            parse_einvoice("<Invoice")
+                .unwrap_err()
+                .starts_with("Invalid e-invoice XML")
+        );
+        assert!(
+            load_einvoice("/nonexistent/invoice.xml")
+                .unwrap_err()
+                .starts_with("Could not read e-invoice")
+        );
+    }
+
+    #[test]
+    fn test_load_invoices_missing_file() {
+        assert!(
+            load_invoices("/nonexistent/invoices.csv")
+                .unwrap_err()
+                .starts_with("Could not read invoice file")
+        );
+    }
+}
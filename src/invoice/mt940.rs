@@ -0,0 +1,218 @@
+use super::lines::parse_date;
+use super::statement::{Statement, StatementEntry};
+
+// SWIFT MT940 customer statement as German banks export it. A field starts
+// with :tag: and wraps onto the following lines.
+pub fn parse_mt940(content: &str) -> Result<Statement, String> {
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim_end();
+        let tag = line
+            .strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(tag, _)| tag.len() <= 3 && tag.starts_with(|c: char| c.is_ascii_digit()));
+        match (tag, fields.last_mut()) {
+            (Some((tag, value)), _) => fields.push((tag, value.to_string())),
+            // The "-" ending a message and lines before the first field
+            (None, _) if line == "-" => {}
+            (None, Some((_, value))) => value.push_str(line),
+            (None, None) => {}
+        }
+    }
+
+    let mut statement = Statement {
+        account: None,
+        currency: "EUR".to_string(),
+        decimal_comma: true,
+        entries: Vec::new(),
+    };
+    for (tag, value) in fields {
+        match tag {
+            "25" => statement.account = Some(value.trim().to_string()),
+            // The opening balance: C or D, YYMMDD, then the currency
+            "60F" | "60M" => {
+                if let Some(currency) = value.get(7..10) {
+                    statement.currency = currency.to_string();
+                }
+            }
+            "61" => {
+                let number = statement.entries.len() + 1;
+                statement
+                    .entries
+                    .push(parse_statement_line(&value, number)?);
+            }
+            "86" => {
+                if let Some(entry) = statement.entries.last_mut() {
+                    let (counterparty, purpose) = parse_information(&value);
+                    entry.counterparty = counterparty;
+                    entry.purpose = purpose;
+                }
+            }
+            _ => {}
+        }
+    }
+    if statement.entries.is_empty() && statement.account.is_none() {
+        return Err("MT940 statement has no :25: account or :61: lines".to_string());
+    }
+    Ok(statement)
+}
+
+fn digits(input: &str) -> Option<u32> {
+    input
+        .chars()
+        .all(|c| c.is_ascii_digit())
+        .then(|| input.parse().ok())
+        .flatten()
+}
+
+// YYMMDD value date, optional MMDD booking date, C/D/RC/RD mark, optional
+// funds code letter, amount with a decimal comma, then the transaction type
+fn parse_statement_line(value: &str, number: usize) -> Result<StatementEntry, String> {
+    let invalid = || format!("Invalid MT940 statement line {number}: {value}");
+    let year = value.get(0..2).and_then(digits).ok_or_else(invalid)? + 2000;
+    let value_month = value.get(2..4).and_then(digits).ok_or_else(invalid)?;
+    let value_day = value.get(4..6).and_then(digits).ok_or_else(invalid)?;
+    let mut rest = &value[6..];
+
+    let (mut month, mut day) = (value_month, value_day);
+    let mut booking_year = year;
+    if let Some(entry_date) = rest.get(0..4).and_then(digits) {
+        (month, day) = (entry_date / 100, entry_date % 100);
+        // A booking across the turn of the year
+        if value_month == 12 && month == 1 {
+            booking_year += 1;
+        } else if value_month == 1 && month == 12 {
+            booking_year -= 1;
+        }
+        rest = &rest[4..];
+    }
+
+    let (debit, rest) = if let Some(rest) = rest.strip_prefix("RC") {
+        (true, rest)
+    } else if let Some(rest) = rest.strip_prefix("RD") {
+        (false, rest)
+    } else if let Some(rest) = rest.strip_prefix('D') {
+        (true, rest)
+    } else if let Some(rest) = rest.strip_prefix('C') {
+        (false, rest)
+    } else {
+        return Err(invalid());
+    };
+    let rest = rest
+        .strip_prefix(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(rest);
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit() && c != ',')
+        .unwrap_or(rest.len());
+    let amount: f64 = rest[..end]
+        .replace(',', ".")
+        .parse()
+        .map_err(|_| invalid())?;
+
+    let booking_date = parse_date(&format!("{booking_year}-{month}-{day}")).ok_or_else(invalid)?;
+    Ok(StatementEntry {
+        booking_date,
+        counterparty: String::new(),
+        purpose: String::new(),
+        amount: if debit { -amount } else { amount },
+    })
+}
+
+// Structured :86: fields start with a three-digit transaction code followed by
+// ?NN subfields: ?20-?29 and ?60-?63 the purpose, ?32-?33 the counterparty.
+// Anything else is taken as the purpose as a whole.
+fn parse_information(value: &str) -> (String, String) {
+    let structured = value.len() > 4
+        && value.get(0..3).and_then(digits).is_some()
+        && value[3..].starts_with('?');
+    if !structured {
+        return (String::new(), value.trim().to_string());
+    }
+
+    let (mut counterparty, mut purpose) = (String::new(), String::new());
+    for subfield in value[4..].split('?') {
+        let (Some(code), Some(text)) = (subfield.get(0..2).and_then(digits), subfield.get(2..))
+        else {
+            continue;
+        };
+        match code {
+            20..=29 | 60..=63 => purpose.push_str(text),
+            32 | 33 => counterparty.push_str(text),
+            _ => {}
+        }
+    }
+    // SEPA puts the remittance text after SVWZ+, behind references such as
+    // EREF+ and before a differing payer or payee (ABWA+, ABWE+)
+    if let Some((_, remittance)) = purpose.split_once("SVWZ+") {
+        let end = ["ABWA+", "ABWE+"]
+            .iter()
+            .filter_map(|tag| remittance.find(tag))
+            .min()
+            .unwrap_or(remittance.len());
+        purpose = remittance[..end].to_string();
+    }
+    (counterparty.trim().to_string(), purpose.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = ":20:STARTUMSE
+:25:12030000/0000202051
+:28C:00042/001
+:60F:C250930EUR1234,56
+:61:2510031003D53,50NDDTNONREF
+:86:105?00SEPA-LASTSCHRIFT?20EREF+4711?21SVWZ+Ticket 4711 Berlin-Ham
+burg?32DB Fernverkehr AG
+:61:2512310102CR1190,00NTRFNONREF
+:86:Gutschrift Rechnung 2025-042
+:62F:C251003EUR2371,06
+-
+";
+
+    #[test]
+    fn test_parse_mt940() {
+        let statement = parse_mt940(SAMPLE).unwrap();
+        assert_eq!(statement.account.as_deref(), Some("12030000/0000202051"));
+        assert_eq!(statement.currency, "EUR");
+        assert!(statement.decimal_comma);
+        assert_eq!(
+            statement.entries[0],
+            StatementEntry {
+                booking_date: "2025-10-03".to_string(),
+                counterparty: "DB Fernverkehr AG".to_string(),
+                purpose: "Ticket 4711 Berlin-Hamburg".to_string(),
+                amount: -53.5,
+            }
+        );
+        // Value date in December, booked in January
+        assert_eq!(statement.entries[1].booking_date, "2026-01-02");
+        assert_eq!(statement.entries[1].counterparty, "");
+        assert_eq!(statement.entries[1].purpose, "Gutschrift Rechnung 2025-042");
+        assert_eq!(statement.entries[1].amount, 1190.0);
+    }
+
+    #[test]
+    fn test_parse_statement_line() {
+        let entry = parse_statement_line("251003D4,20NMSCNONREF", 1).unwrap();
+        assert_eq!(entry.booking_date, "2025-10-03");
+        assert_eq!(entry.amount, -4.2);
+        assert_eq!(
+            parse_statement_line("251003X4,20", 2).unwrap_err(),
+            "Invalid MT940 statement line 2: 251003X4,20"
+        );
+        assert_eq!(
+            parse_statement_line("251340D4,20", 3).unwrap_err(),
+            "Invalid MT940 statement line 3: 251340D4,20"
+        );
+    }
+
+    #[test]
+    fn test_parse_mt940_errors() {
+        assert_eq!(
+            parse_mt940(":20:STARTUMSE\n-\n").unwrap_err(),
+            "MT940 statement has no :25: account or :61: lines"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+
+use super::lines::parse_date;
+use super::toml::parse_toml_tables;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Party {
+    pub name: String,
+    pub street: Option<String>,
+    pub postcode: String,
+    pub city: String,
+    pub country: String,
+    pub email: String,
+    pub vat_id: Option<String>,
+    pub tax_number: Option<String>,
+    pub contact: Option<String>,
+    pub phone: Option<String>,
+}
+
+// Everything an invoice needs besides its lines, from a TOML file with
+// [invoice], [seller] and [buyer] tables
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InvoiceParties {
+    pub number: String,
+    pub date: String,
+    pub buyer_reference: String,
+    pub currency: Option<String>,
+    pub payment_terms: Option<String>,
+    pub iban: String,
+    pub seller: Party,
+    pub buyer: Party,
+}
+
+pub fn load_parties(path: &str) -> Result<InvoiceParties, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Could not read parties file {path}: {e}"))?;
+    parse_parties(&content)
+}
+
+pub fn parse_parties(content: &str) -> Result<InvoiceParties, String> {
+    let tables = parse_toml_tables(content, "parties file")?;
+    let get = |table: &str, key: &str| {
+        tables
+            .get(table)
+            .and_then(|values| values.get(key))
+            .cloned()
+    };
+    let required = |table: &str, key: &str| {
+        get(table, key).ok_or_else(|| format!("Parties file has no {table}.{key}"))
+    };
+    let party = |table: &str| -> Result<Party, String> {
+        Ok(Party {
+            name: required(table, "name")?,
+            street: get(table, "street"),
+            postcode: required(table, "postcode")?,
+            city: required(table, "city")?,
+            country: get(table, "country").unwrap_or_else(|| "DE".to_string()),
+            email: required(table, "email")?,
+            vat_id: get(table, "vat_id"),
+            tax_number: get(table, "tax_number"),
+            contact: get(table, "contact"),
+            phone: get(table, "phone"),
+        })
+    };
+
+    let date = required("invoice", "date")?;
+    let date = parse_date(&date).ok_or_else(|| format!("Invalid invoice.date: {date}"))?;
+
+    // XRechnung requires a seller contact (BR-DE-2) and a tax registration
+    let seller = party("seller")?;
+    if seller.contact.is_none() || seller.phone.is_none() {
+        return Err("Parties file needs seller.contact and seller.phone".to_string());
+    }
+    if seller.vat_id.is_none() && seller.tax_number.is_none() {
+        return Err("Parties file needs seller.vat_id or seller.tax_number".to_string());
+    }
+
+    Ok(InvoiceParties {
+        number: required("invoice", "number")?,
+        date,
+        buyer_reference: required("invoice", "buyer_reference")?,
+        currency: get("invoice", "currency"),
+        payment_terms: get("invoice", "payment_terms"),
+        iban: required("seller", "iban")?,
+        seller,
+        buyer: party("buyer")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+# Rechnungsdaten
+[invoice]
+number = "2025-042"
+date = "2025-10-19"
+buyer_reference = "04011000-12345-34"  # Leitweg-ID
+payment_terms = "Zahlbar innerhalb von 14 Tagen"
+
+[seller]
+name = "Kai Beispiel IT-Beratung"
+street = "Musterweg 1"
+postcode = "10115"
+city = "Berlin"
+email = "rechnung@beispiel.de"
+contact = "Kai Beispiel"
+phone = "+49 30 1234567"
+vat_id = "DE123456789"
+iban = "DE02120300000000202051"
+
+[buyer]
+name = "Amt für \"Digitales\""
+postcode = "20095"
+city = "Hamburg"
+email = "eingang@amt.example"
+"#;
+
+    #[test]
+    fn test_parse_parties() {
+        let parties = parse_parties(SAMPLE).unwrap();
+        assert_eq!(parties.number, "2025-042");
+        assert_eq!(parties.buyer_reference, "04011000-12345-34");
+        assert_eq!(parties.currency, None);
+        assert_eq!(parties.iban, "DE02120300000000202051");
+        assert_eq!(parties.seller.country, "DE");
+        assert_eq!(parties.seller.street.as_deref(), Some("Musterweg 1"));
+        assert_eq!(parties.buyer.name, "Amt für \"Digitales\"");
+        assert_eq!(parties.buyer.street, None);
+    }
+
+    #[test]
+    fn test_parse_parties_errors() {
+        assert_eq!(
+            parse_parties(&SAMPLE.replace("city = \"Hamburg\"", "")).unwrap_err(),
+            "Parties file has no buyer.city"
+        );
+        assert_eq!(
+            parse_parties(&SAMPLE.replace("2025-10-19", "2025-99-99")).unwrap_err(),
+            "Invalid invoice.date: 2025-99-99"
+        );
+        assert_eq!(
+            parse_parties(&SAMPLE.replace("\"2025-10-19\"", "2025-10-19"))
+                .unwrap()
+                .date,
+            "2025-10-19"
+        );
+        assert_eq!(
+            parse_parties(&SAMPLE.replace("2025-10-19", "19.10.2025"))
+                .unwrap()
+                .date,
+            "2025-10-19"
+        );
+        assert_eq!(
+            parse_parties(&SAMPLE.replace("vat_id = \"DE123456789\"", "")).unwrap_err(),
+            "Parties file needs seller.vat_id or seller.tax_number"
+        );
+        assert_eq!(
+            parse_parties(&SAMPLE.replace("phone = \"+49 30 1234567\"", "")).unwrap_err(),
+            "Parties file needs seller.contact and seller.phone"
+        );
+    }
+}
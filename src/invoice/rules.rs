@@ -0,0 +1,256 @@
+use std::fs;
+
+use regex::Regex;
+
+use super::lines::{ImportedLine, split_fields};
+use crate::calculator::TaxTreatment;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleField {
+    Any,
+    Description,
+    Counterparty,
+}
+
+// A keyword is found anywhere in the text; /pattern/ is a regular expression.
+// Both ignore case.
+#[derive(Debug, Clone)]
+pub enum RulePattern {
+    Keyword(String),
+    Regex(Regex),
+}
+
+impl RulePattern {
+    fn parse(input: &str) -> Result<RulePattern, String> {
+        match input
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(pattern) => Regex::new(&format!("(?i){pattern}"))
+                .map(RulePattern::Regex)
+                .map_err(|_| format!("Invalid rule pattern: {input}")),
+            None => Ok(RulePattern::Keyword(input.to_lowercase())),
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            RulePattern::Keyword(keyword) => text.to_lowercase().contains(keyword),
+            RulePattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+// Sets the rate, treatment and category of matching imported lines
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub field: RuleField,
+    pub pattern: RulePattern,
+    pub rate: Option<f64>,
+    pub treatment: TaxTreatment,
+    pub category: Option<String>,
+}
+
+// "7", "exempt" or "19:rc"
+fn parse_rule_value(input: &str) -> Option<(Option<f64>, TaxTreatment)> {
+    let mut rate = None;
+    let mut treatment = TaxTreatment::Standard;
+    for part in input.split(':') {
+        let part = part.trim();
+        match TaxTreatment::parse(part) {
+            Some(parsed) => treatment = parsed,
+            None => {
+                let number = part.trim_end_matches('%').trim().replace(',', ".");
+                rate = Some(number.parse().ok()?);
+            }
+        }
+    }
+    Some((rate, treatment))
+}
+
+impl Rule {
+    // "Deutsche Bahn=7" matches the counterparty by keyword
+    pub fn parse_payee(input: &str) -> Result<Rule, String> {
+        let invalid = || format!("Invalid --payee value: {input}");
+        let (pattern, value) = input
+            .rsplit_once('=')
+            .filter(|(pattern, _)| !pattern.trim().is_empty())
+            .ok_or_else(invalid)?;
+        let (rate, treatment) = parse_rule_value(value).ok_or_else(invalid)?;
+        Ok(Rule {
+            field: RuleField::Counterparty,
+            pattern: RulePattern::Keyword(pattern.trim().to_lowercase()),
+            rate,
+            treatment,
+            category: None,
+        })
+    }
+
+    pub fn matches(&self, line: &ImportedLine) -> bool {
+        let description = line
+            .description
+            .as_deref()
+            .filter(|_| self.field != RuleField::Counterparty);
+        let counterparty = line
+            .counterparty
+            .as_deref()
+            .filter(|_| self.field != RuleField::Description);
+        [description, counterparty]
+            .into_iter()
+            .flatten()
+            .any(|text| self.pattern.is_match(text))
+    }
+}
+
+pub fn load_rules(path: &str) -> Result<Vec<Rule>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Could not read rules file {path}: {e}"))?;
+    parse_rules(&content)
+}
+
+// One rule per line: pattern; rate or treatment; optional category. The
+// pattern may be limited to one field with a description: or counterparty:
+// prefix. Blank lines and lines starting with # are skipped.
+pub fn parse_rules(content: &str) -> Result<Vec<Rule>, String> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            let invalid = || format!("Invalid rule in line {}: {}", index + 1, line.trim());
+            let fields = split_fields(line, ';');
+            let (pattern, value) = match fields.as_slice() {
+                [pattern, value, ..] if !pattern.is_empty() => (pattern, value),
+                _ => return Err(invalid()),
+            };
+            let (field, pattern) = if let Some(rest) = pattern.strip_prefix("description:") {
+                (RuleField::Description, rest.trim())
+            } else if let Some(rest) = pattern.strip_prefix("counterparty:") {
+                (RuleField::Counterparty, rest.trim())
+            } else {
+                (RuleField::Any, pattern.as_str())
+            };
+            let (rate, treatment) = parse_rule_value(value).ok_or_else(invalid)?;
+            Ok(Rule {
+                field,
+                pattern: RulePattern::parse(pattern)?,
+                rate,
+                treatment,
+                category: fields
+                    .get(2)
+                    .filter(|category| !category.is_empty())
+                    .cloned(),
+            })
+        })
+        .collect()
+}
+
+// The first matching rule wins. A rate already given by the line itself is
+// kept. Returns the positions of the lines no rule matched.
+pub fn apply_rules(lines: &mut [ImportedLine], rules: &[Rule]) -> Vec<usize> {
+    let mut unmatched = Vec::new();
+    for (index, line) in lines.iter_mut().enumerate() {
+        let Some(rule) = rules.iter().find(|rule| rule.matches(line)) else {
+            unmatched.push(index);
+            continue;
+        };
+        if line.rate.is_none() {
+            line.rate = rule.rate;
+        }
+        if rule.treatment != TaxTreatment::Standard {
+            line.input.treatment = rule.treatment;
+        }
+        line.category = rule.category.clone();
+    }
+    unmatched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoice::lines::{ColumnMap, parse_lines};
+
+    fn line(description: &str, counterparty: Option<&str>) -> ImportedLine {
+        let csv = format!("description;amount\n{description};10\n");
+        let mut line = parse_lines(&csv, &ColumnMap::default())
+            .unwrap()
+            .lines
+            .remove(0);
+        line.counterparty = counterparty.map(str::to_string);
+        line
+    }
+
+    #[test]
+    fn test_parse_payee() {
+        let rule = Rule::parse_payee("Deutsche Bahn=7").unwrap();
+        assert_eq!(rule.field, RuleField::Counterparty);
+        assert_eq!(rule.rate, Some(7.0));
+        assert_eq!(rule.treatment, TaxTreatment::Standard);
+
+        let rule = Rule::parse_payee("Google=19:rc").unwrap();
+        assert_eq!(rule.rate, Some(19.0));
+        assert_eq!(rule.treatment, TaxTreatment::ReverseCharge);
+
+        assert_eq!(
+            Rule::parse_payee("Miete=exempt").unwrap().treatment,
+            TaxTreatment::Exempt
+        );
+        assert_eq!(
+            Rule::parse_payee("Bahn=abc").unwrap_err(),
+            "Invalid --payee value: Bahn=abc"
+        );
+        assert!(Rule::parse_payee("=7").is_err());
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules(
+            "# Reisen\n/^DB (Fernverkehr|Regio)/; 7; Reisekosten\n\ncounterparty:REWE;7\ndescription:Miete; exempt ;Miete\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 3);
+        assert!(matches!(rules[0].pattern, RulePattern::Regex(_)));
+        assert_eq!(rules[0].category.as_deref(), Some("Reisekosten"));
+        assert_eq!(rules[1].field, RuleField::Counterparty);
+        assert_eq!(rules[1].category, None);
+        assert_eq!(rules[2].field, RuleField::Description);
+        assert_eq!(rules[2].treatment, TaxTreatment::Exempt);
+
+        assert_eq!(
+            parse_rules("# x\nREWE\n").unwrap_err(),
+            "Invalid rule in line 2: REWE"
+        );
+        assert_eq!(
+            parse_rules("REWE;sieben\n").unwrap_err(),
+            "Invalid rule in line 1: REWE;sieben"
+        );
+        assert_eq!(
+            parse_rules("/(/;7\n").unwrap_err(),
+            "Invalid rule pattern: /(/"
+        );
+    }
+
+    #[test]
+    fn test_apply_rules() {
+        let rules = parse_rules(
+            "/^db (fernverkehr|regio)/;7;Reisekosten\ncounterparty:rewe;7;Bewirtung\nMiete;exempt\n",
+        )
+        .unwrap();
+        let mut lines = vec![
+            line("DB Regio Ticket", None),
+            line("Einkauf", Some("REWE Markt GmbH")),
+            line("Rewe-Gutschein", None),
+            line("Miete Oktober", None),
+            line("Taxi", None),
+        ];
+        lines[3].rate = Some(0.0);
+        let unmatched = apply_rules(&mut lines, &rules);
+        assert_eq!(unmatched, vec![2, 4]);
+        assert_eq!(lines[0].rate, Some(7.0));
+        assert_eq!(lines[0].category.as_deref(), Some("Reisekosten"));
+        assert_eq!(lines[1].category.as_deref(), Some("Bewirtung"));
+        assert_eq!(lines[3].input.treatment, TaxTreatment::Exempt);
+        assert_eq!(lines[3].rate, Some(0.0));
+        assert_eq!(lines[4].rate, None);
+    }
+}
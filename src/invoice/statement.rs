@@ -0,0 +1,133 @@
+use std::fs;
+
+use super::lines::ImportedLine;
+use super::{camt, mt940};
+use crate::calculator::TaxTreatment;
+use crate::cli::NumberInput;
+
+// One booking of a bank statement. Debits are negative.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementEntry {
+    pub booking_date: String,
+    pub counterparty: String,
+    pub purpose: String,
+    pub amount: f64,
+}
+
+impl StatementEntry {
+    // Card payments often name the merchant only in the purpose
+    pub fn label(&self) -> String {
+        let name = if self.counterparty.is_empty() {
+            &self.purpose
+        } else {
+            &self.counterparty
+        };
+        format!("{} {name}", self.booking_date).trim().to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub account: Option<String>,
+    pub currency: String,
+    pub decimal_comma: bool,
+    pub entries: Vec<StatementEntry>,
+}
+
+impl Statement {
+    pub fn debits(&self) -> impl Iterator<Item = &StatementEntry> {
+        self.entries.iter().filter(|entry| entry.amount < 0.0)
+    }
+}
+
+pub fn load_statement(path: &str) -> Result<Statement, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read bank statement {path}: {e}"))?;
+    parse_statement(&content)
+}
+
+// CAMT is XML; MT940 is a SWIFT text message made of :tag: fields
+pub fn parse_statement(content: &str) -> Result<Statement, String> {
+    let content = content.trim_start_matches('\u{feff}').trim_start();
+    if content.starts_with('<') {
+        camt::parse_camt(content)
+    } else if content.contains(":61:") || content.contains(":20:") {
+        mt940::parse_mt940(content)
+    } else {
+        Err("Unsupported bank statement format (expected CAMT.053 XML or MT940)".to_string())
+    }
+}
+
+// The outgoing payments as lines to calculate, with the gross as a positive
+// amount. Rules match the purpose as the description and the counterparty.
+pub fn statement_lines(statement: &Statement) -> Vec<ImportedLine> {
+    statement
+        .debits()
+        .map(|entry| ImportedLine {
+            row: entry.purpose.clone(),
+            input: NumberInput {
+                value: -entry.amount,
+                uses_comma: statement.decimal_comma,
+                treatment: TaxTreatment::Standard,
+                quantity: None,
+                label: Some(entry.label()),
+            },
+            rate: None,
+            date: Some(entry.booking_date.clone()).filter(|date| !date.is_empty()),
+            description: Some(entry.purpose.clone()).filter(|purpose| !purpose.is_empty()),
+            counterparty: Some(entry.counterparty.clone())
+                .filter(|counterparty| !counterparty.is_empty()),
+            category: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(counterparty: &str, amount: f64) -> StatementEntry {
+        StatementEntry {
+            booking_date: "2025-10-03".to_string(),
+            counterparty: counterparty.to_string(),
+            purpose: "Kartenzahlung".to_string(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_statement_lines() {
+        let statement = Statement {
+            account: None,
+            currency: "EUR".to_string(),
+            decimal_comma: true,
+            entries: vec![
+                entry("DB Fernverkehr AG", -53.5),
+                entry("Kunde GmbH", 1190.0),
+                entry("", -4.2),
+            ],
+        };
+        let lines = statement_lines(&statement);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].input.value, 53.5);
+        assert_eq!(lines[0].description.as_deref(), Some("Kartenzahlung"));
+        assert_eq!(lines[0].counterparty.as_deref(), Some("DB Fernverkehr AG"));
+        assert_eq!(
+            lines[0].input.label.as_deref(),
+            Some("2025-10-03 DB Fernverkehr AG")
+        );
+        assert_eq!(lines[1].counterparty, None);
+        assert_eq!(
+            lines[1].input.label.as_deref(),
+            Some("2025-10-03 Kartenzahlung")
+        );
+    }
+
+    #[test]
+    fn test_parse_statement_unsupported() {
+        assert_eq!(
+            parse_statement("Datum;Betrag\n").unwrap_err(),
+            "Unsupported bank statement format (expected CAMT.053 XML or MT940)"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use toml::{Table, Value};
+
+// Collects the values of `table` under `name` and its subtables under their
+// dotted names, so [ledger.categories] becomes "ledger.categories"
+fn flatten_table(
+    name: &str,
+    table: Table,
+    source: &str,
+    tables: &mut HashMap<String, HashMap<String, String>>,
+) -> Result<(), String> {
+    let mut values = HashMap::new();
+    for (key, value) in table {
+        let value = match value {
+            Value::String(text) => text,
+            Value::Integer(number) => number.to_string(),
+            Value::Float(number) => number.to_string(),
+            Value::Boolean(flag) => flag.to_string(),
+            Value::Datetime(date) => date.to_string(),
+            Value::Table(subtable) => {
+                let subname = if name.is_empty() {
+                    key
+                } else {
+                    format!("{name}.{key}")
+                };
+                flatten_table(&subname, subtable, source, tables)?;
+                continue;
+            }
+            Value::Array(_) => {
+                return Err(format!("Unsupported array for {key} in {source}"));
+            }
+        };
+        values.insert(key, value);
+    }
+    tables.entry(name.to_string()).or_default().extend(values);
+    Ok(())
+}
+
+// The configuration files as [tables] of key = value pairs, every value as
+// its text; keys outside a table sit in the "" table
+pub fn parse_toml_tables(
+    content: &str,
+    source: &str,
+) -> Result<HashMap<String, HashMap<String, String>>, String> {
+    let table: Table = content.parse().map_err(|error: toml::de::Error| {
+        let line = error
+            .span()
+            .map(|span| content[..span.start].matches('\n').count() + 1);
+        match line {
+            Some(line) => format!("Invalid line {line} in {source}: {}", error.message()),
+            None => format!("Invalid {source}: {}", error.message()),
+        }
+    })?;
+    let mut tables = HashMap::new();
+    flatten_table("", table, source, &mut tables)?;
+    Ok(tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml_tables() {
+        let tables = parse_toml_tables(
+            "top = 1\n[a]\nb = \"x # y\" # note\nc = 2.5\n\"d = e\" = 1\nf = 'C:\\pfad'\n[a.g]\nh = 2025-10-19\n",
+            "test file",
+        )
+        .unwrap();
+        assert_eq!(tables[""]["top"], "1");
+        assert_eq!(tables["a"]["b"], "x # y");
+        assert_eq!(tables["a"]["c"], "2.5");
+        assert_eq!(tables["a"]["d = e"], "1");
+        assert_eq!(tables["a"]["f"], "C:\\pfad");
+        assert_eq!(tables["a.g"]["h"], "2025-10-19");
+        assert!(!tables["a"].contains_key("g"));
+    }
+
+    #[test]
+    fn test_parse_toml_tables_errors() {
+        assert!(
+            parse_toml_tables("[a]\nb = \"open\n", "test file")
+                .unwrap_err()
+                .starts_with("Invalid line 2 in test file: ")
+        );
+        assert!(
+            parse_toml_tables("[a]\nc = 2,5\n", "test file")
+                .unwrap_err()
+                .starts_with("Invalid line 2 in test file: ")
+        );
+        assert!(parse_toml_tables("[a]\nb\n", "test file").is_err());
+        assert_eq!(
+            parse_toml_tables("[a]\nb = [1, 2]\n", "test file").unwrap_err(),
+            "Unsupported array for b in test file"
+        );
+    }
+}
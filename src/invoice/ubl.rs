@@ -0,0 +1,267 @@
+use roxmltree::Node;
+
+use super::{EInvoice, EInvoiceLine, TaxSubtotal, xml_amount, xml_child, xml_text};
+
+// Peppol BIS Billing 3.0 / XRechnung in OASIS UBL 2.1 syntax, for invoices and
+// credit notes alike
+pub fn parse_ubl(root: Node) -> Result<EInvoice, String> {
+    let credit_note = root.has_tag_name("CreditNote");
+    let (line_tag, quantity_tag) = if credit_note {
+        ("CreditNoteLine", "CreditedQuantity")
+    } else {
+        ("InvoiceLine", "InvoicedQuantity")
+    };
+    let currency = xml_text(root, &["DocumentCurrencyCode"])
+        .unwrap_or("EUR")
+        .to_string();
+
+    let mut lines = root
+        .children()
+        .filter(|node| node.has_tag_name(line_tag))
+        .enumerate()
+        .map(|(index, line)| parse_line(line, quantity_tag, index + 1))
+        .collect::<Result<Vec<_>, String>>()?;
+    for charge in root
+        .children()
+        .filter(|node| node.has_tag_name("AllowanceCharge"))
+    {
+        lines.push(parse_allowance_charge(charge)?);
+    }
+
+    // A second TaxTotal without subtotals states the VAT in the accounting
+    // currency when that differs
+    let tax_total = root
+        .children()
+        .filter(|node| node.has_tag_name("TaxTotal"))
+        .find(|node| {
+            xml_child(*node, &["TaxAmount"])
+                .and_then(|amount| amount.attribute("currencyID"))
+                .is_none_or(|id| id == currency)
+        })
+        .ok_or("UBL invoice has no TaxTotal")?;
+    let subtotals = tax_total
+        .children()
+        .filter(|node| node.has_tag_name("TaxSubtotal"))
+        .map(parse_subtotal)
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let monetary_total =
+        xml_child(root, &["LegalMonetaryTotal"]).ok_or("UBL invoice has no LegalMonetaryTotal")?;
+    let total = |name: &str| {
+        xml_amount(monetary_total, &[name])
+            .ok_or_else(|| format!("UBL invoice has no valid {name}"))
+    };
+
+    Ok(EInvoice {
+        number: xml_text(root, &["ID"]).unwrap_or_default().to_string(),
+        lines,
+        subtotals,
+        net_total: total("TaxExclusiveAmount")?,
+        vat_total: xml_amount(tax_total, &["TaxAmount"])
+            .ok_or("UBL invoice has no valid TaxAmount")?,
+        gross_total: total("TaxInclusiveAmount")?,
+        currency,
+    })
+}
+
+fn parse_category(category: Node, context: &str) -> Result<(String, f64), String> {
+    let code =
+        xml_text(category, &["ID"]).ok_or_else(|| format!("{context} has no tax category"))?;
+    // Exempt and reverse-charge categories may omit the rate
+    let rate = match xml_text(category, &["Percent"]) {
+        Some(rate) => rate
+            .parse()
+            .map_err(|_| format!("{context} has an invalid tax rate: {rate}"))?,
+        None => 0.0,
+    };
+    Ok((code.to_string(), rate))
+}
+
+fn parse_line(line: Node, quantity_tag: &str, number: usize) -> Result<EInvoiceLine, String> {
+    let context = format!("UBL invoice line {number}");
+    let net = xml_amount(line, &["LineExtensionAmount"])
+        .ok_or_else(|| format!("{context} has no valid LineExtensionAmount"))?;
+    let category = xml_child(line, &["Item", "ClassifiedTaxCategory"])
+        .ok_or_else(|| format!("{context} has no ClassifiedTaxCategory"))?;
+    let (category, rate) = parse_category(category, &context)?;
+
+    Ok(EInvoiceLine {
+        label: xml_text(line, &["Item", "Name"]).map(str::to_string),
+        quantity: xml_amount(line, &[quantity_tag]).unwrap_or(1.0),
+        net,
+        category,
+        rate,
+    })
+}
+
+// Document-level allowances and charges, kept as lines like in CII
+fn parse_allowance_charge(node: Node) -> Result<EInvoiceLine, String> {
+    let is_charge = xml_text(node, &["ChargeIndicator"]) == Some("true");
+    let kind = if is_charge { "Charge" } else { "Allowance" };
+    let context = format!("UBL invoice {}", kind.to_lowercase());
+    let amount =
+        xml_amount(node, &["Amount"]).ok_or_else(|| format!("{context} has no valid Amount"))?;
+    let category = xml_child(node, &["TaxCategory"])
+        .ok_or_else(|| format!("{context} has no tax category"))?;
+    let (category, rate) = parse_category(category, &context)?;
+
+    Ok(EInvoiceLine {
+        label: Some(match xml_text(node, &["AllowanceChargeReason"]) {
+            Some(reason) => format!("{kind}: {reason}"),
+            None => kind.to_string(),
+        }),
+        quantity: 1.0,
+        net: if is_charge { amount } else { -amount },
+        category,
+        rate,
+    })
+}
+
+fn parse_subtotal(subtotal: Node) -> Result<TaxSubtotal, String> {
+    let context = "UBL invoice VAT breakdown";
+    let category = xml_child(subtotal, &["TaxCategory"])
+        .ok_or_else(|| format!("{context} has no tax category"))?;
+    let (category, rate) = parse_category(category, context)?;
+    Ok(TaxSubtotal {
+        category,
+        rate,
+        basis: xml_amount(subtotal, &["TaxableAmount"])
+            .ok_or_else(|| format!("{context} has no valid TaxableAmount"))?,
+        vat: xml_amount(subtotal, &["TaxAmount"])
+            .ok_or_else(|| format!("{context} has no valid TaxAmount"))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::verify_einvoice;
+    use crate::invoice::parse_einvoice;
+    use crate::rounding::RoundingMode;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:CustomizationID>urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:billing:3.0</cbc:CustomizationID>
+  <cbc:ID>INV-2025-17</cbc:ID>
+  <cbc:IssueDate>2025-10-01</cbc:IssueDate>
+  <cbc:InvoiceTypeCode>380</cbc:InvoiceTypeCode>
+  <cbc:DocumentCurrencyCode>EUR</cbc:DocumentCurrencyCode>
+  <cac:AllowanceCharge>
+    <cbc:ChargeIndicator>true</cbc:ChargeIndicator>
+    <cbc:AllowanceChargeReason>Versand</cbc:AllowanceChargeReason>
+    <cbc:Amount currencyID="EUR">5.00</cbc:Amount>
+    <cac:TaxCategory>
+      <cbc:ID>S</cbc:ID>
+      <cbc:Percent>19</cbc:Percent>
+      <cac:TaxScheme><cbc:ID>VAT</cbc:ID></cac:TaxScheme>
+    </cac:TaxCategory>
+  </cac:AllowanceCharge>
+  <cac:TaxTotal>
+    <cbc:TaxAmount currencyID="EUR">20.95</cbc:TaxAmount>
+    <cac:TaxSubtotal>
+      <cbc:TaxableAmount currencyID="EUR">105.00</cbc:TaxableAmount>
+      <cbc:TaxAmount currencyID="EUR">19.95</cbc:TaxAmount>
+      <cac:TaxCategory>
+        <cbc:ID>S</cbc:ID>
+        <cbc:Percent>19</cbc:Percent>
+        <cac:TaxScheme><cbc:ID>VAT</cbc:ID></cac:TaxScheme>
+      </cac:TaxCategory>
+    </cac:TaxSubtotal>
+    <cac:TaxSubtotal>
+      <cbc:TaxableAmount currencyID="EUR">14.29</cbc:TaxableAmount>
+      <cbc:TaxAmount currencyID="EUR">1.00</cbc:TaxAmount>
+      <cac:TaxCategory>
+        <cbc:ID>S</cbc:ID>
+        <cbc:Percent>7</cbc:Percent>
+        <cac:TaxScheme><cbc:ID>VAT</cbc:ID></cac:TaxScheme>
+      </cac:TaxCategory>
+    </cac:TaxSubtotal>
+  </cac:TaxTotal>
+  <cac:LegalMonetaryTotal>
+    <cbc:LineExtensionAmount currencyID="EUR">114.29</cbc:LineExtensionAmount>
+    <cbc:TaxExclusiveAmount currencyID="EUR">119.29</cbc:TaxExclusiveAmount>
+    <cbc:TaxInclusiveAmount currencyID="EUR">140.24</cbc:TaxInclusiveAmount>
+    <cbc:ChargeTotalAmount currencyID="EUR">5.00</cbc:ChargeTotalAmount>
+    <cbc:PayableAmount currencyID="EUR">140.24</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+  <cac:InvoiceLine>
+    <cbc:ID>1</cbc:ID>
+    <cbc:InvoicedQuantity unitCode="H87">4</cbc:InvoicedQuantity>
+    <cbc:LineExtensionAmount currencyID="EUR">100.00</cbc:LineExtensionAmount>
+    <cac:Item>
+      <cbc:Name>Toner</cbc:Name>
+      <cac:ClassifiedTaxCategory>
+        <cbc:ID>S</cbc:ID>
+        <cbc:Percent>19</cbc:Percent>
+        <cac:TaxScheme><cbc:ID>VAT</cbc:ID></cac:TaxScheme>
+      </cac:ClassifiedTaxCategory>
+    </cac:Item>
+    <cac:Price><cbc:PriceAmount currencyID="EUR">25.00</cbc:PriceAmount></cac:Price>
+  </cac:InvoiceLine>
+  <cac:InvoiceLine>
+    <cbc:ID>2</cbc:ID>
+    <cbc:InvoicedQuantity unitCode="H87">1</cbc:InvoicedQuantity>
+    <cbc:LineExtensionAmount currencyID="EUR">14.29</cbc:LineExtensionAmount>
+    <cac:Item>
+      <cbc:Name>Zeitschrift</cbc:Name>
+      <cac:ClassifiedTaxCategory>
+        <cbc:ID>S</cbc:ID>
+        <cbc:Percent>7</cbc:Percent>
+        <cac:TaxScheme><cbc:ID>VAT</cbc:ID></cac:TaxScheme>
+      </cac:ClassifiedTaxCategory>
+    </cac:Item>
+    <cac:Price><cbc:PriceAmount currencyID="EUR">14.29</cbc:PriceAmount></cac:Price>
+  </cac:InvoiceLine>
+</Invoice>
+"#;
+
+    #[test]
+    fn test_parse_ubl() {
+        let invoice = parse_einvoice(SAMPLE).unwrap();
+        assert_eq!(invoice.number, "INV-2025-17");
+        assert_eq!(invoice.currency, "EUR");
+        assert_eq!(invoice.lines.len(), 3);
+        assert_eq!(invoice.lines[0].label.as_deref(), Some("Toner"));
+        assert_eq!(invoice.lines[0].quantity, 4.0);
+        assert_eq!(invoice.lines[1].rate, 7.0);
+        assert_eq!(invoice.lines[2].label.as_deref(), Some("Charge: Versand"));
+        assert_eq!(invoice.lines[2].net, 5.0);
+        assert_eq!(invoice.subtotals.len(), 2);
+        assert_eq!(invoice.subtotals[0].basis, 105.0);
+        assert_eq!(invoice.net_total, 119.29);
+        assert_eq!(invoice.vat_total, 20.95);
+        assert_eq!(invoice.gross_total, 140.24);
+        assert!(verify_einvoice(&invoice, 0.01, 2, RoundingMode::HalfUp).passed);
+    }
+
+    #[test]
+    fn test_parse_ubl_credit_note() {
+        let credit_note = SAMPLE
+            .replace(
+                "<Invoice xmlns=\"urn:oasis:names:specification:ubl:schema:xsd:Invoice-2\"",
+                "<CreditNote xmlns=\"urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2\"",
+            )
+            .replace("</Invoice>", "</CreditNote>")
+            .replace("InvoiceLine>", "CreditNoteLine>")
+            .replace("InvoicedQuantity", "CreditedQuantity");
+        let invoice = parse_einvoice(&credit_note).unwrap();
+        assert_eq!(invoice.lines.len(), 3);
+        assert_eq!(invoice.lines[0].quantity, 4.0);
+    }
+
+    #[test]
+    fn test_parse_ubl_errors() {
+        let without_amount = SAMPLE.replace(
+            "<cbc:LineExtensionAmount currencyID=\"EUR\">14.29</cbc:LineExtensionAmount>",
+            "",
+        );
+        assert_eq!(
+            parse_einvoice(&without_amount).unwrap_err(),
+            "UBL invoice line 2 has no valid LineExtensionAmount"
+        );
+        let without_total = SAMPLE.replace("LegalMonetaryTotal", "MonetaryTotal");
+        assert_eq!(
+            parse_einvoice(&without_total).unwrap_err(),
+            "UBL invoice has no LegalMonetaryTotal"
+        );
+    }
+}
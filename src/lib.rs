@@ -1,10 +1,19 @@
 pub mod calculator;
 pub mod cli;
 pub mod display;
+pub mod exchange;
+pub mod invoice;
+pub mod rounding;
 pub mod utils;
 
 // Re-export main functionality for testing
-pub use calculator::process_numbers;
+pub use calculator::{
+    apply_line_adjustments, calculate_total_adjustment, process_numbers,
+    process_numbers_with_components, round_adjusted_amounts, round_results,
+};
 pub use cli::{parse_arguments, print_usage};
-pub use display::{create_clipboard_content, display_results};
+pub use display::{
+    create_clipboard_content, create_clipboard_html, create_component_clipboard_html,
+    display_component_results, display_results, print_rounding_rule, print_total_adjustment,
+};
 pub use utils::copy_to_clipboard;
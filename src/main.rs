@@ -3,12 +3,50 @@ use std::env;
 mod calculator;
 mod cli;
 mod display;
+mod exchange;
+mod invoice;
+mod rounding;
 mod utils;
 
-use calculator::process_numbers;
-use cli::{parse_arguments, print_usage};
-use display::{create_clipboard_content, display_results};
-use utils::copy_to_clipboard;
+use calculator::{
+    CalculationResult, PricingTarget, allocate_shares, apply_line_adjustments, booked_results,
+    build_einvoice, calculate_total_adjustment, convert_results, process_einvoice_lines,
+    process_lines, process_margin_scheme, process_numbers, process_numbers_with_components,
+    process_pricing, round_adjusted_amounts, round_results, solve_rates, split_installments,
+    total_margin_scheme, total_split_parts, verify_einvoice, verify_invoices,
+};
+use cli::{Mode, ParsedArgs, parse_arguments, print_usage};
+use display::datev::create_datev_output;
+use display::json::{
+    create_allocation_json_output, create_einvoice_json_output, create_installment_json_output,
+    create_json_output, create_margin_scheme_json_output, create_pricing_json_output,
+    create_rate_solution_json_output, create_statement_json_output,
+    create_verification_json_output,
+};
+use display::ledger::create_ledger_output;
+use display::xrechnung::{check_xrechnung, create_xrechnung_output};
+use display::{
+    OutputFormat, create_allocation_clipboard_content, create_allocation_clipboard_html,
+    create_clipboard_content, create_clipboard_html, create_component_clipboard_html,
+    create_installment_clipboard_content, create_installment_clipboard_html,
+    create_line_import_output, create_margin_scheme_clipboard_content,
+    create_margin_scheme_clipboard_html, create_pricing_clipboard_content,
+    create_pricing_clipboard_html, create_rate_solution_clipboard_content,
+    create_rate_solution_clipboard_html, display_allocation, display_component_results,
+    display_einvoice, display_installments, display_margin_scheme_results, display_pricing_results,
+    display_rate_solutions, display_results, display_statement, display_verification_results,
+    print_conversion, print_rounding_level, print_rounding_rule, print_total_adjustment,
+    print_unmatched_lines,
+};
+use exchange::RateTable;
+use invoice::accounts::{LedgerAccounts, load_accounts, load_ledger_accounts};
+use invoice::lines::{ImportedLine, load_lines};
+use invoice::parties::load_parties;
+use invoice::rules::{apply_rules, load_rules};
+use invoice::statement::{load_statement, statement_lines};
+use invoice::{load_einvoice, load_invoices};
+use rounding::minor_units;
+use utils::{copy_to_clipboard, current_date, current_timestamp};
 
 fn run(args: Vec<String>) -> Result<(), String> {
     let program_name = args[0].clone();
@@ -22,10 +60,474 @@ fn run(args: Vec<String>) -> Result<(), String> {
         }
     };
 
-    let results = process_numbers(&parsed_args.numbers, parsed_args.vat_rate);
-    display_results(&results, parsed_args.vat_rate);
-    let clipboard_content = create_clipboard_content(&results);
-    copy_to_clipboard(&clipboard_content);
+    match parsed_args.mode {
+        Mode::Vat => run_vat(&parsed_args),
+        Mode::Pricing(target) => {
+            run_pricing(&parsed_args, target);
+            Ok(())
+        }
+        Mode::MarginScheme => {
+            run_margin_scheme(&parsed_args);
+            Ok(())
+        }
+        Mode::SolveRate => {
+            run_solve_rate(&parsed_args);
+            Ok(())
+        }
+        Mode::Verify => run_verify(&parsed_args),
+        Mode::Installments(count) => {
+            run_installments(&parsed_args, count);
+            Ok(())
+        }
+        Mode::Allocate => {
+            run_allocate(&parsed_args);
+            Ok(())
+        }
+        Mode::LineImport => run_line_import(&parsed_args),
+        Mode::EInvoice => run_einvoice(&parsed_args),
+        Mode::XRechnung => run_xrechnung(&parsed_args),
+        Mode::Statement => run_statement(&parsed_args),
+    }
+}
+
+fn run_pricing(parsed_args: &ParsedArgs, target: PricingTarget) {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let vat_rate = parsed_args.vat_rate;
+
+    let results = process_pricing(&parsed_args.numbers, target, vat_rate, precision, mode);
+    if parsed_args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            create_pricing_json_output(&results, target, vat_rate, precision, mode)
+        );
+        return;
+    }
+
+    display_pricing_results(&results, target, vat_rate, precision, mode);
+    let clipboard_content = create_pricing_clipboard_content(&results, precision, mode);
+    let clipboard_html = create_pricing_clipboard_html(&results, target, vat_rate, precision, mode);
+    copy_to_clipboard(&clipboard_content, Some(&clipboard_html));
+}
+
+fn run_margin_scheme(parsed_args: &ParsedArgs) {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let vat_rate = parsed_args.vat_rate;
+
+    let results = process_margin_scheme(&parsed_args.pairs, vat_rate, precision, mode);
+    let total = total_margin_scheme(&results);
+    if parsed_args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            create_margin_scheme_json_output(&results, &total, vat_rate, precision, mode)
+        );
+        return;
+    }
+
+    display_margin_scheme_results(&results, &total, vat_rate, precision, mode);
+    let clipboard_content = create_margin_scheme_clipboard_content(&results, precision, mode);
+    let clipboard_html =
+        create_margin_scheme_clipboard_html(&results, &total, vat_rate, precision, mode);
+    copy_to_clipboard(&clipboard_content, Some(&clipboard_html));
+}
+
+fn run_solve_rate(parsed_args: &ParsedArgs) {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let known_rates = &parsed_args.known_rates;
+    let tolerance = parsed_args.rate_tolerance;
+
+    let results = solve_rates(&parsed_args.pairs, known_rates, tolerance, precision, mode);
+    if parsed_args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            create_rate_solution_json_output(&results, known_rates, tolerance, precision, mode)
+        );
+        return;
+    }
+
+    display_rate_solutions(&results, known_rates, tolerance, precision, mode);
+    let clipboard_content = create_rate_solution_clipboard_content(&results, mode);
+    let clipboard_html =
+        create_rate_solution_clipboard_html(&results, known_rates, precision, mode);
+    copy_to_clipboard(&clipboard_content, Some(&clipboard_html));
+}
+
+fn run_installments(parsed_args: &ParsedArgs, count: usize) {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let vat_rate = parsed_args.vat_rate;
+
+    let installments =
+        split_installments(&parsed_args.numbers[0], count, vat_rate, precision, mode);
+    let total = total_split_parts(&installments);
+    if parsed_args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            create_installment_json_output(&installments, &total, vat_rate, precision, mode)
+        );
+        return;
+    }
+
+    display_installments(&installments, &total, vat_rate, precision, mode);
+    let clipboard_content = create_installment_clipboard_content(&installments, precision, mode);
+    let clipboard_html =
+        create_installment_clipboard_html(&installments, &total, vat_rate, precision, mode);
+    copy_to_clipboard(&clipboard_content, Some(&clipboard_html));
+}
+
+fn run_allocate(parsed_args: &ParsedArgs) {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let vat_rate = parsed_args.vat_rate;
+    let shares = &parsed_args.shares;
+
+    let parts = allocate_shares(&parsed_args.numbers[0], shares, vat_rate, precision, mode);
+    let total = total_split_parts(&parts);
+    if parsed_args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            create_allocation_json_output(shares, &parts, &total, vat_rate, precision, mode)
+        );
+        return;
+    }
+
+    display_allocation(shares, &parts, &total, vat_rate, precision, mode);
+    let clipboard_content = create_allocation_clipboard_content(shares, &parts, precision, mode);
+    let clipboard_html =
+        create_allocation_clipboard_html(shares, &parts, &total, vat_rate, precision, mode);
+    copy_to_clipboard(&clipboard_content, Some(&clipboard_html));
+}
+
+// Rules from --payee come before those of the --rules file. The report of
+// unmatched lines goes to stderr, so CSV and XML output stay redirectable.
+fn apply_import_rules(lines: &mut [ImportedLine], parsed_args: &ParsedArgs) -> Result<(), String> {
+    let mut rules = parsed_args.payee_rules.clone();
+    if let Some(path) = &parsed_args.rules_file {
+        rules.extend(load_rules(path).inspect_err(|error| eprintln!("Error: {error}"))?);
+    }
+    if !rules.is_empty() {
+        let unmatched = apply_rules(lines, &rules);
+        print_unmatched_lines(lines, &unmatched, parsed_args.vat_rate);
+    }
+    Ok(())
+}
+
+// Prints the imported rows as CSV, so the output can be redirected to a file
+fn run_line_import(parsed_args: &ParsedArgs) -> Result<(), String> {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let path = parsed_args.lines_file.as_deref().unwrap_or_default();
+
+    let mut import = load_lines(path, &parsed_args.column_map)
+        .inspect_err(|error| eprintln!("Error: {error}"))?;
+    apply_import_rules(&mut import.lines, parsed_args)?;
+    let results = process_lines(
+        &import.lines,
+        parsed_args.vat_rate,
+        precision,
+        mode,
+        parsed_args.rounding_level,
+    );
+    if parsed_args.format == OutputFormat::Datev {
+        let currency = parsed_args.currency.as_deref().unwrap_or("EUR");
+        return print_datev(&import.lines, &results, currency, parsed_args);
+    }
+    if parsed_args.format.is_journal() {
+        let currency = parsed_args.currency.as_deref().unwrap_or("EUR");
+        return print_journal(Some(&import.lines), &results, currency, parsed_args);
+    }
+    println!(
+        "{}",
+        create_line_import_output(&import, &results, precision, mode)
+    );
+    Ok(())
+}
+
+// Prints the DATEV bookings, so the output can be redirected to a file
+fn print_datev(
+    lines: &[ImportedLine],
+    results: &[CalculationResult],
+    currency: &str,
+    parsed_args: &ParsedArgs,
+) -> Result<(), String> {
+    let path = parsed_args.accounts_file.as_deref().unwrap_or_default();
+    let output = load_accounts(path)
+        .and_then(|accounts| {
+            create_datev_output(
+                lines,
+                results,
+                &accounts,
+                parsed_args.vat_rate,
+                currency,
+                &current_timestamp(),
+                parsed_args.precision,
+                parsed_args.rounding_mode,
+            )
+        })
+        .inspect_err(|error| eprintln!("Error: {error}"))?;
+    println!("{output}");
+    Ok(())
+}
+
+// Prints hledger, ledger or beancount transactions; without an accounts file
+// the default account names are used
+fn print_journal(
+    lines: Option<&[ImportedLine]>,
+    results: &[CalculationResult],
+    currency: &str,
+    parsed_args: &ParsedArgs,
+) -> Result<(), String> {
+    let accounts = match &parsed_args.accounts_file {
+        Some(path) => load_ledger_accounts(path),
+        None => Ok(LedgerAccounts::default()),
+    };
+    let output = accounts
+        .and_then(|accounts| {
+            create_ledger_output(
+                parsed_args.format,
+                lines,
+                results,
+                &accounts,
+                currency,
+                &current_date(),
+                parsed_args.precision,
+                parsed_args.rounding_mode,
+            )
+        })
+        .inspect_err(|error| eprintln!("Error: {error}"))?;
+    println!("{output}");
+    Ok(())
+}
+
+fn run_einvoice(parsed_args: &ParsedArgs) -> Result<(), String> {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let tolerance = parsed_args.tolerance;
+    let path = parsed_args.einvoice_file.as_deref().unwrap_or_default();
+
+    let invoice = load_einvoice(path).inspect_err(|error| eprintln!("Error: {error}"))?;
+    let results = process_einvoice_lines(&invoice, parsed_args.vat_rate, precision, mode);
+    let check = verify_einvoice(&invoice, tolerance, precision, mode);
+    if parsed_args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            create_einvoice_json_output(&invoice, &results, &check, tolerance, precision, mode)
+        );
+    } else {
+        display_einvoice(&invoice, &results, &check, tolerance, precision, mode);
+    }
+
+    if !check.passed {
+        return Err(format!("E-invoice {} failed verification", invoice.number));
+    }
+    Ok(())
+}
+
+// Prints the invoice XML, so the output can be redirected to a file
+fn run_xrechnung(parsed_args: &ParsedArgs) -> Result<(), String> {
+    let mode = parsed_args.rounding_mode;
+    // XRechnung amounts have two decimals whatever --precision says
+    let precision = 2;
+    let vat_rate = parsed_args.vat_rate;
+    let path = parsed_args.parties_file.as_deref().unwrap_or_default();
+
+    let parties = load_parties(path).inspect_err(|error| eprintln!("Error: {error}"))?;
+    let (results, rates) = match &parsed_args.lines_file {
+        Some(lines_file) => {
+            let mut import = load_lines(lines_file, &parsed_args.column_map)
+                .inspect_err(|error| eprintln!("Error: {error}"))?;
+            apply_import_rules(&mut import.lines, parsed_args)?;
+            let results = process_lines(
+                &import.lines,
+                vat_rate,
+                precision,
+                mode,
+                parsed_args.rounding_level,
+            );
+            let rates = import
+                .lines
+                .iter()
+                .map(|line| line.rate.unwrap_or(vat_rate))
+                .collect();
+            (results, rates)
+        }
+        None => {
+            let numbers = &parsed_args.numbers;
+            let results = process_numbers(
+                numbers,
+                vat_rate,
+                precision,
+                mode,
+                parsed_args.rounding_level,
+            );
+            (results, vec![vat_rate; numbers.len()])
+        }
+    };
+
+    let currency = parsed_args
+        .currency
+        .as_deref()
+        .or(parties.currency.as_deref())
+        .unwrap_or("EUR");
+    let invoice = build_einvoice(&results, &rates, &parties.number, currency, precision, mode);
+    check_xrechnung(&invoice, &parties).inspect_err(|error| eprintln!("Error: {error}"))?;
+
+    println!("{}", create_xrechnung_output(&invoice, &parties, mode));
+    Ok(())
+}
+
+fn run_statement(parsed_args: &ParsedArgs) -> Result<(), String> {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let vat_rate = parsed_args.vat_rate;
+    let path = parsed_args.statement_file.as_deref().unwrap_or_default();
+
+    let statement = load_statement(path).inspect_err(|error| eprintln!("Error: {error}"))?;
+    let mut lines = statement_lines(&statement);
+    apply_import_rules(&mut lines, parsed_args)?;
+    let results = process_lines(
+        &lines,
+        vat_rate,
+        precision,
+        mode,
+        parsed_args.rounding_level,
+    );
+    if parsed_args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            create_statement_json_output(&statement, &lines, &results, vat_rate, precision, mode)
+        );
+        return Ok(());
+    }
+    if parsed_args.format == OutputFormat::Datev {
+        return print_datev(&lines, &results, &statement.currency, parsed_args);
+    }
+    if parsed_args.format.is_journal() {
+        return print_journal(Some(&lines), &results, &statement.currency, parsed_args);
+    }
+
+    display_statement(&statement, &lines, &results, vat_rate, precision, mode);
+    let clipboard_content = create_clipboard_content(&results, precision, mode);
+    let clipboard_html = create_clipboard_html(&results, vat_rate, precision, mode);
+    copy_to_clipboard(&clipboard_content, Some(&clipboard_html));
+    Ok(())
+}
+
+fn run_verify(parsed_args: &ParsedArgs) -> Result<(), String> {
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let tolerance = parsed_args.tolerance;
+
+    let mut invoices = parsed_args.invoices.clone();
+    if let Some(path) = &parsed_args.invoice_file {
+        invoices.extend(load_invoices(path).inspect_err(|error| eprintln!("Error: {error}"))?);
+    }
+
+    let results = verify_invoices(&invoices, parsed_args.vat_rate, tolerance, precision, mode);
+    if parsed_args.format == OutputFormat::Json {
+        println!(
+            "{}",
+            create_verification_json_output(&results, tolerance, precision, mode)
+        );
+    } else {
+        display_verification_results(&results, tolerance, precision, mode);
+    }
+
+    let failed = results.iter().filter(|result| !result.passed).count();
+    if failed > 0 {
+        return Err(format!("{failed} invoice(s) failed verification"));
+    }
+    Ok(())
+}
+
+fn run_vat(parsed_args: &ParsedArgs) -> Result<(), String> {
+    let conversion = match (
+        &parsed_args.currency,
+        &parsed_args.convert_to,
+        &parsed_args.rates_file,
+    ) {
+        (Some(from), Some(to), Some(path)) => Some(
+            RateTable::load(path, parsed_args.rate_date.as_deref())
+                .and_then(|table| table.conversion(from, to))
+                .inspect_err(|error| eprintln!("Error: {error}"))?,
+        ),
+        _ => None,
+    };
+
+    let mode = parsed_args.rounding_mode;
+    let precision = parsed_args.precision;
+    let components = &parsed_args.tax_components;
+    let adjustments = &parsed_args.adjustments;
+    let level = parsed_args.rounding_level;
+    let numbers = &parsed_args.numbers;
+    let mut results = if components.is_empty() {
+        process_numbers(numbers, parsed_args.vat_rate, precision, mode, level)
+    } else {
+        process_numbers_with_components(numbers, components, precision, mode, level)
+    };
+    apply_line_adjustments(&mut results, adjustments);
+    let mut total = calculate_total_adjustment(&results, adjustments);
+    if let Some(rule) = &parsed_args.rounding {
+        round_results(
+            &mut results,
+            rule,
+            parsed_args.vat_rate,
+            components,
+            precision,
+        );
+        total = total.map(|total| round_adjusted_amounts(total, rule, precision));
+    }
+    if let Some(conversion) = &conversion {
+        convert_results(&mut results, conversion, minor_units(&conversion.to), mode);
+    }
+
+    if parsed_args.format.is_journal() {
+        // Journals book the adjusted amounts in the target currency
+        let booked_precision = conversion
+            .as_ref()
+            .map_or(precision, |conversion| minor_units(&conversion.to));
+        let booked = booked_results(&results, conversion.as_ref(), booked_precision, mode);
+        let currency = match &conversion {
+            Some(conversion) => conversion.to.as_str(),
+            None => parsed_args.currency.as_deref().unwrap_or("EUR"),
+        };
+        return print_journal(None, &booked, currency, parsed_args);
+    }
+    if parsed_args.format == OutputFormat::Json {
+        let json = create_json_output(
+            &results,
+            parsed_args.vat_rate,
+            components,
+            total.as_ref(),
+            conversion.as_ref(),
+            precision,
+            mode,
+        );
+        println!("{json}");
+        return Ok(());
+    }
+
+    let clipboard_html = if components.is_empty() {
+        display_results(&results, parsed_args.vat_rate, precision, mode);
+        create_clipboard_html(&results, parsed_args.vat_rate, precision, mode)
+    } else {
+        display_component_results(&results, components, precision, mode);
+        create_component_clipboard_html(&results, components, precision, mode)
+    };
+    if let Some(total) = &total {
+        print_total_adjustment(&results, total, precision, mode);
+    }
+    if let Some(rule) = &parsed_args.rounding {
+        print_rounding_rule(rule);
+    }
+    print_rounding_level(&results, level);
+    if let Some(conversion) = &conversion {
+        print_conversion(conversion);
+    }
+    let clipboard_content = create_clipboard_content(&results, precision, mode);
+    copy_to_clipboard(&clipboard_content, Some(&clipboard_html));
 
     Ok(())
 }
@@ -65,6 +567,26 @@ mod tests {
         assert_eq!(result.unwrap_err(), "No valid numbers provided");
     }
 
+    #[test]
+    fn test_run_with_missing_rates_file() {
+        let args = vec![
+            "program".to_string(),
+            "119".to_string(),
+            "--currency".to_string(),
+            "USD".to_string(),
+            "--convert-to".to_string(),
+            "EUR".to_string(),
+            "--rates".to_string(),
+            "/nonexistent/eurofxref.csv".to_string(),
+        ];
+        let result = run(args);
+        assert!(
+            result
+                .unwrap_err()
+                .starts_with("Could not read exchange rate file")
+        );
+    }
+
     #[test]
     fn test_run_with_rate_missing_value() {
         let args = vec![
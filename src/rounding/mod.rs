@@ -0,0 +1,276 @@
+// Binary floats cannot represent most decimal ties exactly (123.455 is stored
+// as 123.4549999...), so anything within a few units in the last place of a tie
+// or of a whole number of increments is treated as lying exactly on it. The
+// tolerance grows with the value, as the spacing of floats does.
+fn tie_epsilon(scaled: f64) -> f64 {
+    scaled.abs().max(1.0) * f64::EPSILON * 4.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    #[default]
+    HalfUp,
+    HalfEven,
+    Floor,
+    Ceil,
+}
+
+impl RoundingMode {
+    pub fn parse(input: &str) -> Option<RoundingMode> {
+        match input.to_lowercase().as_str() {
+            "half-up" | "commercial" => Some(RoundingMode::HalfUp),
+            "half-even" | "bankers" => Some(RoundingMode::HalfEven),
+            "floor" | "down" => Some(RoundingMode::Floor),
+            "ceil" | "up" => Some(RoundingMode::Ceil),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RoundingMode::HalfUp => "half-up",
+            RoundingMode::HalfEven => "half-even",
+            RoundingMode::Floor => "floor",
+            RoundingMode::Ceil => "ceil",
+        }
+    }
+}
+
+// Whether quantity lines are rounded per unit before multiplying or only once
+// on the line total. Line level is what most invoicing software does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingLevel {
+    #[default]
+    Line,
+    Unit,
+}
+
+impl RoundingLevel {
+    pub fn parse(input: &str) -> Option<RoundingLevel> {
+        match input.to_lowercase().as_str() {
+            "line" => Some(RoundingLevel::Line),
+            "unit" => Some(RoundingLevel::Unit),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RoundingLevel::Line => "line",
+            RoundingLevel::Unit => "unit",
+        }
+    }
+}
+
+// ISO 4217 minor units of the currencies that do not use two decimals.
+pub fn minor_units(code: &str) -> usize {
+    match code.to_uppercase().as_str() {
+        "JPY" | "KRW" | "ISK" | "CLP" | "VND" | "PYG" | "UGX" | "XAF" | "XOF" => 0,
+        "BHD" | "KWD" | "OMR" | "JOD" | "TND" | "LYD" | "IQD" => 3,
+        _ => 2,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundingRule {
+    pub increment: f64,
+    pub mode: RoundingMode,
+}
+
+impl Default for RoundingRule {
+    fn default() -> Self {
+        RoundingRule {
+            increment: 0.01,
+            mode: RoundingMode::HalfUp,
+        }
+    }
+}
+
+impl RoundingRule {
+    // Cash rounding of the currencies that do not settle to their minor unit.
+    pub fn for_currency(code: &str) -> Option<RoundingRule> {
+        let increment = match code.to_uppercase().as_str() {
+            "CHF" => 0.05,
+            "DKK" => 0.5,
+            "NOK" | "SEK" | "CZK" => 1.0,
+            "HUF" => 5.0,
+            _ => return None,
+        };
+        Some(RoundingRule {
+            increment,
+            mode: RoundingMode::HalfUp,
+        })
+    }
+
+    pub fn round(&self, value: f64) -> f64 {
+        round_to_increment(value, self.increment, self.mode)
+    }
+}
+
+pub fn round_to_increment(value: f64, increment: f64, mode: RoundingMode) -> f64 {
+    let scaled = value / increment;
+    let nearest = scaled.round();
+    let epsilon = tie_epsilon(scaled);
+    if (scaled - nearest).abs() < epsilon {
+        return nearest * increment;
+    }
+
+    let floor = scaled.floor();
+    let is_tie = (scaled - floor - 0.5).abs() < epsilon;
+    let rounded = match mode {
+        RoundingMode::Floor => floor,
+        RoundingMode::Ceil => scaled.ceil(),
+        RoundingMode::HalfUp if is_tie => {
+            if scaled < 0.0 {
+                floor
+            } else {
+                floor + 1.0
+            }
+        }
+        RoundingMode::HalfEven if is_tie => {
+            if floor % 2.0 == 0.0 {
+                floor
+            } else {
+                floor + 1.0
+            }
+        }
+        RoundingMode::HalfUp | RoundingMode::HalfEven => nearest,
+    };
+
+    rounded * increment
+}
+
+pub fn round_to_precision(value: f64, precision: usize, mode: RoundingMode) -> f64 {
+    round_to_increment(value, 10f64.powi(-(precision as i32)), mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_rounding_mode_parse() {
+        assert_eq!(RoundingMode::parse("half-up"), Some(RoundingMode::HalfUp));
+        assert_eq!(RoundingMode::parse("Bankers"), Some(RoundingMode::HalfEven));
+        assert_eq!(RoundingMode::parse("floor"), Some(RoundingMode::Floor));
+        assert_eq!(RoundingMode::parse("ceil"), Some(RoundingMode::Ceil));
+        assert_eq!(RoundingMode::parse("random"), None);
+    }
+
+    #[test]
+    fn test_rounding_level_parse() {
+        assert_eq!(RoundingLevel::parse("unit"), Some(RoundingLevel::Unit));
+        assert_eq!(RoundingLevel::parse("Line"), Some(RoundingLevel::Line));
+        assert_eq!(RoundingLevel::parse("total"), None);
+        assert_eq!(RoundingLevel::default(), RoundingLevel::Line);
+    }
+
+    #[test]
+    fn test_round_to_swiss_rappen() {
+        let rule = RoundingRule::for_currency("chf").unwrap();
+        assert_close(rule.round(100.42), 100.40);
+        assert_close(rule.round(100.425), 100.45);
+        assert_close(rule.round(100.475), 100.50);
+        assert_close(rule.round(100.48), 100.50);
+    }
+
+    #[test]
+    fn test_round_to_whole_units() {
+        let rule = RoundingRule::for_currency("SEK").unwrap();
+        assert_close(rule.round(99.5), 100.0);
+        assert_close(rule.round(99.49), 99.0);
+    }
+
+    #[test]
+    fn test_round_default_rule_is_cents() {
+        assert_eq!(RoundingRule::for_currency("EUR"), None);
+        assert_close(RoundingRule::default().round(123.455), 123.46);
+    }
+
+    #[test]
+    fn test_round_half_even() {
+        assert_close(
+            round_to_increment(0.125, 0.01, RoundingMode::HalfEven),
+            0.12,
+        );
+        assert_close(
+            round_to_increment(0.135, 0.01, RoundingMode::HalfEven),
+            0.14,
+        );
+        assert_close(round_to_increment(2.5, 1.0, RoundingMode::HalfEven), 2.0);
+        assert_close(round_to_increment(3.5, 1.0, RoundingMode::HalfEven), 4.0);
+    }
+
+    #[test]
+    fn test_round_tie_on_large_amount() {
+        // 123456780.0005 / 0.001 is 123456780000.49998, where floats lie
+        // 1.5e-5 apart
+        let close = |actual: f64, expected: f64| (actual - expected).abs() < 1e-6;
+        assert!(close(
+            round_to_precision(123456780.0005, 3, RoundingMode::HalfEven),
+            123456780.0
+        ));
+        assert!(close(
+            round_to_precision(123456780.0005, 3, RoundingMode::HalfUp),
+            123456780.001
+        ));
+        assert!(close(
+            round_to_precision(-123456780.0005, 3, RoundingMode::HalfUp),
+            -123456780.001
+        ));
+        assert!(close(
+            round_to_precision(123456780.0006, 3, RoundingMode::HalfEven),
+            123456780.001
+        ));
+    }
+
+    #[test]
+    fn test_round_half_up_negative_rounds_away_from_zero() {
+        assert_close(round_to_increment(-2.5, 1.0, RoundingMode::HalfUp), -3.0);
+        assert_close(
+            round_to_increment(-0.025, 0.05, RoundingMode::HalfUp),
+            -0.05,
+        );
+    }
+
+    #[test]
+    fn test_round_floor_and_ceil() {
+        assert_close(
+            round_to_increment(100.49, 0.05, RoundingMode::Floor),
+            100.45,
+        );
+        assert_close(round_to_increment(100.41, 0.05, RoundingMode::Ceil), 100.45);
+        assert_close(round_to_increment(-1.2, 1.0, RoundingMode::Floor), -2.0);
+        assert_close(round_to_increment(-1.2, 1.0, RoundingMode::Ceil), -1.0);
+    }
+
+    #[test]
+    fn test_round_exact_multiple_is_stable() {
+        // 0.3 / 0.1 is 2.9999999999999996 in binary floating point
+        assert_close(round_to_increment(0.3, 0.1, RoundingMode::Floor), 0.3);
+        assert_close(round_to_increment(0.3, 0.1, RoundingMode::Ceil), 0.3);
+    }
+
+    #[test]
+    fn test_minor_units() {
+        assert_eq!(minor_units("EUR"), 2);
+        assert_eq!(minor_units("jpy"), 0);
+        assert_eq!(minor_units("BHD"), 3);
+        assert_eq!(minor_units("KWD"), 3);
+        assert_eq!(minor_units("XYZ"), 2);
+    }
+
+    #[test]
+    fn test_round_to_precision() {
+        assert_close(round_to_precision(123.455, 2, RoundingMode::HalfUp), 123.46);
+        assert_close(round_to_precision(1234.5, 0, RoundingMode::HalfUp), 1235.0);
+        assert_close(round_to_precision(1.2345, 3, RoundingMode::HalfEven), 1.234);
+    }
+}
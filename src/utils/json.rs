@@ -0,0 +1,395 @@
+// A JSON value as the input files hold it and the output is built from.
+// Numbers keep their text, so amounts print exactly as formatted.
+#[derive(Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(value: &str) -> Json {
+        Json::String(value.to_string())
+    }
+
+    pub fn plain_number(value: f64) -> Json {
+        if value.is_finite() {
+            Json::Number(value.to_string())
+        } else {
+            Json::Null
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent + 1);
+        let closing_pad = "  ".repeat(indent);
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Json::Number(value) => out.push_str(value),
+            Json::String(value) => write_escaped(out, value),
+            Json::Array(items) if items.is_empty() => out.push_str("[]"),
+            Json::Array(items) => {
+                out.push_str("[\n");
+                for (index, item) in items.iter().enumerate() {
+                    out.push_str(&pad);
+                    item.write(out, indent + 1);
+                    out.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&closing_pad);
+                out.push(']');
+            }
+            Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Json::Object(fields) => {
+                out.push_str("{\n");
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(&pad);
+                    write_escaped(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                    out.push_str(if index + 1 < fields.len() {
+                        ",\n"
+                    } else {
+                        "\n"
+                    });
+                }
+                out.push_str(&closing_pad);
+                out.push('}');
+            }
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: input.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(format!("Unexpected character at {}", parser.position));
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+// Deep enough for any invoice file, shallow enough not to exhaust the stack
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{expected}' at {}", self.position))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') | Some('[') => {
+                self.depth += 1;
+                if self.depth > MAX_DEPTH {
+                    return Err(format!("JSON nested deeper than {MAX_DEPTH} levels"));
+                }
+                let value = if self.peek() == Some('{') {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                value
+            }
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('n') => self.literal("null", Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(format!("Unexpected character at {}", self.position)),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(format!("Unexpected character at {}", self.position));
+            }
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    // -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        let invalid = |position: usize| format!("Invalid number at {position}");
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        let leading_zero = self.peek() == Some('0');
+        let integer_digits = self.digits();
+        if integer_digits == 0 || (leading_zero && integer_digits > 1) {
+            return Err(invalid(start));
+        }
+        if self.peek() == Some('.') {
+            self.position += 1;
+            if self.digits() == 0 {
+                return Err(invalid(start));
+            }
+        }
+        if self.peek().is_some_and(|c| c == 'e' || c == 'E') {
+            self.position += 1;
+            if self.peek().is_some_and(|c| c == '+' || c == '-') {
+                self.position += 1;
+            }
+            if self.digits() == 0 {
+                return Err(invalid(start));
+            }
+        }
+        Ok(Json::Number(
+            self.chars[start..self.position].iter().collect(),
+        ))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            let c = self.peek().ok_or("Unterminated string")?;
+            self.position += 1;
+            match c {
+                '"' => return Ok(value),
+                '\\' => {
+                    let escaped = self.peek().ok_or("Unterminated string")?;
+                    self.position += 1;
+                    match escaped {
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        'b' => value.push('\u{8}'),
+                        'f' => value.push('\u{c}'),
+                        'u' => {
+                            let hex: String =
+                                self.chars.iter().skip(self.position).take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16)
+                                .map_err(|_| format!("Invalid escape at {}", self.position))?;
+                            value.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                            self.position += 4;
+                        }
+                        other => value.push(other),
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(format!("Expected ',' or ']' at {}", self.position)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(format!("Expected ',' or '}}' at {}", self.position)),
+            }
+        }
+    }
+}
+
+fn write_escaped(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_nested() {
+        let json = Json::object(vec![
+            ("name", Json::string("Taxi \"night\"\n")),
+            (
+                "values",
+                Json::Array(vec![Json::plain_number(1.5), Json::Bool(false)]),
+            ),
+            ("empty", Json::Array(vec![])),
+            ("flag", Json::Bool(true)),
+        ]);
+        assert_eq!(
+            json.render(),
+            "{\n  \"name\": \"Taxi \\\"night\\\"\\n\",\n  \"values\": [\n    1.5,\n    false\n  ],\n  \"empty\": [],\n  \"flag\": true\n}"
+        );
+    }
+
+    #[test]
+    fn test_escape_control_characters() {
+        assert_eq!(Json::string("a\u{1}b").render(), "\"a\\u0001b\"");
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let json = Json::object(vec![
+            ("name", Json::string("Taxi \"A\"\n")),
+            ("amount", Json::Number("23.80".to_string())),
+            (
+                "items",
+                Json::Array(vec![Json::Bool(true), Json::Null, Json::Array(vec![])]),
+            ),
+        ]);
+        assert_eq!(Json::parse(&json.render()).unwrap(), json);
+    }
+
+    #[test]
+    fn test_parse_get() {
+        let json = Json::parse(r#"{"gross": 119, "note": "\u00e4"}"#).unwrap();
+        assert_eq!(json.get("gross"), Some(&Json::Number("119".to_string())));
+        assert_eq!(json.get("note"), Some(&Json::string("ä")));
+        assert_eq!(json.get("net"), None);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Json::parse("{\"gross\": }").is_err());
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("\"open").is_err());
+        assert!(Json::parse("{} x").is_err());
+    }
+
+    #[test]
+    fn test_parse_depth_limit() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(64)).is_ok());
+        assert_eq!(
+            Json::parse(&nested(65)).unwrap_err(),
+            "JSON nested deeper than 64 levels"
+        );
+        // Deep enough to overflow the stack without the limit
+        assert!(Json::parse(&"[".repeat(1_000_000)).is_err());
+    }
+
+    #[test]
+    fn test_parse_number_grammar() {
+        for valid in ["0", "-1", "119.00", "1.5e3", "2E-2", "-0.5"] {
+            assert_eq!(
+                Json::parse(valid).unwrap(),
+                Json::Number(valid.to_string()),
+                "{valid}"
+            );
+        }
+        for invalid in [
+            "+1", "--1", "1-", "01", "1.", ".5", "1e", "1e+", "1.2.3", "-",
+        ] {
+            assert!(Json::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_plain_number() {
+        assert_eq!(Json::plain_number(f64::NEG_INFINITY), Json::Null);
+        assert_eq!(Json::plain_number(1.5), Json::Number("1.5".to_string()));
+    }
+}
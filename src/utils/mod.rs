@@ -1,9 +1,17 @@
+pub mod json;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use arboard::Clipboard;
 
-pub fn copy_to_clipboard(content: &str) {
+pub fn copy_to_clipboard(content: &str, html: Option<&str>) {
     match Clipboard::new() {
         Ok(mut clipboard) => {
-            if let Err(e) = clipboard.set_text(content) {
+            let result = match html {
+                Some(html) => clipboard.set_html(html, Some(content)),
+                None => clipboard.set_text(content),
+            };
+            if let Err(e) = result {
                 eprintln!("Warning: Could not copy to clipboard: {e}");
             } else {
                 println!("\n✓ Results copied to clipboard (without VAT values)");
@@ -14,3 +22,47 @@ pub fn copy_to_clipboard(content: &str) {
         }
     }
 }
+
+// The current UTC time as YYYYMMDDHHMMSSmmm
+pub fn current_timestamp() -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let millis = elapsed.subsec_millis();
+    let seconds = elapsed.as_secs();
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let shifted = days as i64 + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}{month:02}{day:02}{:02}{:02}{:02}{millis:03}",
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+// Today's UTC date as YYYY-MM-DD
+pub fn current_date() -> String {
+    let timestamp = current_timestamp();
+    format!(
+        "{}-{}-{}",
+        &timestamp[0..4],
+        &timestamp[4..6],
+        &timestamp[6..8]
+    )
+}
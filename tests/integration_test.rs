@@ -127,3 +127,652 @@ fn test_rate_flag_overrides_env_var() {
     assert!(stdout.contains("VAT Rate: 19%"));
     assert!(stdout.contains("100.00"));
 }
+
+#[test]
+fn test_reverse_charge_and_exempt_lines() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "119", "100:rc", "50:exempt"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("reverse charge (§13b UStG), self-assessed VAT: 19.00"));
+    assert!(stdout.contains("tax-exempt (§4 UStG)"));
+    assert!(stdout.contains("Self-assessed VAT owed (reverse charge): 19.00"));
+}
+
+#[test]
+fn test_compound_tax_components() {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "115.5",
+            "--tax",
+            "GST=5",
+            "--tax",
+            "QST=10:compound",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Taxes: GST 5% + QST 10% (compound)"));
+    assert!(stdout.contains("100.00"));
+    assert!(stdout.contains("5.00"));
+    assert!(stdout.contains("10.50"));
+}
+
+#[test]
+fn test_skonto_discount() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "119", "--discount", "3%"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("adjusted: gross 115.43, net 97.00, VAT correction -0.57"));
+}
+
+#[test]
+fn test_swiss_cash_rounding() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "119.52", "--rate", "8.1", "--currency", "CHF"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("119.50"));
+    assert!(stdout.contains("110.55"));
+    assert!(stdout.contains("Amounts rounded to 0.05 (half-up)"));
+}
+
+#[test]
+fn test_currency_precision() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "1100", "--rate", "10", "--currency", "JPY"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1000 "));
+    assert!(!stdout.contains("1000.00"));
+}
+
+#[test]
+fn test_currency_conversion_with_ecb_csv() {
+    let rates_file = std::env::temp_dir().join("remove-tax-test-eurofxref.csv");
+    std::fs::write(
+        &rates_file,
+        "Date, USD, JPY, \n17 October 2025, 1.1689, 176.23, \n",
+    )
+    .expect("Failed to write rates file");
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "119",
+            "--currency",
+            "USD",
+            "--convert-to",
+            "EUR",
+            "--rates",
+        ])
+        .arg(&rates_file)
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Without VAT EUR"));
+    assert!(stdout.contains("85.55"));
+    assert!(stdout.contains("16.25"));
+    assert!(
+        stdout.contains("Converted USD to EUR at 0.855505 (reference rate of 17 October 2025)")
+    );
+}
+
+#[test]
+fn test_currency_conversion_after_discount() {
+    let rates_file = std::env::temp_dir().join("remove-tax-test-eurofxref-discount.csv");
+    std::fs::write(
+        &rates_file,
+        "Date, USD, JPY, \n17 October 2025, 1.1689, 176.23, \n",
+    )
+    .expect("Failed to write rates file");
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "119",
+            "--discount",
+            "2%",
+            "--currency",
+            "USD",
+            "--convert-to",
+            "EUR",
+            "--rates",
+        ])
+        .arg(&rates_file)
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("116.62"));
+    assert!(stdout.contains("83.84"));
+    assert!(stdout.contains("15.93"));
+    assert!(!stdout.contains("85.55"));
+}
+
+#[test]
+fn test_margin_pricing_mode() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "49,99", "--margin", "30"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Margin: 30%, VAT Rate: 19%"));
+    assert!(stdout.contains("Gross price"));
+    assert!(stdout.contains("71,41"));
+    assert!(stdout.contains("84,98"));
+    assert!(stdout.contains("21,42"));
+}
+
+#[test]
+fn test_margin_scheme_mode() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "--margin-scheme", "800/1000", "500/450"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Margin scheme (§25a UStG), VAT Rate: 19%"));
+    assert!(stdout.contains("31.93"));
+    assert!(stdout.contains("168.07"));
+    assert!(stdout.contains("-50.00"));
+    assert!(stdout.contains("118.07"));
+}
+
+#[test]
+fn test_json_output() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "119", "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with('{'));
+    assert!(stdout.contains("\"vat_rate\": 19"));
+    assert!(stdout.contains("\"without_vat\": 100.00"));
+    assert!(!stdout.contains("copied to clipboard"));
+}
+
+#[test]
+fn test_solve_rate_mode() {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "--solve-rate",
+            "119/100",
+            "107,05/100",
+            "116/100",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Implied VAT rates (known: 19%, 7%, 0%"));
+    assert!(stdout.contains("7,05%"));
+    assert!(stdout.contains("no match"));
+    assert!(stdout.contains("No known rate matches item(s) 3"));
+}
+
+#[test]
+fn test_verify_mode_exit_code() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "verify", "119/100/19"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("All 1 invoice(s) match"));
+
+    let invoice_file = std::env::temp_dir().join("remove-tax-test-invoices.csv");
+    std::fs::write(&invoice_file, "gross;net;vat;rate\n107,00;100,00;19,00;7\n")
+        .expect("Failed to write invoice file");
+    let output = Command::new("cargo")
+        .args(["run", "--", "verify", "119/100/19", "--file"])
+        .arg(&invoice_file)
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("MISMATCH"));
+    assert!(stdout.contains("1 of 2 invoice(s) differ beyond the tolerance: item(s) 2"));
+}
+
+#[test]
+fn test_installments_mode() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "1000", "--installments", "3"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Installment plan: 3 payments, VAT Rate: 19%"));
+    assert!(stdout.contains("333.34"));
+    assert!(stdout.contains("280.12"));
+    assert!(stdout.contains("840.34"));
+    assert!(stdout.contains("159.66"));
+}
+
+#[test]
+fn test_allocation_mode() {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "250",
+            "--share",
+            "Sales=2",
+            "--share",
+            "Support=1",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Bill allocation: 2 shares, VAT Rate: 19%"));
+    assert!(stdout.contains("Sales"));
+    assert!(stdout.contains("166.67"));
+    assert!(stdout.contains("70.02"));
+    assert!(stdout.contains("210.08"));
+}
+
+#[test]
+fn test_tip_excluded_from_vat() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "45,90", "--tip", "4,10"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("38,57"));
+    assert!(stdout.contains("tip (not subject to VAT)"));
+    assert!(stdout.contains("Receipt total: 50,00 = net 38,57 + VAT 7,33 + tip 4,10"));
+}
+
+#[test]
+fn test_csv_line_import() {
+    let lines_file = std::env::temp_dir().join("remove-tax-test-lines.csv");
+    std::fs::write(
+        &lines_file,
+        "Text;Menge;Betrag;MwSt\nTaxi;1;23,80;19\nBuch;2;1.070,00;7\n",
+    )
+    .expect("Failed to write line item file");
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--lines"])
+        .arg(&lines_file)
+        .args(["--map", "amount=Betrag,rate=MwSt,quantity=Menge"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Text;Menge;Betrag;MwSt;Net;VAT"));
+    assert!(stdout.contains("Taxi;1;23,80;19;20,00;3,80"));
+    assert!(stdout.contains("Buch;2;1.070,00;7;2000,00;140,00"));
+}
+
+#[test]
+fn test_quantity_rounding_level() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "3x0,35", "--round-per", "unit"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Qty"));
+    assert!(stdout.contains("Unit price"));
+    assert!(stdout.contains("1,05"));
+    assert!(stdout.contains("0,87"));
+    assert!(stdout.contains("Quantities rounded per unit"));
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "3x0,35"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("0,88"));
+    assert!(stdout.contains("Quantities rounded per line"));
+}
+
+#[test]
+fn test_labels_as_first_column() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "Taxi=23,80", "Hotel=119"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Label | With VAT"));
+    assert!(stdout.contains("Taxi  | 23,80"));
+    assert!(stdout.contains("Hotel | 119.00"));
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "Taxi=23,80", "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"label\": \"Taxi\""));
+}
+
+const CII_INVOICE: &str = r#"<rsm:CrossIndustryInvoice xmlns:rsm="urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100" xmlns:ram="urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100">
+  <rsm:ExchangedDocument><ram:ID>R-7</ram:ID></rsm:ExchangedDocument>
+  <rsm:SupplyChainTradeTransaction>
+    <ram:IncludedSupplyChainTradeLineItem>
+      <ram:SpecifiedTradeProduct><ram:Name>Webhosting</ram:Name></ram:SpecifiedTradeProduct>
+      <ram:SpecifiedLineTradeDelivery><ram:BilledQuantity unitCode="MON">12</ram:BilledQuantity></ram:SpecifiedLineTradeDelivery>
+      <ram:SpecifiedLineTradeSettlement>
+        <ram:ApplicableTradeTax><ram:TypeCode>VAT</ram:TypeCode><ram:CategoryCode>S</ram:CategoryCode><ram:RateApplicablePercent>19</ram:RateApplicablePercent></ram:ApplicableTradeTax>
+        <ram:SpecifiedTradeSettlementLineMonetarySummation><ram:LineTotalAmount>120.00</ram:LineTotalAmount></ram:SpecifiedTradeSettlementLineMonetarySummation>
+      </ram:SpecifiedLineTradeSettlement>
+    </ram:IncludedSupplyChainTradeLineItem>
+    <ram:ApplicableHeaderTradeSettlement>
+      <ram:InvoiceCurrencyCode>EUR</ram:InvoiceCurrencyCode>
+      <ram:ApplicableTradeTax><ram:CalculatedAmount>VAT_AMOUNT</ram:CalculatedAmount><ram:TypeCode>VAT</ram:TypeCode><ram:BasisAmount>120.00</ram:BasisAmount><ram:CategoryCode>S</ram:CategoryCode><ram:RateApplicablePercent>19</ram:RateApplicablePercent></ram:ApplicableTradeTax>
+      <ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+        <ram:LineTotalAmount>120.00</ram:LineTotalAmount>
+        <ram:TaxBasisTotalAmount>120.00</ram:TaxBasisTotalAmount>
+        <ram:TaxTotalAmount currencyID="EUR">VAT_AMOUNT</ram:TaxTotalAmount>
+        <ram:GrandTotalAmount>142.80</ram:GrandTotalAmount>
+      </ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+    </ram:ApplicableHeaderTradeSettlement>
+  </rsm:SupplyChainTradeTransaction>
+</rsm:CrossIndustryInvoice>"#;
+
+#[test]
+fn test_cii_einvoice_verification() {
+    let invoice_file = std::env::temp_dir().join("remove-tax-test-cii.xml");
+    std::fs::write(&invoice_file, CII_INVOICE.replace("VAT_AMOUNT", "22.80"))
+        .expect("Failed to write e-invoice");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--einvoice"])
+        .arg(&invoice_file)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("E-invoice R-7 (EUR), 1 line(s)"));
+    assert!(stdout.contains("Webhosting"));
+    assert!(stdout.contains("✓ Declared tax totals match"));
+
+    let invoice_file = std::env::temp_dir().join("remove-tax-test-cii-mismatch.xml");
+    std::fs::write(&invoice_file, CII_INVOICE.replace("VAT_AMOUNT", "22.60"))
+        .expect("Failed to write e-invoice");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--format", "json", "--einvoice"])
+        .arg(&invoice_file)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"passed\": false"));
+    assert!(stdout.contains("\"expected_vat\": 22.80"));
+}
+
+#[test]
+fn test_ubl_einvoice_verification() {
+    let invoice = r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>UBL-3</cbc:ID>
+  <cbc:DocumentCurrencyCode>EUR</cbc:DocumentCurrencyCode>
+  <cac:TaxTotal>
+    <cbc:TaxAmount currencyID="EUR">3.50</cbc:TaxAmount>
+    <cac:TaxSubtotal>
+      <cbc:TaxableAmount currencyID="EUR">50.00</cbc:TaxableAmount>
+      <cbc:TaxAmount currencyID="EUR">3.50</cbc:TaxAmount>
+      <cac:TaxCategory><cbc:ID>S</cbc:ID><cbc:Percent>7</cbc:Percent></cac:TaxCategory>
+    </cac:TaxSubtotal>
+  </cac:TaxTotal>
+  <cac:LegalMonetaryTotal>
+    <cbc:TaxExclusiveAmount currencyID="EUR">50.00</cbc:TaxExclusiveAmount>
+    <cbc:TaxInclusiveAmount currencyID="EUR">53.50</cbc:TaxInclusiveAmount>
+  </cac:LegalMonetaryTotal>
+  <cac:InvoiceLine>
+    <cbc:InvoicedQuantity unitCode="H87">2</cbc:InvoicedQuantity>
+    <cbc:LineExtensionAmount currencyID="EUR">50.00</cbc:LineExtensionAmount>
+    <cac:Item>
+      <cbc:Name>Kochbuch</cbc:Name>
+      <cac:ClassifiedTaxCategory><cbc:ID>S</cbc:ID><cbc:Percent>7</cbc:Percent></cac:ClassifiedTaxCategory>
+    </cac:Item>
+  </cac:InvoiceLine>
+</Invoice>"#;
+    let invoice_file = std::env::temp_dir().join("remove-tax-test-ubl.xml");
+    std::fs::write(&invoice_file, invoice).expect("Failed to write e-invoice");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--einvoice"])
+        .arg(&invoice_file)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("E-invoice UBL-3 (EUR), 1 line(s)"));
+    assert!(stdout.contains("Kochbuch"));
+    assert!(stdout.contains("✓ Declared tax totals match"));
+}
+
+#[test]
+fn test_xrechnung_generation() {
+    let parties = r#"[invoice]
+number = "2025-042"
+date = "2025-10-19"
+buyer_reference = "04011000-12345-34"
+
+[seller]
+name = "Beispiel IT-Beratung"
+postcode = "10115"
+city = "Berlin"
+email = "rechnung@beispiel.example"
+contact = "Kai Beispiel"
+phone = "+49 30 1234567"
+vat_id = "DE123456789"
+iban = "DE02120300000000202051"
+
+[buyer]
+name = "Amt für Digitales"
+postcode = "20095"
+city = "Hamburg"
+email = "eingang@amt.example"
+"#;
+    let parties_file = std::env::temp_dir().join("remove-tax-test-parties.toml");
+    std::fs::write(&parties_file, parties).expect("Failed to write parties file");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--xrechnung"])
+        .arg(&parties_file)
+        .args(["Beratung=8x119", "Kochbuch=53,50", "--rate", "19"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let xml = String::from_utf8_lossy(&output.stdout);
+    assert!(xml.contains("<ram:BuyerReference>04011000-12345-34</ram:BuyerReference>"));
+    assert!(xml.contains("<ram:Name>Kochbuch</ram:Name>"));
+    assert!(xml.contains("<ram:GrandTotalAmount>1005.50</ram:GrandTotalAmount>"));
+
+    // The generated invoice passes the e-invoice check
+    let invoice_file = std::env::temp_dir().join("remove-tax-test-xrechnung.xml");
+    std::fs::write(&invoice_file, xml.as_bytes()).expect("Failed to write e-invoice");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--einvoice"])
+        .arg(&invoice_file)
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("E-invoice 2025-042 (EUR), 2 line(s)"));
+
+    // Reverse charge needs the buyer's VAT ID
+    let output = Command::new("cargo")
+        .args(["run", "--", "--xrechnung"])
+        .arg(&parties_file)
+        .arg("Lizenz=500:rc")
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("need seller.vat_id and buyer.vat_id"));
+}
+
+#[test]
+fn test_bank_statement_import() {
+    let statement = ":20:STARTUMSE
+:25:12030000/0000202051
+:60F:C250930EUR1234,56
+:61:2510031003D53,50NDDTNONREF
+:86:105?00SEPA-LASTSCHRIFT?20SVWZ+Ticket 4711?32DB Fernverkehr AG
+:61:2510061006D119,00NMSCNONREF
+:86:106?00KARTENZAHLUNG?20SVWZ+Buerobedarf?32Papier Schulze
+:61:2510071007C1190,00NTRFNONREF
+:86:166?00GUTSCHRIFT?20SVWZ+Rechnung 2025-042?32Kunde GmbH
+-
+";
+    let statement_file = std::env::temp_dir().join("remove-tax-test-statement.sta");
+    std::fs::write(&statement_file, statement).expect("Failed to write bank statement");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--statement"])
+        .arg(&statement_file)
+        .args(["--payee", "fernverkehr=7", "--rate", "19"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 debit(s), 1 credit(s) skipped"));
+    assert!(stdout.contains("VAT Rate: 7%"));
+    assert!(stdout.contains("2025-10-03 DB Fernverkehr AG"));
+    assert!(stdout.contains("2025-10-06 Papier Schulze"));
+    assert!(stdout.contains("Total payments: net 150,00, VAT 22,50, gross 172,50"));
+}
+
+#[test]
+fn test_rules_file_assigns_rates() {
+    let rules = "# pattern; rate or treatment; category
+/^DB (Fernverkehr|Regio)/; 7; Reisekosten
+REWE; 7; Bewirtung
+Miete; exempt; Miete
+";
+    let rules_file = std::env::temp_dir().join("remove-tax-test-rules.txt");
+    std::fs::write(&rules_file, rules).expect("Failed to write rules file");
+    let lines_file = std::env::temp_dir().join("remove-tax-test-rules-lines.csv");
+    std::fs::write(
+        &lines_file,
+        "description;amount\nDB Regio Ticket;10,70\nREWE Markt;21,40\nMiete Oktober;800,00\nTaxi;23,80\n",
+    )
+    .expect("Failed to write line item file");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--lines"])
+        .arg(&lines_file)
+        .arg("--rules")
+        .arg(&rules_file)
+        .args(["--rate", "19"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("description;amount;Net;VAT;Category"));
+    assert!(stdout.contains("DB Regio Ticket;10,70;10,00;0,70;Reisekosten"));
+    assert!(stdout.contains("Miete Oktober;800,00;800,00;0,00;Miete"));
+    assert!(stdout.contains("Taxi;23,80;20,00;3,80;"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Rules matched 3 of 4 line(s)"));
+    assert!(stderr.contains("  Taxi"));
+}
+
+#[test]
+fn test_datev_export() {
+    let accounts = "[datev]
+consultant = 1234567
+client = 10001
+
+[accounts]
+default = 4980
+contra = 1200
+
+[categories]
+Reisekosten = 4670
+";
+    let accounts_file = std::env::temp_dir().join("remove-tax-test-accounts.toml");
+    std::fs::write(&accounts_file, accounts).expect("Failed to write accounts file");
+    let lines_file = std::env::temp_dir().join("remove-tax-test-datev-lines.csv");
+    std::fs::write(
+        &lines_file,
+        "Datum;Text;Betrag;MwSt\n03.10.2025;Bahnticket;53,50;7\n06.10.2025;Papier;119,00;19\n",
+    )
+    .expect("Failed to write line item file");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--lines"])
+        .arg(&lines_file)
+        .args([
+            "--map",
+            "date=Datum,description=Text,amount=Betrag,rate=MwSt",
+        ])
+        .args(["--format", "datev", "--accounts"])
+        .arg(&accounts_file)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("\"EXTF\";700;21;\"Buchungsstapel\";13;"));
+    assert!(stdout.contains(";1234567;10001;20250101;4;20251003;20251006;"));
+    assert!(stdout.contains("53,50;\"S\";\"EUR\";;;;4980;1200;\"8\";0310;"));
+    assert!(stdout.contains("119,00;\"S\";\"EUR\";;;;4980;1200;\"9\";0610;"));
+}
+
+#[test]
+fn test_plain_text_accounting_output() {
+    let accounts_file = std::env::temp_dir().join("remove-tax-test-ledger-accounts.toml");
+    std::fs::write(
+        &accounts_file,
+        "[ledger]\nexpense = \"Expenses:Office\"\npayment = \"Assets:Bank:Checking\"\n",
+    )
+    .expect("Failed to write accounts file");
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "Papier=119",
+            "--format",
+            "hledger",
+            "--accounts",
+        ])
+        .arg(&accounts_file)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(" * Papier\n"));
+    assert!(stdout.contains("    Expenses:Office             100.00 EUR\n"));
+    assert!(stdout.contains("    Assets:VAT:Input             19.00 EUR\n"));
+    assert!(stdout.contains("    Assets:Bank:Checking       -119.00 EUR"));
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "Papier=119", "--format", "beancount"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(" open Expenses:General\n"));
+    assert!(stdout.contains(" * \"Papier\"\n"));
+}
@@ -1,6 +1,7 @@
 use remove_tax::calculator::{CalculationResult, process_numbers};
 use remove_tax::cli::{parse_arguments, print_usage};
-use remove_tax::display::{create_clipboard_content, display_results};
+use remove_tax::display::{create_clipboard_content, create_clipboard_html, display_results};
+use remove_tax::rounding::RoundingMode;
 use remove_tax::utils::copy_to_clipboard;
 
 #[test]
@@ -21,15 +22,33 @@ fn test_integration_full_flow() {
     assert_eq!(parsed.vat_rate, 19.0);
     assert_eq!(parsed.numbers.len(), 1);
 
-    let results = process_numbers(&parsed.numbers, parsed.vat_rate);
+    let results = process_numbers(
+        &parsed.numbers,
+        parsed.vat_rate,
+        parsed.precision,
+        parsed.rounding_mode,
+        parsed.rounding_level,
+    );
     assert_eq!(results.len(), 1);
 
-    let clipboard_content = create_clipboard_content(&results);
+    let clipboard_content =
+        create_clipboard_content(&results, parsed.precision, parsed.rounding_mode);
     assert_eq!(clipboard_content, "100.00");
 
     // These functions print to stdout, just ensure they don't panic
-    display_results(&results, parsed.vat_rate);
-    copy_to_clipboard(&clipboard_content);
+    display_results(
+        &results,
+        parsed.vat_rate,
+        parsed.precision,
+        parsed.rounding_mode,
+    );
+    let clipboard_html = create_clipboard_html(
+        &results,
+        parsed.vat_rate,
+        parsed.precision,
+        parsed.rounding_mode,
+    );
+    copy_to_clipboard(&clipboard_content, Some(&clipboard_html));
 }
 
 #[test]
@@ -42,15 +61,16 @@ fn test_print_usage_coverage() {
 #[test]
 fn test_copy_to_clipboard_coverage() {
     // Test clipboard function with various inputs
-    copy_to_clipboard("");
-    copy_to_clipboard("test content");
-    copy_to_clipboard("100.00\n200.00");
+    copy_to_clipboard("", None);
+    copy_to_clipboard("test content", None);
+    copy_to_clipboard("100.00\n200.00", None);
+    copy_to_clipboard("100.00", Some("<table><tr><td>100.00</td></tr></table>"));
 }
 
 #[test]
 fn test_display_results_empty() {
     let results: Vec<CalculationResult> = vec![];
-    display_results(&results, 19.0);
+    display_results(&results, 19.0, 2, RoundingMode::HalfUp);
 }
 
 #[test]
@@ -60,12 +80,14 @@ fn test_display_results_multiple() {
             with_vat: 119.0,
             without_vat: 100.0,
             uses_comma: false,
+            ..Default::default()
         },
         CalculationResult {
             with_vat: 238.0,
             without_vat: 200.0,
             uses_comma: true,
+            ..Default::default()
         },
     ];
-    display_results(&results, 19.0);
+    display_results(&results, 19.0, 2, RoundingMode::HalfUp);
 }